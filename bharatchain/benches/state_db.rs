@@ -0,0 +1,87 @@
+// Account state throughput with a million accounts. Run with `cargo bench`.
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::state_db::StateDB;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const ACCOUNTS: u64 = 1_000_000;
+const BLOCK_TRANSFERS: u64 = 1_000;
+
+fn address(index: u64) -> Address {
+    let mut hash = [0; 32];
+    hash[..8].copy_from_slice(&index.to_le_bytes());
+    Address::from_hash(hash)
+}
+
+// Every account funded well beyond what the benchmarks can spend
+fn populated_state() -> StateDB {
+    (0..ACCOUNTS)
+        .map(|index| Account {
+            balance: Amount::from_base_units(u64::MAX / (2 * ACCOUNTS)),
+            ..Account::new(address(index))
+        })
+        .collect()
+}
+
+// Transfers between random existing accounts. Signatures are not checked by
+// `execute`, so they are left out to measure the state alone.
+fn random_transfers(rng: &mut StdRng, count: u64) -> Vec<BlockTransaction> {
+    (0..count)
+        .map(|_| {
+            let sender = address(rng.gen_range(0..ACCOUNTS));
+            let receiver = address(rng.gen_range(0..ACCOUNTS));
+            BlockTransaction::new(sender, receiver, Amount::from_base_units(1), 0, 1)
+        })
+        .collect()
+}
+
+fn state_db(c: &mut Criterion) {
+    let mut state = populated_state();
+    let mut rng = StdRng::seed_from_u64(1947);
+
+    let mut group = c.benchmark_group("state_db_1m_accounts");
+
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("get", |b| {
+        b.iter_batched(
+            || address(rng.gen_range(0..ACCOUNTS)),
+            |address| black_box(state.get(&address).map(|account| account.balance)),
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("transfer", |b| {
+        b.iter_batched(
+            || random_transfers(&mut rng, 1).remove(0),
+            |tx| tx.execute(&mut state).expect("transfer applies"),
+            BatchSize::SmallInput,
+        )
+    });
+
+    // A block's worth of transfers under a snapshot: apply, collect the undo
+    // record, then revert, as when preparing a block template
+    group.throughput(Throughput::Elements(BLOCK_TRANSFERS));
+    group.bench_function("block_snapshot_revert", |b| {
+        b.iter_batched(
+            || random_transfers(&mut rng, BLOCK_TRANSFERS),
+            |txns| {
+                let snapshot = state.snapshot();
+                for tx in &txns {
+                    tx.execute(&mut state).expect("transfer applies");
+                }
+                black_box(state.changes_since(snapshot));
+                state.revert(snapshot);
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, state_db);
+criterion_main!(benches);
@@ -1,16 +1,29 @@
 use hex::decode;
 use secp256k1::{PublicKey, Secp256k1, SecretKey};
-use sha2::{Digest as _, Sha256};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+use super::address::Address;
+use super::amount::{Amount, AmountError};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Account {
-    pub address: String,
-    pub balance: f64,
+    pub address: Address,
+    pub balance: Amount,
+    pub nonce: u64, // Number of transactions sent from this account; the next expected nonce
 }
 
 impl Account {
+    // Empty account for an address that has not been seen before
+    pub fn new(address: Address) -> Self {
+        Account {
+            address,
+            balance: Amount::ZERO,
+            nonce: 0,
+        }
+    }
+
     // Constructor to create a new account with a given address and initial balance.
-    pub fn from_secret_key(secret_key: &str, balance: f64) -> Result<Self, String> {
+    pub fn from_secret_key(secret_key: &str, balance: Amount) -> Result<Self, String> {
         // Create a new Secp256k1 context
         let secret_key_bytes = decode(secret_key).map_err(|e| format!("Invalid hex: {}", e))?;
 
@@ -28,26 +41,24 @@ impl Account {
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
 
         // Derive the address from the public key (hash of public key)
-        let pub_key_bytes = public_key.serialize_uncompressed();
-        let address = format!("{:x}", Sha256::digest(pub_key_bytes.as_ref()));
-
-        print!("Account address: {} \n *********** ==== *********", address); // Use the public key hash as address
+        let address = Address::from_public_key(&public_key);
 
-        Ok(Account { address, balance })
+        Ok(Account {
+            address,
+            balance,
+            nonce: 0,
+        })
     }
 
     // Method to debit the account (subtract balance).
-    pub fn debit(&mut self, amount: f64) -> Result<(), String> {
-        if self.balance >= amount {
-            self.balance -= amount;
-            Ok(())
-        } else {
-            Err("Insufficient funds".to_string())
-        }
+    pub fn debit(&mut self, amount: Amount) -> Result<(), AmountError> {
+        self.balance = self.balance.checked_sub(amount)?;
+        Ok(())
     }
 
     // Method to credit the account (add balance).
-    pub fn credit(&mut self, amount: f64) {
-        self.balance += amount;
+    pub fn credit(&mut self, amount: Amount) -> Result<(), AmountError> {
+        self.balance = self.balance.checked_add(amount)?;
+        Ok(())
     }
 }
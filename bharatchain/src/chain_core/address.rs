@@ -0,0 +1,188 @@
+use bech32::primitives::decode::UncheckedHrpstring;
+use bech32::{Bech32m, Hrp};
+use secp256k1::PublicKey;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use sha2::{Digest as _, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+use super::encoding::{Decode, Decoder, Encode, Encoder};
+
+// Human-readable prefix of every address: "bharat1..."
+pub const ADDRESS_HRP: Hrp = Hrp::parse_unchecked("bharat");
+
+// Version of addresses derived from a public key as below; a new derivation
+// would get a new version so both kinds stay distinguishable
+pub const ADDRESS_VERSION: u8 = 0;
+
+// Bytes in the public key hash an address commits to
+pub const ADDRESS_HASH_LEN: usize = 32;
+
+// Errors raised when parsing or decoding an address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressError {
+    InvalidEncoding(String), // Not bech32m, or the checksum does not match
+    WrongPrefix(String),
+    UnsupportedVersion(u8),
+    InvalidLength(usize),
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AddressError::InvalidEncoding(e) => write!(f, "Invalid address: {}", e),
+            AddressError::WrongPrefix(prefix) => write!(
+                f,
+                "Address has prefix '{}', expected '{}'",
+                prefix, ADDRESS_HRP
+            ),
+            AddressError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported address version {}", version)
+            }
+            AddressError::InvalidLength(len) => {
+                write!(
+                    f,
+                    "Address holds {} bytes, expected {}",
+                    len, ADDRESS_HASH_LEN
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+// Account address: a version and the SHA-256 hash of the owner's uncompressed
+// public key. Its text form is bech32m with the "bharat" prefix, so the
+// checksum catches any single typo and most others before funds are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address {
+    version: u8,
+    hash: [u8; ADDRESS_HASH_LEN],
+}
+
+impl Address {
+    // All-zero address; no known key hashes to it, so it can never sign. Used
+    // as the sender of coinbase transactions.
+    pub const ZERO: Address = Address {
+        version: ADDRESS_VERSION,
+        hash: [0; ADDRESS_HASH_LEN],
+    };
+
+    pub fn from_public_key(public_key: &PublicKey) -> Self {
+        Address::from_hash(Sha256::digest(public_key.serialize_uncompressed()).into())
+    }
+
+    pub const fn from_hash(hash: [u8; ADDRESS_HASH_LEN]) -> Self {
+        Address {
+            version: ADDRESS_VERSION,
+            hash,
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn hash(&self) -> &[u8; ADDRESS_HASH_LEN] {
+        &self.hash
+    }
+
+    // Whether `s` parses as an address, checksum included
+    pub fn is_valid(s: &str) -> bool {
+        s.parse::<Address>().is_ok()
+    }
+
+    fn from_parts(version: u8, hash: &[u8]) -> Result<Self, AddressError> {
+        if version != ADDRESS_VERSION {
+            return Err(AddressError::UnsupportedVersion(version));
+        }
+        let hash = hash
+            .try_into()
+            .map_err(|_| AddressError::InvalidLength(hash.len()))?;
+        Ok(Address { version, hash })
+    }
+}
+
+impl fmt::Display for Address {
+    // Lowercase bech32m of the version byte followed by the hash
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut data = Vec::with_capacity(1 + ADDRESS_HASH_LEN);
+        data.push(self.version);
+        data.extend_from_slice(&self.hash);
+        bech32::encode_lower_to_fmt::<Bech32m, _>(f, ADDRESS_HRP, &data).map_err(|_| fmt::Error)
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    // Accepts the all-lowercase or all-uppercase form; mixed case is rejected
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unchecked = UncheckedHrpstring::new(s.trim()).map_err(|_| {
+            AddressError::InvalidEncoding("not a bech32 string, or mixed case".to_string())
+        })?;
+        if unchecked.hrp() != ADDRESS_HRP {
+            return Err(AddressError::WrongPrefix(unchecked.hrp().to_string()));
+        }
+        let checked = unchecked
+            .validate_and_remove_checksum::<Bech32m>()
+            .map_err(|_| AddressError::InvalidEncoding("checksum does not match".to_string()))?;
+
+        let data: Vec<u8> = checked.byte_iter().collect();
+        let (version, hash) = data.split_first().ok_or(AddressError::InvalidLength(0))?;
+        let address = Address::from_parts(*version, hash)?;
+
+        // Unused padding bits must be zero, so each address has one spelling
+        if !address.to_string().eq_ignore_ascii_case(s.trim()) {
+            return Err(AddressError::InvalidEncoding(
+                "non-zero padding".to_string(),
+            ));
+        }
+        Ok(address)
+    }
+}
+
+// Serialized in its bech32m text form
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AddressVisitor;
+
+        impl Visitor<'_> for AddressVisitor {
+            type Value = Address;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a bech32m address starting with {}1", ADDRESS_HRP)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Address, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(AddressVisitor)
+    }
+}
+
+// Encoded as the version byte followed by the fixed-size hash
+impl Encode for Address {
+    fn encode_to(&self, out: &mut Encoder) {
+        out.put_u8(self.version);
+        out.put_array(&self.hash);
+    }
+}
+
+impl Decode for Address {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        let version = input.get_u8()?;
+        let hash = input.get_array::<ADDRESS_HASH_LEN>()?;
+        Address::from_parts(version, &hash).map_err(|e| e.to_string())
+    }
+}
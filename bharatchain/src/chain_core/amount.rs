@@ -0,0 +1,165 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Number of decimal places an amount can carry
+pub const DECIMALS: u32 = 8;
+
+// Base units in one whole coin (10^DECIMALS)
+pub const UNITS_PER_COIN: u64 = 100_000_000;
+
+// Errors raised by amount arithmetic and parsing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    Overflow,
+    InsufficientFunds,
+    Negative,
+    TooManyDecimals,
+    InvalidFormat(String),
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::Overflow => write!(f, "Amount overflow"),
+            AmountError::InsufficientFunds => write!(f, "Insufficient funds"),
+            AmountError::Negative => write!(f, "Amount cannot be negative"),
+            AmountError::TooManyDecimals => {
+                write!(f, "Amount has more than {} decimal places", DECIMALS)
+            }
+            AmountError::InvalidFormat(s) => write!(f, "Invalid amount: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+// A non-negative token amount stored as an integer number of base units, so
+// balances are exact and identical on every platform.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const MAX: Amount = Amount(u64::MAX);
+
+    pub const fn from_base_units(units: u64) -> Self {
+        Amount(units)
+    }
+
+    // Whole coins, failing if the result does not fit in base units
+    pub fn from_coins(coins: u64) -> Result<Self, AmountError> {
+        coins
+            .checked_mul(UNITS_PER_COIN)
+            .map(Amount)
+            .ok_or(AmountError::Overflow)
+    }
+
+    pub const fn base_units(&self) -> u64 {
+        self.0
+    }
+
+    pub const fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: Amount) -> Result<Amount, AmountError> {
+        self.0
+            .checked_add(other.0)
+            .map(Amount)
+            .ok_or(AmountError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Result<Amount, AmountError> {
+        self.0
+            .checked_sub(other.0)
+            .map(Amount)
+            .ok_or(AmountError::InsufficientFunds)
+    }
+}
+
+impl fmt::Display for Amount {
+    // Decimal form with trailing fractional zeros trimmed, e.g. "300.45" or "50"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let whole = self.0 / UNITS_PER_COIN;
+        let fraction = self.0 % UNITS_PER_COIN;
+        if fraction == 0 {
+            return write!(f, "{}", whole);
+        }
+
+        let digits = format!("{:0width$}", fraction, width = DECIMALS as usize);
+        write!(f, "{}.{}", whole, digits.trim_end_matches('0'))
+    }
+}
+
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    // Parse a decimal string such as "300.45" into base units
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with('-') {
+            return Err(AmountError::Negative);
+        }
+
+        let (whole, fraction) = match s.split_once('.') {
+            Some((whole, fraction)) => (whole, fraction),
+            None => (s, ""),
+        };
+
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+            return Err(AmountError::InvalidFormat(s.to_string()));
+        }
+        if fraction.len() > DECIMALS as usize {
+            return Err(AmountError::TooManyDecimals);
+        }
+
+        let whole_units = if whole.is_empty() {
+            Amount::ZERO
+        } else {
+            let coins = whole.parse::<u64>().map_err(|_| AmountError::Overflow)?;
+            Amount::from_coins(coins)?
+        };
+
+        let fraction_units = if fraction.is_empty() {
+            0
+        } else {
+            let scale = 10u64.pow(DECIMALS - fraction.len() as u32);
+            fraction
+                .parse::<u64>()
+                .map_err(|_| AmountError::InvalidFormat(s.to_string()))?
+                * scale
+        };
+
+        whole_units.checked_add(Amount(fraction_units))
+    }
+}
+
+// Amounts are serialized as decimal strings so JSON consumers never see floats
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AmountVisitor;
+
+        impl Visitor<'_> for AmountVisitor {
+            type Value = Amount;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a decimal amount string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Amount, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(AmountVisitor)
+    }
+}
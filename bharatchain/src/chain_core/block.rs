@@ -1,19 +1,98 @@
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::fmt::Debug;
 
-use super::account::Account;
+use super::amount::{Amount, AmountError};
+use super::difficulty::{CompactTarget, MAX_TARGET_BITS, U256};
+use super::encoding::{self, Decode, Decoder, Encode, Encoder};
 use super::helper::get_current_timestamp;
-use super::transaction::{BlockTransaction, MerkleTree};
+use super::merkle_tree::MerklePatriciaTree;
+use super::reward::RewardSchedule;
+use super::state_db::StateDB;
+use super::transaction::{BlockTransaction, MerkleProof, MerkleTree};
 
-#[derive(Debug)]
-pub struct DataBlock {
+// Blocks using the original Merkle construction (duplicated odd leaf, no domain separation)
+pub const BLOCK_VERSION_LEGACY: u32 = 1;
+
+// Version of newly created blocks
+pub const BLOCK_VERSION: u32 = 2;
+
+// Outcome of a single transaction when a block is applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReceiptStatus {
+    Applied,
+    Failed(String), // Validation or execution error that aborted the block
+    Skipped,        // Not executed because an earlier transaction in the block failed
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionReceipt {
+    pub tx_hash: String,
+    pub status: ReceiptStatus,
+}
+
+// Fields committed to by the block hash. A header serializes and hashes on its
+// own, so headers can be exchanged and checked without the transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
     pub block_number: u64,
-    pub previous_hash: String,
-    pub merkle_root: String,
-    pub block_hash: String,
-    pub transactions: Vec<BlockTransaction>,
+    pub previous_hash: String, // Hash of the parent block
+    pub merkle_root: String,   // Root of the body's transaction hashes
+    pub state_root: String,    // Root of the account state trie after applying this block
     pub timestamp: u64,
-    pub nounce: u64,
+    pub bits: CompactTarget, // Proof-of-work target the block hash must not exceed
+    pub nounce: u32,
+    pub extra_nonce: u64, // Rolled by the miner once every 32-bit nonce has been tried
+}
+
+impl BlockHeader {
+    // Block hash: SHA-256 of the canonical encoding of the header
+    pub fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(encoding::encode(self)))
+    }
+
+    // Whether `hash`, read as a 256-bit number, is at most the target
+    pub fn meets_target(&self, hash: &str) -> bool {
+        match (U256::from_str_radix(hash, 16), self.bits.to_target()) {
+            (Ok(hash), Ok(target)) => hash <= target,
+            _ => false,
+        }
+    }
+
+    // Expected number of hashes it took to mine this header
+    pub fn work(&self) -> U256 {
+        self.bits.work()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockBody {
+    pub transactions: Vec<BlockTransaction>,
+}
+
+impl BlockBody {
+    // Merkle root of the transaction hashes, built the way `version` blocks build it
+    pub fn merkle_root(&self, version: u32) -> String {
+        let transaction_hashes = self
+            .transactions
+            .iter()
+            .map(|tx| tx.compute_hash())
+            .collect();
+        MerkleTree::with_version(version).build_merkle_tree(transaction_hashes)
+    }
+}
+
+// A header, its cached hash and the body. Both parts are flattened in JSON, so
+// a block serializes as one object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataBlock {
+    #[serde(flatten)]
+    pub header: BlockHeader,
+    pub block_hash: String,
+    #[serde(flatten)]
+    pub body: BlockBody,
 }
 
 impl DataBlock {
@@ -22,71 +101,249 @@ impl DataBlock {
         previous_hash: String,
         transactions: Vec<BlockTransaction>,
     ) -> Self {
-        // Create the Merkle tree and calculate the Merkle root
-        let mut merkle_tree = MerkleTree::new();
-        merkle_tree.process_transactions(transactions.clone());
-
-        let mut block = DataBlock {
+        let body = BlockBody { transactions };
+        let header = BlockHeader {
+            version: BLOCK_VERSION,
             block_number,
             previous_hash,
-            merkle_root: merkle_tree.get_merkle_root(),
-            transactions,
+            merkle_root: body.merkle_root(BLOCK_VERSION),
+            state_root: MerklePatriciaTree::empty_root(),
             timestamp: get_current_timestamp(),
+            bits: MAX_TARGET_BITS,
             nounce: 0,
-            block_hash: String::new(),
+            extra_nonce: 0,
         };
 
-        block.block_hash = block.calculate_hash();
-        block
+        DataBlock::from_parts(header, body)
     }
 
-    // Calculate the hash of the block (with nonce and Merkle root)
+    // Reassemble a block from a header and body received separately
+    pub fn from_parts(header: BlockHeader, body: BlockBody) -> Self {
+        DataBlock {
+            block_hash: header.hash(),
+            header,
+            body,
+        }
+    }
+
+    // Recompute the block hash from the header
     pub fn calculate_hash(&self) -> String {
-        let block_data = format!(
-            "{}{}{}{}{}{}", // Index, Timestamp, Transactions (Merkle root), Previous hash, Nonce
-            self.block_number,
-            self.timestamp,
-            self.merkle_root,
-            self.previous_hash,
-            self.nounce,
-            self.timestamp, // Adding a timestamp to make it unique
-        );
-
-        let mut hasher = Sha256::new();
-        hasher.update(block_data);
-        let result = hasher.finalize();
-        format!("{:x}", result)
-    }
-
-    // Perform proof-of-work to find a valid hash
-    pub fn mine_block(&mut self, difficulty: usize) {
-        let target = vec!['0'; difficulty]; // "difficulty" number of leading zeros
-        while &self.block_hash[..difficulty] != target.iter().collect::<String>() {
-            self.nounce += 1;
-            self.block_hash = self.calculate_hash();
+        self.header.hash()
+    }
+
+    // Check that the version is one this node knows and that `merkle_root`
+    // commits to the transactions under that version's construction. Legacy
+    // blocks are still accepted, but their duplicated odd leaf gives a list with
+    // its last transactions repeated the same root (CVE-2012-2459), so no block
+    // may contain the same transaction twice.
+    pub fn check_merkle_root(&self) -> Result<(), String> {
+        let header = &self.header;
+        if header.version != BLOCK_VERSION && header.version != BLOCK_VERSION_LEGACY {
+            return Err(format!(
+                "Block {} has unknown version {}",
+                header.block_number, header.version
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = self
+            .body
+            .transactions
+            .iter()
+            .map(|tx| tx.compute_hash())
+            .find(|hash| !seen.insert(hash.clone()))
+        {
+            return Err(format!(
+                "Block {} contains transaction {} twice",
+                header.block_number, duplicate
+            ));
+        }
+
+        let recalculated_merkle_root = self.body.merkle_root(header.version);
+        if header.merkle_root != recalculated_merkle_root {
+            return Err(format!(
+                "Block {} has Merkle root {}, calculated {}",
+                header.block_number, header.merkle_root, recalculated_merkle_root
+            ));
+        }
+        Ok(())
+    }
+
+    // Inclusion proof for one of this block's transactions, checkable against
+    // `merkle_root` with `verify_merkle_proof`
+    pub fn transaction_proof(&self, transaction_hash: &str) -> Option<MerkleProof> {
+        let mut merkle_tree = MerkleTree::with_version(self.header.version);
+        merkle_tree.process_transactions(self.body.transactions.clone());
+        merkle_tree.get_proof(transaction_hash)
+    }
+
+    // Commit the block to the post-execution account state
+    pub fn set_state_root(&mut self, state_root: String) {
+        self.header.state_root = state_root;
+        self.block_hash = self.header.hash();
+    }
+
+    // Whether the block hash, read as a 256-bit number, is at most the target
+    pub fn meets_target(&self) -> bool {
+        self.header.meets_target(&self.block_hash)
+    }
+
+    // Expected number of hashes it took to mine this block
+    pub fn work(&self) -> U256 {
+        self.header.work()
+    }
+
+    // Most a coinbase in this block may claim: the subsidy plus all fees
+    pub fn coinbase_allowance(&self, rewards: &RewardSchedule) -> Result<Amount, AmountError> {
+        self.body
+            .transactions
+            .iter()
+            .filter(|tx| !tx.is_coinbase())
+            .try_fold(rewards.subsidy_at(self.header.block_number), |total, tx| {
+                total.checked_add(tx.fee)
+            })
+    }
+
+    // Apply the transactions in the block to the account state of the chain
+    // `chain_id`. Transactions run in order, so each one sees the effects of the
+    // ones before it. The changes are only kept if every transaction succeeds;
+    // otherwise the state is reverted and the receipts say which transaction
+    // failed and why.
+    pub fn apply_transactions(
+        &self,
+        accounts: &mut StateDB,
+        rewards: &RewardSchedule,
+        chain_id: u64,
+    ) -> Result<Vec<TransactionReceipt>, Vec<TransactionReceipt>> {
+        let snapshot = accounts.snapshot();
+        let mut receipts = Vec::with_capacity(self.body.transactions.len());
+        let mut failed = false;
+
+        for (index, tx) in self.body.transactions.iter().enumerate() {
+            let status = if failed {
+                ReceiptStatus::Skipped
+            } else {
+                // Transactions signed for another chain are never applied
+                let result = if tx.chain_id != chain_id {
+                    Err(format!(
+                        "Transaction is for chain {}, expected {}",
+                        tx.chain_id, chain_id
+                    ))
+                } else if tx.is_coinbase() {
+                    self.apply_coinbase(index, tx, accounts, rewards)
+                } else {
+                    tx.validate(accounts).and_then(|_| tx.execute(accounts))
+                };
+
+                match result {
+                    Ok(()) => ReceiptStatus::Applied,
+                    Err(e) => {
+                        failed = true;
+                        ReceiptStatus::Failed(e)
+                    }
+                }
+            };
+
+            receipts.push(TransactionReceipt {
+                tx_hash: tx.compute_hash(),
+                status,
+            });
         }
-        println!(
-            "Block mined! Nonce: {}, Hash: {}",
-            self.nounce, self.block_hash
-        );
-    }
-
-    // Apply the transactions in the block to the account state
-    pub fn apply_transactions(&self, accounts: &mut Vec<Account>) -> bool {
-        for tx in &self.transactions {
-            if !tx.is_valid(accounts) {
-                return false; // Transaction failed validation (insufficient funds)
-            }
+
+        if failed {
+            accounts.revert(snapshot);
+            return Err(receipts);
+        }
+
+        accounts.commit(snapshot);
+        Ok(receipts)
+    }
+
+    // The coinbase must come first and may not claim more than the subsidy plus fees
+    fn apply_coinbase(
+        &self,
+        index: usize,
+        tx: &BlockTransaction,
+        accounts: &mut StateDB,
+        rewards: &RewardSchedule,
+    ) -> Result<(), String> {
+        if index != 0 {
+            return Err("Coinbase must be the first transaction in the block".to_string());
         }
 
-        // Apply the transactions
-        for tx in &self.transactions {
-            match tx.execute(accounts) {
-                Ok(_) => return true,
-                Err(_) => return false,
-            }
+        let allowance = self
+            .coinbase_allowance(rewards)
+            .map_err(|e| e.to_string())?;
+        if tx.amount > allowance {
+            return Err(format!(
+                "Coinbase claims {} but the block allows at most {}",
+                tx.amount, allowance
+            ));
         }
 
-        true
+        tx.execute_coinbase(accounts)
+    }
+}
+
+impl Encode for BlockHeader {
+    fn encode_to(&self, out: &mut Encoder) {
+        out.put_u32(self.version);
+        out.put_u64(self.block_number);
+        out.put_str(&self.previous_hash);
+        out.put_str(&self.merkle_root);
+        out.put_str(&self.state_root);
+        out.put_u64(self.timestamp);
+        out.put_u32(self.bits.bits());
+        out.put_u32(self.nounce);
+        out.put_u64(self.extra_nonce);
+    }
+}
+
+impl Decode for BlockHeader {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        Ok(BlockHeader {
+            version: input.get_u32()?,
+            block_number: input.get_u64()?,
+            previous_hash: input.get_str()?,
+            merkle_root: input.get_str()?,
+            state_root: input.get_str()?,
+            timestamp: input.get_u64()?,
+            bits: CompactTarget::from_bits(input.get_u32()?),
+            nounce: input.get_u32()?,
+            extra_nonce: input.get_u64()?,
+        })
+    }
+}
+
+impl Encode for BlockBody {
+    fn encode_to(&self, out: &mut Encoder) {
+        out.put_list(&self.transactions);
+    }
+}
+
+impl Decode for BlockBody {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        Ok(BlockBody {
+            transactions: input.get_list()?,
+        })
+    }
+}
+
+// Full block: the header, the block hash and the body
+impl Encode for DataBlock {
+    fn encode_to(&self, out: &mut Encoder) {
+        self.header.encode_to(out);
+        out.put_str(&self.block_hash);
+        self.body.encode_to(out);
+    }
+}
+
+impl Decode for DataBlock {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        Ok(DataBlock {
+            header: BlockHeader::decode_from(input)?,
+            block_hash: input.get_str()?,
+            body: BlockBody::decode_from(input)?,
+        })
     }
 }
@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+
+use super::block::DataBlock;
+use super::difficulty::U256;
+
+// A block known to the node but not on the main chain
+#[derive(Debug, Clone)]
+pub struct SideBlock {
+    pub block: DataBlock,
+    pub total_work: U256, // Work of the branch from genesis up to and including this block
+    pub stored: bool,     // Already written to the block store, having been on the main chain
+}
+
+// Blocks off the main chain: competing branches that may overtake it, and
+// blocks disconnected by a reorganization. Every block's parent is either in
+// the tree or on the main chain. Blocks that failed validation are remembered,
+// so they and anything built on them are rejected without being checked again.
+#[derive(Debug, Default)]
+pub struct BlockTree {
+    blocks: HashMap<String, SideBlock>, // Block hash -> block
+    invalid: HashSet<String>,
+}
+
+impl BlockTree {
+    pub fn new() -> Self {
+        BlockTree::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn contains(&self, block_hash: &str) -> bool {
+        self.blocks.contains_key(block_hash)
+    }
+
+    pub fn get(&self, block_hash: &str) -> Option<&SideBlock> {
+        self.blocks.get(block_hash)
+    }
+
+    pub fn insert(&mut self, block: DataBlock, total_work: U256, stored: bool) {
+        self.blocks.insert(
+            block.block_hash.clone(),
+            SideBlock {
+                block,
+                total_work,
+                stored,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, block_hash: &str) -> Option<SideBlock> {
+        self.blocks.remove(block_hash)
+    }
+
+    pub fn is_invalid(&self, block_hash: &str) -> bool {
+        self.invalid.contains(block_hash)
+    }
+
+    // Mark a block invalid and drop every descendant of it from the tree
+    pub fn invalidate(&mut self, block_hash: &str) {
+        self.blocks.remove(block_hash);
+        self.invalid.insert(block_hash.to_string());
+
+        loop {
+            let orphaned: Vec<String> = self
+                .blocks
+                .iter()
+                .filter(|(_, side)| self.invalid.contains(&side.block.header.previous_hash))
+                .map(|(hash, _)| hash.clone())
+                .collect();
+            if orphaned.is_empty() {
+                break;
+            }
+            for hash in orphaned {
+                self.blocks.remove(&hash);
+                self.invalid.insert(hash);
+            }
+        }
+    }
+
+    // Hashes of the side branch ending at `block_hash`, from the block whose
+    // parent is on the main chain up to `block_hash` itself
+    pub fn branch(&self, block_hash: &str) -> Vec<String> {
+        let mut branch = vec![];
+        let mut hash = block_hash;
+        while let Some(side) = self.blocks.get(hash) {
+            branch.push(hash.to_string());
+            hash = &side.block.header.previous_hash;
+        }
+        branch.reverse();
+        branch
+    }
+}
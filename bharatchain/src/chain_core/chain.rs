@@ -1,51 +1,536 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
 use super::account::Account;
-use super::block::DataBlock;
-use super::transaction::{BlockTransaction, MerkleTree};
-use rand::rngs::OsRng;
-use secp256k1::SecretKey;
+use super::address::Address;
+use super::amount::Amount;
+use super::block::{BlockHeader, DataBlock, ReceiptStatus, TransactionReceipt};
+use super::block_tree::BlockTree;
+use super::difficulty::{CompactTarget, U256};
+use super::genesis::GenesisConfig;
+use super::helper::get_current_timestamp;
+use super::mempool::Mempool;
+use super::merkle_tree::MerklePatriciaTree;
+use super::miner::{CancelToken, Miner, MiningOutcome};
+use super::reward::RewardSchedule;
+use super::state_db::StateDB;
+use super::storage::BlockStore;
+use super::transaction::BlockTransaction;
+
+// Chain id used by the default network
+pub const DEFAULT_CHAIN_ID: u64 = 1;
+
+// A block's timestamp must be later than the median of this many blocks before it
+pub const MEDIAN_TIME_SPAN: u64 = 11;
+
+// Seconds a block's timestamp may be ahead of the local clock
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 2 * 60 * 60;
+
+// Chain handle shared between the node's services (RPC, networking, mining)
+pub type SharedChain = Arc<Mutex<BharatChain>>;
+
+// Accounts a main-chain block changed, as they were before it (`None` for
+// accounts it created), so the block can be disconnected again
+type StateUndo = Vec<(Address, Option<Account>)>;
+
+// What happened to a block passed to `submit_block`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockStatus {
+    Connected(Vec<TransactionReceipt>), // Extended the main chain
+    Reorganized(Reorg), // Completed a heavier side branch, which became the main chain
+    SideBranch,         // Stored on a side branch with no more work than the main chain
+}
+
+// A switch of the main chain to a heavier branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reorg {
+    pub fork_point: u64,                    // Last block both branches share
+    pub disconnected: Vec<String>,          // Hashes of the blocks removed, oldest first
+    pub connected: Vec<String>,             // Hashes of the blocks added, oldest first
+    pub returned_transactions: Vec<String>, // Orphaned transactions put back in the mempool
+}
+
+// Changes of the main chain, sent to every subscriber
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainEvent {
+    BlockConnected {
+        block_number: u64,
+        block_hash: String,
+    },
+    Reorganized(Reorg),
+    // A transaction was added to the mempool
+    TransactionAccepted {
+        tx_hash: String,
+    },
+}
 
 #[derive(Debug)]
 pub struct BharatChain {
     pub chain: Vec<DataBlock>,
-    pub difficulty: usize,
-    pub accounts: Vec<Account>, // Track the state of all accounts
+    pub chain_id: u64,
+    pub accounts: StateDB,              // Track the state of all accounts
+    pub state: MerklePatriciaTree,      // Account state trie keyed by address
+    pub mempool: Mempool,               // Validated transactions waiting to be mined
+    pub rewards: RewardSchedule,        // Block subsidy and halving schedule
+    pub miner_address: Option<Address>, // Credited with the coinbase of locally mined blocks
+    pub genesis: GenesisConfig,         // Configuration the genesis block was built from
+    pub miner: Miner,                   // Proof-of-work worker used by `add_block`
+    mining: CancelToken, // Cancelled when the tip changes, stopping stale mining jobs
+    store: Option<BlockStore>, // On-disk log of every block that has been on the main chain
+    tree: BlockTree,     // Known blocks off the main chain
+    chain_work: Vec<U256>, // Cumulative work of each main-chain block
+    undo: Vec<StateUndo>, // Account changes made by each main-chain block
+    events: Vec<Sender<ChainEvent>>, // Subscribers to chain events
 }
 
 impl BharatChain {
-    // Create a new blockchain with a genesis block
-    pub fn new(difficulty: usize) -> Self {
-        let genesis_block_prvious_hash =
-            String::from("27d9e52ddb66a5e2d1adeac33afcc9a1cf64847064760fa49cdf4eeb110c4953");
+    // Create a new blockchain whose genesis block and initial accounts come from `genesis`
+    pub fn new(genesis: GenesisConfig) -> Result<Self, String> {
+        genesis.validate()?;
 
-        let genesis_block: DataBlock = DataBlock::new(0, genesis_block_prvious_hash, vec![]);
-        let key1 = SecretKey::from_keypair(keypair);
-        let alice = Account::from_secret_key(&, 1000.00);
-        let bob = Account::from_secret_key(&hex::encode("Bob"), 500.00);
+        let accounts = genesis.accounts();
+        let mut state = MerklePatriciaTree::new();
+        let state_root = update_state(&mut state, &accounts, &created(&accounts));
+        let genesis_block = genesis.genesis_block(state_root);
+        let genesis_work = genesis_block.work();
 
-        match alice {
-            Ok(_) => println!("Alice:"),
-            Err(e) => println!("Error : {}", e),
+        Ok(BharatChain {
+            chain: vec![genesis_block],
+            chain_id: genesis.chain_id,
+            accounts,
+            state,
+            mempool: Mempool::default(),
+            rewards: genesis.rewards.clone(),
+            miner_address: None,
+            genesis,
+            miner: Miner::default(),
+            mining: CancelToken::new(),
+            store: None,
+            tree: BlockTree::new(),
+            chain_work: vec![genesis_work],
+            undo: vec![vec![]],
+            events: vec![],
+        })
+    }
+
+    // Open a persistent blockchain stored in `data_dir`. A fresh store is seeded
+    // with the genesis block; an existing one must start with the same genesis
+    // block and is replayed, reorganizations included, to rebuild the accounts.
+    pub fn open<P: AsRef<Path>>(data_dir: P, genesis: GenesisConfig) -> Result<Self, String> {
+        let mut store =
+            BlockStore::open(data_dir).map_err(|e| format!("Failed to open block store: {}", e))?;
+        let mut blockchain = BharatChain::new(genesis)?;
+
+        if store.is_empty() {
+            store
+                .append(&blockchain.chain[0])
+                .map_err(|e| format!("Failed to write genesis block: {}", e))?;
+        } else {
+            let mut blocks = store
+                .load_all()
+                .map_err(|e| format!("Failed to read block store: {}", e))?
+                .into_iter();
+
+            let stored_genesis = blocks.next().map(|block| block.block_hash);
+            if stored_genesis.as_deref() != Some(blockchain.chain[0].block_hash.as_str()) {
+                return Err(
+                    "Block store was created from a different genesis configuration".to_string(),
+                );
+            }
+            for block in blocks {
+                let block_number = block.header.block_number;
+                blockchain.accept_block(block, false).map_err(|e| {
+                    format!("Stored block {} failed to replay: {}", block_number, e)
+                })?;
+            }
         }
 
-        match bob {
-            Ok(_) => println!("Alice:"),
-            Err(e) => println!("Error : {}", e),
+        blockchain.store = Some(store);
+        Ok(blockchain)
+    }
+
+    // Accept a block from this node or a peer. A block on the tip is connected;
+    // any other block with a known parent is checked as far as possible without
+    // its parent's state and kept on a side branch, which replaces the main
+    // chain once it has more work. With `persist`, blocks that join the main
+    // chain are written to the store.
+    fn accept_block(&mut self, block: DataBlock, persist: bool) -> Result<BlockStatus, String> {
+        let block_hash = block.block_hash.clone();
+        let block_number = block.header.block_number;
+        if self.is_known(&block) {
+            return Err(format!("Block {} is already known", block_hash));
+        }
+        if self.tree.is_invalid(&block_hash) || self.tree.is_invalid(&block.header.previous_hash) {
+            return Err(format!("Block {} is on an invalid branch", block_hash));
         }
 
-        // let accounts: Vec<Result<Account, String>> = vec![alice, bob];
+        if block.header.previous_hash == self.get_latest_block().block_hash {
+            let receipts = self.connect_block(block, persist)?;
+            self.emit(ChainEvent::BlockConnected {
+                block_number,
+                block_hash,
+            });
+            return Ok(BlockStatus::Connected(receipts));
+        }
 
-        // let acc_list: Vec<Account> = accounts
-        //     .into_iter()
-        //     .filter_map(|res| res.ok()) // Extract only the Ok values, discarding Err
-        //     .collect();
+        let (parent, parent_work) =
+            self.find_block(&block.header.previous_hash)
+                .ok_or_else(|| {
+                    format!(
+                        "Block {} has unknown parent {}",
+                        block_number, block.header.previous_hash
+                    )
+                })?;
+        self.check_block(&block, parent, self.target_after(parent))?;
 
-        // print!("Initializing blockchain... {} \n", acc_list.len());
+        let total_work = parent_work + block.work();
+        self.tree.insert(block, total_work, false);
+        if total_work <= self.total_work() {
+            return Ok(BlockStatus::SideBranch);
+        }
+        self.reorganize(&block_hash, persist)
+            .map(BlockStatus::Reorganized)
+    }
 
-        BharatChain {
-            chain: vec![genesis_block],
-            difficulty,
-            accounts: vec![],
+    // Checks that need only the block and its ancestors: link, timestamp, hash,
+    // target and proof-of-work, and the version and Merkle root
+    fn check_block(
+        &self,
+        block: &DataBlock,
+        parent: &DataBlock,
+        bits: CompactTarget,
+    ) -> Result<(), String> {
+        if block.header.block_number != parent.header.block_number + 1
+            || block.header.previous_hash != parent.block_hash
+        {
+            return Err(format!(
+                "Block {} does not extend its parent",
+                block.header.block_number
+            ));
+        }
+
+        // The median bounds how far back a miner can skew the retarget period;
+        // the clock bounds how far forward
+        let median_time = self.median_time_past(parent);
+        if block.header.timestamp <= median_time {
+            return Err(format!(
+                "Block {} has timestamp {}, not after the median time {} of the blocks before it",
+                block.header.block_number, block.header.timestamp, median_time
+            ));
+        }
+        let latest_allowed = get_current_timestamp() + MAX_FUTURE_BLOCK_TIME;
+        if block.header.timestamp > latest_allowed {
+            return Err(format!(
+                "Block {} has timestamp {}, too far in the future (latest allowed {})",
+                block.header.block_number, block.header.timestamp, latest_allowed
+            ));
+        }
+
+        if block.block_hash != block.calculate_hash() {
+            return Err(format!(
+                "Block {} has an invalid hash",
+                block.header.block_number
+            ));
+        }
+
+        if block.header.bits != bits || !block.meets_target() {
+            return Err(format!(
+                "Block {} does not meet the required target",
+                block.header.block_number
+            ));
+        }
+
+        block.check_merkle_root()?;
+
+        Ok(())
+    }
+
+    // Fully validate a block on top of the current tip and apply it:
+    // `check_block`, then transactions and state root. With `persist` the block
+    // is written to the store before any state is committed.
+    fn connect_block(
+        &mut self,
+        block: DataBlock,
+        persist: bool,
+    ) -> Result<Vec<TransactionReceipt>, String> {
+        self.check_block(&block, self.get_latest_block(), self.next_target())?;
+        self.check_replay(&block.body.transactions)?;
+
+        let snapshot = self.accounts.snapshot();
+        let receipts =
+            match block.apply_transactions(&mut self.accounts, &self.rewards, self.chain_id) {
+                Ok(receipts) => receipts,
+                Err(receipts) => {
+                    self.accounts.revert(snapshot);
+                    return Err(format!(
+                        "Transaction failed. Block not added. {}",
+                        failure_reason(&receipts)
+                    ));
+                }
+            };
+        let changes = self.accounts.changes_since(snapshot);
+
+        let previous_root = self.state.root_hash();
+        let state_root = update_state(&mut self.state, &self.accounts, &changes);
+        if state_root != block.header.state_root {
+            self.accounts.revert(snapshot);
+            self.state.set_root(&previous_root)?;
+            return Err(format!(
+                "Block {} has state root {}, applying it produced {}",
+                block.header.block_number, block.header.state_root, state_root
+            ));
+        }
+
+        // Only commit the new state once the block is safely on disk
+        if persist {
+            if let Some(store) = self.store.as_mut() {
+                if let Err(e) = store.append(&block) {
+                    self.accounts.revert(snapshot);
+                    self.state.set_root(&previous_root)?;
+                    return Err(format!("Failed to persist block: {}", e));
+                }
+            }
         }
+
+        self.accounts.commit(snapshot);
+        self.undo.push(changes);
+        self.chain_work.push(self.total_work() + block.work());
+        // Nodes replaced by this block are no longer needed
+        self.state.prune();
+        self.mempool.remove_included(&block.body.transactions);
+        self.mempool.prune(&self.accounts);
+        self.chain.push(block);
+
+        // Any block still being mined at this height can no longer extend the tip
+        self.renew_mining_token();
+        Ok(receipts)
+    }
+
+    // Remove main-chain blocks above `block_number`, restoring the accounts and
+    // state trie entries they changed, and keep them in the tree. Returns their
+    // hashes, oldest first.
+    fn disconnect_to(&mut self, block_number: u64) -> Result<Vec<String>, String> {
+        let mut disconnected = vec![];
+        while self.height() > block_number {
+            let block = self.chain.pop().expect("chain above genesis");
+            let total_work = self.chain_work.pop().expect("work of every block");
+            let undo = self.undo.pop().expect("undo of every block");
+            // Earlier roots are pruned, so the trie is rolled back entry by entry
+            for (address, previous) in &undo {
+                match previous {
+                    Some(account) => {
+                        let value = serde_json::to_string(account).expect("account serializes");
+                        self.state.insert(&address.to_string(), value);
+                    }
+                    None => {
+                        self.state.delete(&address.to_string());
+                    }
+                }
+            }
+            self.accounts.restore(undo);
+
+            disconnected.push(block.block_hash.clone());
+            self.tree.insert(block, total_work, true);
+        }
+
+        let state_root = &self.get_latest_block().header.state_root;
+        if self.state.root_hash() != *state_root {
+            return Err(format!(
+                "Disconnecting to block {} produced state root {}, expected {}",
+                block_number,
+                self.state.root_hash(),
+                state_root
+            ));
+        }
+        self.state.prune();
+        self.renew_mining_token();
+        disconnected.reverse();
+        Ok(disconnected)
+    }
+
+    // Switch the main chain to the side branch ending at `tip_hash`. Blocks of
+    // the branch are fully validated as they are connected; if one fails, it and
+    // its descendants are marked invalid, and the old main chain is restored
+    // unless the valid part of the branch still has more work. Transactions of
+    // the old chain that the new one does not include go back to the mempool.
+    fn reorganize(&mut self, tip_hash: &str, persist: bool) -> Result<Reorg, String> {
+        let branch = self.tree.branch(tip_hash);
+        let fork_point = self
+            .tree
+            .get(&branch[0])
+            .expect("branch is in the tree")
+            .block
+            .header
+            .block_number
+            - 1;
+        let old_work = self.total_work();
+
+        let disconnected = self.disconnect_to(fork_point)?;
+        let mut connected = vec![];
+        let mut failure = None;
+        for hash in branch {
+            let side = self.tree.remove(&hash).expect("branch is in the tree");
+            match self.connect_block(side.block, persist && !side.stored) {
+                Ok(_) => connected.push(hash),
+                Err(e) => {
+                    self.tree.invalidate(&hash);
+                    failure = Some(format!("Reorganization to {} failed: {}", tip_hash, e));
+                    break;
+                }
+            }
+        }
+
+        if let Some(e) = &failure {
+            if self.total_work() <= old_work {
+                self.disconnect_to(fork_point)?;
+                for hash in &disconnected {
+                    let side = self
+                        .tree
+                        .remove(hash)
+                        .expect("disconnected block is in the tree");
+                    self.connect_block(side.block, false)?;
+                }
+                // Connecting the branch took its transactions out of the mempool
+                self.return_to_mempool(&connected, fork_point);
+                return Err(e.clone());
+            }
+        }
+
+        let returned_transactions = self.return_to_mempool(&disconnected, fork_point);
+        let reorg = Reorg {
+            fork_point,
+            disconnected,
+            connected,
+            returned_transactions,
+        };
+        self.emit(ChainEvent::Reorganized(reorg.clone()));
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(reorg),
+        }
+    }
+
+    // Resubmit the transactions of the side blocks `hashes` that the main chain
+    // above `fork_point` does not include, in chain order so each sender's nonces
+    // stay in sequence. Returns the hashes the mempool accepted.
+    fn return_to_mempool(&mut self, hashes: &[String], fork_point: u64) -> Vec<String> {
+        let included: HashSet<String> = self.chain[fork_point as usize + 1..]
+            .iter()
+            .flat_map(|block| block.body.transactions.iter().map(|tx| tx.compute_hash()))
+            .collect();
+        let orphaned: Vec<BlockTransaction> = hashes
+            .iter()
+            .filter_map(|hash| self.tree.get(hash))
+            .flat_map(|side| side.block.body.transactions.iter())
+            .filter(|tx| !tx.is_coinbase() && !included.contains(&tx.compute_hash()))
+            .cloned()
+            .collect();
+
+        orphaned
+            .into_iter()
+            .filter_map(|tx| self.submit_transaction(tx).ok())
+            .collect()
+    }
+
+    // A known block, on the main chain or a side branch, with the cumulative
+    // work up to and including it
+    fn find_block(&self, block_hash: &str) -> Option<(&DataBlock, U256)> {
+        if let Some(side) = self.tree.get(block_hash) {
+            return Some((&side.block, side.total_work));
+        }
+        let block_number = self.get_block_by_hash(block_hash)?.header.block_number;
+        Some((
+            &self.chain[block_number as usize],
+            self.chain_work[block_number as usize],
+        ))
+    }
+
+    fn is_known(&self, block: &DataBlock) -> bool {
+        self.tree.contains(&block.block_hash)
+            || self
+                .get_block_by_number(block.header.block_number)
+                .is_some_and(|known| known.block_hash == block.block_hash)
+    }
+
+    // Header at `block_number` on the branch ending at `block_hash`
+    pub fn ancestor(&self, block_hash: &str, block_number: u64) -> Option<&BlockHeader> {
+        let mut hash = block_hash;
+        while let Some(side) = self.tree.get(hash) {
+            if side.block.header.block_number == block_number {
+                return Some(&side.block.header);
+            }
+            hash = &side.block.header.previous_hash;
+        }
+        // Side branches always fork off the main chain
+        self.get_block_by_number(block_number)
+            .map(|block| &block.header)
+    }
+
+    // Target of the block that follows `parent`, on whichever branch it is
+    fn target_after(&self, parent: &DataBlock) -> CompactTarget {
+        self.genesis
+            .retarget
+            .next_target(&parent.header, |block_number| {
+                self.ancestor(&parent.block_hash, block_number)
+                    .expect("retarget period starts on the same branch")
+            })
+    }
+
+    // Median timestamp of `parent` and the blocks before it on its branch, up
+    // to `MEDIAN_TIME_SPAN` blocks in all
+    fn median_time_past(&self, parent: &DataBlock) -> u64 {
+        let last = parent.header.block_number;
+        let first = (last + 1).saturating_sub(MEDIAN_TIME_SPAN);
+        median_timestamp(
+            (first..=last)
+                .map(|block_number| {
+                    self.ancestor(&parent.block_hash, block_number)
+                        .expect("ancestors are on the same branch")
+                        .timestamp
+                })
+                .collect(),
+        )
+    }
+
+    fn renew_mining_token(&mut self) {
+        self.mining.cancel();
+        self.mining = CancelToken::new();
+    }
+
+    // Receive every future chain event
+    pub fn subscribe(&mut self) -> Receiver<ChainEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.events.push(sender);
+        receiver
+    }
+
+    fn emit(&mut self, event: ChainEvent) {
+        self.events
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    // Blocks known off the main chain
+    pub fn block_tree(&self) -> &BlockTree {
+        &self.tree
+    }
+
+    // Target the next block must be mined at, per the retarget schedule
+    pub fn next_target(&self) -> CompactTarget {
+        self.target_after(self.get_latest_block())
+    }
+
+    // Total work of the chain up to and including `block_number`
+    pub fn cumulative_work(&self, block_number: u64) -> Option<U256> {
+        self.chain_work
+            .get(usize::try_from(block_number).ok()?)
+            .copied()
+    }
+
+    // Total work of the main chain, the measure fork choice compares
+    pub fn total_work(&self) -> U256 {
+        *self.chain_work.last().expect("chain has a genesis block")
     }
 
     // Get the latest block in the chain
@@ -53,93 +538,328 @@ impl BharatChain {
         self.chain.last().unwrap()
     }
 
-    // Add a new block to the blockchain
-    pub fn add_block(&mut self, txns: Vec<BlockTransaction>) {
-        print!("\n Adding block on chain....\n");
+    // Number of the latest block
+    pub fn height(&self) -> u64 {
+        self.get_latest_block().header.block_number
+    }
+
+    pub fn get_block_by_number(&self, block_number: u64) -> Option<&DataBlock> {
+        self.chain.get(usize::try_from(block_number).ok()?)
+    }
+
+    pub fn get_block_by_hash(&self, block_hash: &str) -> Option<&DataBlock> {
+        self.chain
+            .iter()
+            .find(|block| block.block_hash == block_hash)
+    }
+
+    // A block on the main chain or a side branch
+    pub fn get_known_block(&self, block_hash: &str) -> Option<&DataBlock> {
+        self.find_block(block_hash).map(|(block, _)| block)
+    }
+
+    // Hashes describing the main chain to a peer: the tip and its ancestors,
+    // dense near the tip and exponentially sparser towards genesis
+    pub fn block_locator(&self) -> Vec<String> {
+        let mut locator = vec![];
+        let mut block_number = self.height();
+        let mut step = 1;
+        loop {
+            locator.push(self.chain[block_number as usize].block_hash.clone());
+            if block_number == 0 {
+                break;
+            }
+            if locator.len() >= 10 {
+                step *= 2;
+            }
+            block_number = block_number.saturating_sub(step);
+        }
+        locator
+    }
+
+    // Up to `max` main-chain blocks following the first `locator` entry on the
+    // main chain (genesis if none is), stopping after `stop_hash`
+    pub fn locate_blocks(&self, locator: &[String], stop_hash: &str, max: usize) -> &[DataBlock] {
+        let start = locator
+            .iter()
+            .find_map(|hash| self.get_block_by_hash(hash))
+            .map_or(0, |block| block.header.block_number) as usize
+            + 1;
+
+        let blocks = &self.chain[start.min(self.chain.len())..];
+        let blocks = &blocks[..blocks.len().min(max)];
+        match blocks
+            .iter()
+            .position(|block| block.block_hash == stop_hash)
+        {
+            Some(stop) => &blocks[..=stop],
+            None => blocks,
+        }
+    }
+
+    // Find a transaction by its hash, along with the block that includes it
+    pub fn get_transaction(&self, tx_hash: &str) -> Option<(&DataBlock, &BlockTransaction)> {
+        self.chain.iter().find_map(|block| {
+            block
+                .body
+                .transactions
+                .iter()
+                .find(|tx| tx.compute_hash() == tx_hash)
+                .map(|tx| (block, tx))
+        })
+    }
+
+    // Build the next block on top of the tip from `txns`, adding the coinbase
+    // for the local miner, and commit it to the resulting state root. The block
+    // still has to be mined; the returned token is cancelled as soon as another
+    // block is connected at this height.
+    pub fn prepare_block(
+        &mut self,
+        mut txns: Vec<BlockTransaction>,
+    ) -> Result<(DataBlock, CancelToken), String> {
+        // Reject replayed or reordered transactions before spending work on mining
+        self.check_replay(&txns)?;
 
         let latest_block = self.get_latest_block();
-        let new_block = DataBlock::new(
-            latest_block.block_number + 1,
-            latest_block.block_hash.clone(),
-            txns,
-        );
-
-        let mut block_to_mine = new_block;
-        block_to_mine.mine_block(self.difficulty);
-
-        // Apply the transactions to the account state before adding the block
-        if block_to_mine.apply_transactions(&mut self.accounts) {
-            self.chain.push(block_to_mine);
-        } else {
-            // Panic if the transaction is failed.
-            println!("Transaction failed. Block not added.");
+        let block_number = latest_block.header.block_number + 1;
+        let previous_hash = latest_block.block_hash.clone();
+        let median_time = self.median_time_past(latest_block);
+
+        // Pay the local miner the subsidy plus every fee in the block
+        let has_coinbase = txns.first().is_some_and(|tx| tx.is_coinbase());
+        if let (Some(miner), false) = (self.miner_address, has_coinbase) {
+            let reward = txns
+                .iter()
+                .try_fold(self.rewards.subsidy_at(block_number), |total, tx| {
+                    total.checked_add(tx.fee)
+                })
+                .map_err(|e| e.to_string())?;
+            let coinbase = BlockTransaction::coinbase(miner, reward, block_number, self.chain_id);
+            txns.insert(0, coinbase);
+        }
+
+        let mut block = DataBlock::new(block_number, previous_hash, txns);
+        block.header.bits = self.next_target();
+
+        // Blocks mined within the same second must still move past the median
+        block.header.timestamp = block.header.timestamp.max(median_time + 1);
+
+        // Apply the transactions under a snapshot of the account state, so the
+        // block can commit to the resulting state root without changing the chain yet
+        let snapshot = self.accounts.snapshot();
+        if let Err(receipts) =
+            block.apply_transactions(&mut self.accounts, &self.rewards, self.chain_id)
+        {
+            self.accounts.revert(snapshot);
+            return Err(format!(
+                "Transaction failed. Block not added. {}",
+                failure_reason(&receipts)
+            ));
+        }
+        let changes = self.accounts.changes_since(snapshot);
+
+        let previous_root = self.state.root_hash();
+        block.set_state_root(update_state(&mut self.state, &self.accounts, &changes));
+        self.accounts.revert(snapshot);
+        self.state.set_root(&previous_root)?;
+
+        Ok((block, self.mining.clone()))
+    }
+
+    // Validate a mined block, from this node or a peer, and add it to the main
+    // chain or a side branch, reorganizing if it makes a side branch heavier
+    pub fn submit_block(&mut self, block: DataBlock) -> Result<BlockStatus, String> {
+        self.accept_block(block, true)
+    }
+
+    // Add a new block to the blockchain: prepare, mine and submit it
+    pub fn add_block(
+        &mut self,
+        txns: Vec<BlockTransaction>,
+    ) -> Result<Vec<TransactionReceipt>, String> {
+        let (block, cancel) = self.prepare_block(txns)?;
+        match self.miner.mine(block, &cancel) {
+            MiningOutcome::Mined(block, _) => match self.submit_block(*block)? {
+                BlockStatus::Connected(receipts) => Ok(receipts),
+                _ => Err("Mined block did not extend the chain".to_string()),
+            },
+            MiningOutcome::Cancelled(_) => Err("Mining was cancelled".to_string()),
+        }
+    }
+
+    // Validate a transaction and queue it in the mempool for a later block
+    pub fn submit_transaction(&mut self, tx: BlockTransaction) -> Result<String, String> {
+        let tx_hash = self.mempool.submit(tx, &self.accounts, self.chain_id)?;
+        self.emit(ChainEvent::TransactionAccepted {
+            tx_hash: tx_hash.clone(),
+        });
+        Ok(tx_hash)
+    }
+
+    // Mine a block from the best transactions in the mempool
+    pub fn mine_pending_block(
+        &mut self,
+        max_transactions: usize,
+    ) -> Result<Vec<TransactionReceipt>, String> {
+        let txns = self
+            .mempool
+            .block_template(&mut self.accounts, max_transactions);
+        self.add_block(txns)
+    }
+
+    // Check chain ids and that each sender's nonces continue from its account nonce
+    // without gaps, so a transaction can neither be replayed nor applied out of order
+    fn check_replay(&self, txns: &[BlockTransaction]) -> Result<(), String> {
+        let mut next_nonces: HashMap<&Address, u64> = HashMap::new();
+
+        for tx in txns {
+            if tx.is_coinbase() {
+                continue;
+            }
+
+            if tx.chain_id != self.chain_id {
+                return Err(format!(
+                    "Transaction {} is for chain {}, expected {}",
+                    tx.id, tx.chain_id, self.chain_id
+                ));
+            }
+
+            let expected = *next_nonces
+                .entry(&tx.sender)
+                .or_insert_with(|| self.accounts.nonce(&tx.sender));
+
+            if tx.nonce < expected {
+                return Err(format!(
+                    "Transaction {} reuses nonce {} (expected {})",
+                    tx.id, tx.nonce, expected
+                ));
+            }
+            if tx.nonce > expected {
+                return Err(format!(
+                    "Transaction {} has out-of-order nonce {} (expected {})",
+                    tx.id, tx.nonce, expected
+                ));
+            }
+
+            next_nonces.insert(&tx.sender, expected + 1);
         }
+
+        Ok(())
     }
 
-    // Check if the blockchain is valid (basic check for now)
+    // Check if the blockchain is valid: block links, hashes, Merkle roots, and the
+    // state root of every block, recomputed by replaying the chain from genesis
     pub fn is_valid(&self) -> bool {
+        self.verify().is_ok()
+    }
+
+    // Same checks as `is_valid`, but reports the first problem found
+    pub fn verify(&self) -> Result<(), String> {
+        let mut accounts = self.genesis.accounts();
+        let mut state = MerklePatriciaTree::new();
+        let genesis_block =
+            self.genesis
+                .genesis_block(update_state(&mut state, &accounts, &created(&accounts)));
+
+        if self.chain[0].block_hash != genesis_block.block_hash {
+            return Err(format!(
+                "Genesis block {} does not match the configured genesis {}",
+                self.chain[0].block_hash, genesis_block.block_hash
+            ));
+        }
+
         let length = self.chain.len();
         for i in 1..length {
             let previous_block = &self.chain[i - 1];
             let current_block = &self.chain[i];
+            let block_number = current_block.header.block_number;
+
+            let first = i.saturating_sub(MEDIAN_TIME_SPAN as usize);
+            let median_time = median_timestamp(
+                self.chain[first..i]
+                    .iter()
+                    .map(|block| block.header.timestamp)
+                    .collect(),
+            );
+            if current_block.header.timestamp <= median_time {
+                return Err(format!(
+                    "Block {} has timestamp {}, not after the median time {} of the blocks before it",
+                    block_number, current_block.header.timestamp, median_time
+                ));
+            }
 
             // Check that the previous block's hash matches the current block's "previous_hash"
-            if current_block.previous_hash != previous_block.block_hash {
-                print!(
-                    "previous_hash mismatch: {}, calculated {} ",
-                    current_block.previous_hash, previous_block.block_hash
-                );
-                return false;
+            if current_block.header.previous_hash != previous_block.block_hash {
+                return Err(format!(
+                    "Block {} has previous hash {}, expected {}",
+                    block_number, current_block.header.previous_hash, previous_block.block_hash
+                ));
             }
 
             // Check that the current block's hash matches the calculated hash
             let current_recalculated_hash = current_block.calculate_hash();
-
             if current_block.block_hash != current_recalculated_hash {
-                print!(
-                    "current_hash mismatch: {}, calculated {} ",
-                    current_block.block_hash, current_recalculated_hash
-                );
-                return false;
+                return Err(format!(
+                    "Block {} has hash {}, calculated {}",
+                    block_number, current_block.block_hash, current_recalculated_hash
+                ));
             }
 
-            // Check Merkle root consistency
-            let transaction_hashes: Vec<String> = current_block
-                .transactions
-                .clone()
-                .into_iter()
-                .map(|tx| tx.compute_hash())
-                .collect();
-
-            let mut merkle_tree = MerkleTree::new();
-            let recalculated_merkle_root = merkle_tree.build_merkle_tree(transaction_hashes);
-
-            // validae if the current block is not already in the tree
-            // or if the block is altered o not
-            if current_block.merkle_root != recalculated_merkle_root {
-                print!(
-                    "root mismatch: {}, calculated {} ",
-                    current_block.merkle_root, recalculated_merkle_root
-                );
-                return false;
+            // Check the target follows the retarget schedule and the work was done
+            let expected_target = self
+                .genesis
+                .retarget
+                .next_target(&previous_block.header, |block_number| {
+                    &self.chain[block_number as usize].header
+                });
+            if current_block.header.bits != expected_target || !current_block.meets_target() {
+                return Err(format!(
+                    "Block {} does not meet the required target {}",
+                    block_number, expected_target
+                ));
+            }
+
+            // Check the version and Merkle root consistency
+            current_block.check_merkle_root()?;
+
+            // Check if the account state is consistent with the transactions in the block
+            let snapshot = accounts.snapshot();
+            current_block
+                .apply_transactions(&mut accounts, &self.rewards, self.chain_id)
+                .map_err(|receipts| {
+                    format!(
+                        "Block {} failed to apply. {}",
+                        block_number,
+                        failure_reason(&receipts)
+                    )
+                })?;
+
+            let changes = accounts.changes_since(snapshot);
+            accounts.commit(snapshot);
+            let recalculated_state_root = update_state(&mut state, &accounts, &changes);
+            if current_block.header.state_root != recalculated_state_root {
+                return Err(format!(
+                    "Block {} has state root {}, calculated {}",
+                    block_number, current_block.header.state_root, recalculated_state_root
+                ));
             }
         }
 
-        // Check if the account state is consistent with the transactions in the block
-        true
+        Ok(())
     }
 
     pub fn history(&mut self) {
         print!("\n Blockchain History......:\n");
         for (index, block) in self.chain.iter().enumerate() {
             println!("Block #{}:", index);
-            println!("- Block Number: {}", block.block_number);
-            println!("- Previous Hash: {}", block.previous_hash);
-            println!("- Merkle Root: {}", block.merkle_root);
-            println!("- Block timestamp: {} \n", block.timestamp);
+            println!("- Block Number: {}", block.header.block_number);
+            println!("- Previous Hash: {}", block.header.previous_hash);
+            println!("- Merkle Root: {}", block.header.merkle_root);
+            println!("- State Root: {}", block.header.state_root);
+            println!("- Block timestamp: {} \n", block.header.timestamp);
             println!("- Block hash: {} \n", block.block_hash);
             println!("- Transactions:\n");
-            for tx in &block.transactions {
+            for tx in &block.body.transactions {
                 println!("  - ID: {}", tx.id);
                 println!("  - Sender: {}", tx.sender);
                 println!("  - Receiver: {}", tx.receiver);
@@ -154,13 +874,13 @@ impl BharatChain {
         print!("\n genesis_block_details ......:\n");
         let genesis_block = self.chain.first().unwrap();
 
-        println!("- Block Number: {}", genesis_block.block_number);
-        println!("- Previous Hash: {}", genesis_block.previous_hash);
-        println!("- Merkle Root: {}", genesis_block.merkle_root);
-        println!("- Block timestamp: {} \n", genesis_block.timestamp);
+        println!("- Block Number: {}", genesis_block.header.block_number);
+        println!("- Previous Hash: {}", genesis_block.header.previous_hash);
+        println!("- Merkle Root: {}", genesis_block.header.merkle_root);
+        println!("- Block timestamp: {} \n", genesis_block.header.timestamp);
         println!("- Block hash: {} \n", genesis_block.block_hash);
         println!("- Transactions:\n");
-        for tx in &genesis_block.transactions {
+        for tx in &genesis_block.body.transactions {
             println!("  - ID: {}", tx.id);
             println!("  - Sender: {}", tx.sender);
             println!("  - Receiver: {}", tx.receiver);
@@ -170,10 +890,53 @@ impl BharatChain {
         println!("---\n");
     }
 
-    pub fn get_balance(&self, account_address: String) -> Option<f64> {
-        self.accounts
-            .iter()
-            .find(|acc| acc.address == account_address)
-            .map(|acc| acc.balance)
+    pub fn get_balance(&self, account_address: &Address) -> Option<Amount> {
+        self.accounts.get(account_address).map(|acc| acc.balance)
+    }
+
+    // Next nonce the given account must use; unknown accounts start at 0
+    pub fn get_nonce(&self, account_address: &Address) -> u64 {
+        self.accounts.nonce(account_address)
+    }
+}
+
+// First failure recorded in the receipts of a block that did not apply
+fn failure_reason(receipts: &[TransactionReceipt]) -> String {
+    receipts
+        .iter()
+        .find_map(|receipt| match &receipt.status {
+            ReceiptStatus::Failed(e) => Some(format!("{}: {}", receipt.tx_hash, e)),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+// Undo record of a state whose accounts were all created from nothing
+fn created(accounts: &StateDB) -> StateUndo {
+    accounts.iter().map(|acc| (acc.address, None)).collect()
+}
+
+// Median of a non-empty list of block timestamps
+pub fn median_timestamp(mut timestamps: Vec<u64>) -> u64 {
+    timestamps.sort_unstable();
+    timestamps[timestamps.len() / 2]
+}
+
+// Write the changed accounts that differ from their previous versions into the
+// state trie and return the new root
+fn update_state(
+    state: &mut MerklePatriciaTree,
+    accounts: &StateDB,
+    changes: &[(Address, Option<Account>)],
+) -> String {
+    for (address, previous) in changes {
+        if let Some(account) = accounts.get(address) {
+            if previous.as_ref() != Some(account) {
+                let value = serde_json::to_string(account).expect("account serializes");
+                state.insert(&address.to_string(), value);
+            }
+        }
     }
+
+    state.root_hash()
 }
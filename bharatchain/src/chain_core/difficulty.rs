@@ -0,0 +1,200 @@
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+use super::block::BlockHeader;
+
+pub use ethnum::U256;
+
+// Easiest target any block may use (the proof-of-work limit): 0x7fffff << 232
+pub const MAX_TARGET_BITS: CompactTarget = CompactTarget(0x207f_ffff);
+
+// A 256-bit proof-of-work target in Bitcoin's compact "nBits" form: the high
+// byte is a base-256 exponent and the low three bytes a mantissa, so
+// target = mantissa * 256^(exponent - 3). A block is valid when its hash,
+// read as a big-endian number, is at most the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CompactTarget(u32);
+
+impl CompactTarget {
+    pub const fn from_bits(bits: u32) -> Self {
+        CompactTarget(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    // Expand to the full target, rejecting negative, zero and overflowing encodings
+    pub fn to_target(&self) -> Result<U256, String> {
+        let exponent = self.0 >> 24;
+        let mantissa = self.0 & 0x007f_ffff;
+
+        if self.0 & 0x0080_0000 != 0 && mantissa != 0 {
+            return Err(format!("Compact target {} is negative", self));
+        }
+
+        let target = if exponent <= 3 {
+            U256::from(mantissa >> (8 * (3 - exponent)))
+        } else {
+            // The mantissa must still fit in 256 bits once shifted into place
+            let mantissa_bits = 32 - mantissa.leading_zeros();
+            if mantissa_bits + 8 * (exponent - 3) > 256 {
+                return Err(format!("Compact target {} overflows 256 bits", self));
+            }
+            U256::from(mantissa) << (8 * (exponent - 3))
+        };
+
+        if target == U256::ZERO {
+            return Err(format!("Compact target {} is zero", self));
+        }
+        Ok(target)
+    }
+
+    // Compact form of `target`, keeping its three most significant bytes
+    pub fn from_target(target: U256) -> Self {
+        let mut size = (256 - target.leading_zeros()).div_ceil(8);
+        let mut mantissa = if size <= 3 {
+            target.as_u32() << (8 * (3 - size))
+        } else {
+            (target >> (8 * (size - 3))).as_u32()
+        };
+
+        // The mantissa's top bit is a sign bit, so move a set bit into the exponent
+        if mantissa & 0x0080_0000 != 0 {
+            mantissa >>= 8;
+            size += 1;
+        }
+        CompactTarget(mantissa | (size << 24))
+    }
+
+    // Expected number of hashes needed to meet this target: 2^256 / (target + 1)
+    pub fn work(&self) -> U256 {
+        match self.to_target() {
+            Ok(target) if target != U256::MAX => (!target / (target + 1)) + 1,
+            Ok(_) => U256::ONE,
+            Err(_) => U256::ZERO,
+        }
+    }
+}
+
+impl fmt::Display for CompactTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08x}", self.0)
+    }
+}
+
+// Serialized as eight hex digits, e.g. "1f00ffff", as block explorers show it
+impl Serialize for CompactTarget {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactTarget {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CompactTargetVisitor;
+
+        impl Visitor<'_> for CompactTargetVisitor {
+            type Value = CompactTarget;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a compact target as 8 hex digits")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<CompactTarget, E> {
+                if v.len() != 8 {
+                    return Err(E::custom(format!("Invalid compact target: {}", v)));
+                }
+                u32::from_str_radix(v, 16)
+                    .map(CompactTarget)
+                    .map_err(|_| E::custom(format!("Invalid compact target: {}", v)))
+            }
+        }
+
+        deserializer.deserialize_str(CompactTargetVisitor)
+    }
+}
+
+// Retargeting rule: every `interval` blocks the target is scaled by how far the
+// observed block time was from `target_block_time`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetargetConfig {
+    pub interval: u64,          // Blocks per retarget period
+    pub target_block_time: u64, // Desired seconds between blocks
+    pub max_adjustment: u64,    // Largest factor the target may change by in one period
+}
+
+impl Default for RetargetConfig {
+    fn default() -> Self {
+        RetargetConfig {
+            interval: 10,
+            target_block_time: 10,
+            max_adjustment: 4,
+        }
+    }
+}
+
+impl RetargetConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval < 2 {
+            return Err("Retarget interval must be at least 2 blocks".to_string());
+        }
+        if self.target_block_time == 0 {
+            return Err("Target block time must be positive".to_string());
+        }
+        if self.max_adjustment < 1 {
+            return Err("Maximum retarget adjustment must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    // Target required of the block that follows `tip`. Outside of a retarget
+    // height this is the tip's target. `ancestor` returns the header at a given
+    // height on the tip's branch, which may be a side branch.
+    pub fn next_target<'a>(
+        &self,
+        tip: &BlockHeader,
+        ancestor: impl FnOnce(u64) -> &'a BlockHeader,
+    ) -> CompactTarget {
+        let next_number = tip.block_number + 1;
+        if !next_number.is_multiple_of(self.interval) {
+            return tip.bits;
+        }
+
+        // Measure from the last block of the previous period, so every gap of
+        // this period is counted. The genesis timestamp is fixed by
+        // configuration and says nothing about when mining started, so the
+        // first period is measured from block 1 instead.
+        let first = (next_number - self.interval).saturating_sub(1).max(1);
+        let gaps = tip.block_number.saturating_sub(first);
+        if gaps == 0 {
+            return tip.bits;
+        }
+
+        let actual = tip.timestamp.saturating_sub(ancestor(first).timestamp);
+        let expected = gaps * self.target_block_time;
+        retarget(tip.bits, actual, expected, self.max_adjustment)
+    }
+}
+
+// Scale the target by actual / expected time, clamped to `max_adjustment`
+// either way and never easier than the proof-of-work limit
+fn retarget(bits: CompactTarget, actual: u64, expected: u64, max_adjustment: u64) -> CompactTarget {
+    let limit = MAX_TARGET_BITS
+        .to_target()
+        .expect("valid proof-of-work limit");
+    let target = bits.to_target().unwrap_or(limit);
+
+    let expected = U256::from(expected);
+    let actual = U256::from(actual).clamp(
+        (expected / U256::from(max_adjustment)).max(U256::ONE),
+        expected * U256::from(max_adjustment),
+    );
+
+    let new_target = match target.checked_mul(actual) {
+        Some(scaled) => (scaled / expected).clamp(U256::ONE, limit),
+        None => limit,
+    };
+    CompactTarget::from_target(new_target)
+}
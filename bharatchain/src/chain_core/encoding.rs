@@ -0,0 +1,195 @@
+// Canonical binary encoding used for hashing, the block store and the network.
+//
+// Integers are fixed-width big-endian, strings and byte strings carry a u32
+// big-endian length prefix, fixed-size byte arrays are written as they are,
+// and lists carry a u32 item count. Every encoding produced by `encode` starts
+// with `ENCODING_VERSION`, so the format can change without old bytes being
+// misread. Each value has exactly one encoding, and decoding rejects anything
+// `encode` would not have produced.
+
+// Version byte at the start of every top-level encoding
+// (2: addresses became a version byte and a fixed-size hash)
+pub const ENCODING_VERSION: u8 = 2;
+
+// Longest string or list the decoder accepts, bounding allocations made for
+// lengths read from untrusted input
+pub const MAX_ENCODED_LEN: usize = 32 * 1024 * 1024;
+
+pub trait Encode {
+    fn encode_to(&self, out: &mut Encoder);
+}
+
+pub trait Decode: Sized {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String>;
+}
+
+// Versioned encoding of `value`
+pub fn encode<T: Encode>(value: &T) -> Vec<u8> {
+    let mut out = Encoder::new();
+    out.put_u8(ENCODING_VERSION);
+    value.encode_to(&mut out);
+    out.into_bytes()
+}
+
+// Decode a value produced by `encode`, rejecting unknown versions and trailing bytes
+pub fn decode<T: Decode>(bytes: &[u8]) -> Result<T, String> {
+    let mut input = Decoder::new(bytes);
+    let version = input.get_u8()?;
+    if version != ENCODING_VERSION {
+        return Err(format!("Unsupported encoding version {}", version));
+    }
+
+    let value = T::decode_from(&mut input)?;
+    input.finish()?;
+    Ok(value)
+}
+
+#[derive(Debug, Default)]
+pub struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder { bytes: vec![] }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn put_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn put_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn put_u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+    }
+
+    pub fn put_bytes(&mut self, value: &[u8]) {
+        self.put_len(value.len());
+        self.bytes.extend_from_slice(value);
+    }
+
+    // Bytes whose length the type fixes, without a length prefix
+    pub fn put_array<const N: usize>(&mut self, value: &[u8; N]) {
+        self.bytes.extend_from_slice(value);
+    }
+
+    pub fn put_str(&mut self, value: &str) {
+        self.put_bytes(value.as_bytes());
+    }
+
+    pub fn put_list<T: Encode>(&mut self, items: &[T]) {
+        self.put_len(items.len());
+        for item in items {
+            item.encode_to(self);
+        }
+    }
+
+    fn put_len(&mut self, len: usize) {
+        let len = u32::try_from(len).expect("encoded length fits in u32");
+        self.put_u32(len);
+    }
+}
+
+#[derive(Debug)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Decoder { bytes, position: 0 }
+    }
+
+    pub fn get_u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn get_u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn get_u64(&mut self) -> Result<u64, String> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn get_bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.get_len()?;
+        self.take(len)
+    }
+
+    pub fn get_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    pub fn get_str(&mut self) -> Result<String, String> {
+        let bytes = self.get_bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "Encoded string is not UTF-8".to_string())
+    }
+
+    pub fn get_list<T: Decode>(&mut self) -> Result<Vec<T>, String> {
+        let len = self.get_len()?;
+        // Every item takes at least one byte, so a count beyond the remaining
+        // input is malformed and must not drive the allocation
+        let mut items = Vec::with_capacity(len.min(self.remaining()));
+        for _ in 0..len {
+            items.push(T::decode_from(self)?);
+        }
+        Ok(items)
+    }
+
+    // Fail unless the whole input has been consumed
+    pub fn finish(&self) -> Result<(), String> {
+        match self.remaining() {
+            0 => Ok(()),
+            extra => Err(format!("{} trailing bytes after encoded value", extra)),
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+
+    fn get_len(&mut self) -> Result<usize, String> {
+        let len = self.get_u32()? as usize;
+        if len > MAX_ENCODED_LEN {
+            return Err(format!("Encoded length {} is too large", len));
+        }
+        Ok(len)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.remaining() {
+            return Err(format!(
+                "Unexpected end of input: needed {} bytes, {} left",
+                len,
+                self.remaining()
+            ));
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+}
+
+// Strings as list items, e.g. lists of hashes
+impl Encode for String {
+    fn encode_to(&self, out: &mut Encoder) {
+        out.put_str(self);
+    }
+}
+
+impl Decode for String {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        input.get_str()
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::account::Account;
+use super::address::Address;
+use super::amount::Amount;
+use super::block::DataBlock;
+use super::chain::DEFAULT_CHAIN_ID;
+use super::difficulty::{CompactTarget, RetargetConfig, MAX_TARGET_BITS};
+use super::reward::RewardSchedule;
+use super::state_db::StateDB;
+
+// Previous hash recorded in every genesis block
+pub const GENESIS_PREVIOUS_HASH: &str =
+    "27d9e52ddb66a5e2d1adeac33afcc9a1cf64847064760fa49cdf4eeb110c4953";
+
+// Balance credited to an address before the first block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisAllocation {
+    pub address: Address,
+    pub balance: Amount,
+}
+
+// Network parameters and initial allocations, usually loaded from a JSON file.
+// Allocations are the only coins that exist without being mined, so two nodes
+// with the same file always agree on the genesis block and its state root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisConfig {
+    pub chain_id: u64,
+    pub bits: CompactTarget, // Target of the first blocks, until the first retarget
+    pub timestamp: u64,      // Fixed so the genesis hash does not depend on when a node starts
+    #[serde(default)]
+    pub retarget: RetargetConfig,
+    #[serde(default)]
+    pub rewards: RewardSchedule,
+    #[serde(default)]
+    pub allocations: Vec<GenesisAllocation>,
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        GenesisConfig {
+            chain_id: DEFAULT_CHAIN_ID,
+            bits: MAX_TARGET_BITS,
+            timestamp: 0,
+            retarget: RetargetConfig::default(),
+            rewards: RewardSchedule::default(),
+            allocations: vec![],
+        }
+    }
+}
+
+impl GenesisConfig {
+    // Read and validate a genesis file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let json = fs::read_to_string(path.as_ref()).map_err(|e| {
+            format!(
+                "Failed to read genesis file {}: {}",
+                path.as_ref().display(),
+                e
+            )
+        })?;
+        Self::from_json(&json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let config: GenesisConfig =
+            serde_json::from_str(json).map_err(|e| format!("Invalid genesis file: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Reject configurations that would give nodes an ambiguous starting state
+    pub fn validate(&self) -> Result<(), String> {
+        let limit = MAX_TARGET_BITS.to_target()?;
+        if self.bits.to_target()? > limit {
+            return Err(format!(
+                "Genesis target {} is easier than the proof-of-work limit {}",
+                self.bits, MAX_TARGET_BITS
+            ));
+        }
+        self.retarget.validate()?;
+
+        let mut seen = HashSet::new();
+        let mut supply = Amount::ZERO;
+
+        for allocation in &self.allocations {
+            if !seen.insert(allocation.address) {
+                return Err(format!(
+                    "Genesis allocates to {} more than once",
+                    allocation.address
+                ));
+            }
+            supply = supply
+                .checked_add(allocation.balance)
+                .map_err(|_| "Genesis allocations exceed the maximum supply".to_string())?;
+        }
+
+        Ok(())
+    }
+
+    // Accounts that exist before the first block
+    pub fn accounts(&self) -> StateDB {
+        self.allocations
+            .iter()
+            .map(|allocation| Account {
+                balance: allocation.balance,
+                ..Account::new(allocation.address)
+            })
+            .collect()
+    }
+
+    // Genesis block committing to the state root of the initial accounts
+    pub fn genesis_block(&self, state_root: String) -> DataBlock {
+        let mut block = DataBlock::new(0, GENESIS_PREVIOUS_HASH.to_string(), vec![]);
+        block.header.timestamp = self.timestamp;
+        block.header.bits = self.bits;
+        block.set_state_root(state_root);
+        block
+    }
+}
@@ -0,0 +1,257 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+use super::address::Address;
+use super::amount::Amount;
+use super::state_db::StateDB;
+use super::transaction::BlockTransaction;
+
+// Limits applied to the pending transaction pool
+#[derive(Debug, Clone)]
+pub struct MempoolConfig {
+    pub max_transactions: usize, // Pool size; the lowest-fee entries are evicted beyond it
+    pub max_per_sender: usize,   // Pending transactions allowed per sender
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        MempoolConfig {
+            max_transactions: 10_000,
+            max_per_sender: 64,
+        }
+    }
+}
+
+// Pool of validated transactions waiting to be mined. Each sender's entries
+// form a gap-free nonce sequence starting at the sender's account nonce.
+#[derive(Debug, Default)]
+pub struct Mempool {
+    config: MempoolConfig,
+    transactions: HashMap<String, BlockTransaction>, // Transaction hash -> transaction
+    senders: HashMap<Address, BTreeMap<u64, String>>, // Sender -> nonce -> transaction hash
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Mempool {
+            config,
+            transactions: HashMap::new(),
+            senders: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    pub fn contains(&self, tx_hash: &str) -> bool {
+        self.transactions.contains_key(tx_hash)
+    }
+
+    pub fn get(&self, tx_hash: &str) -> Option<&BlockTransaction> {
+        self.transactions.get(tx_hash)
+    }
+
+    // Nonce the sender's next transaction must use, after its pending ones
+    pub fn next_nonce(&self, accounts: &StateDB, sender: &Address) -> u64 {
+        let pending = self.senders.get(sender).map_or(0, |nonces| nonces.len());
+        accounts.nonce(sender) + pending as u64
+    }
+
+    // Validate a transaction against the current state and add it to the pool.
+    // Returns the transaction hash.
+    pub fn submit(
+        &mut self,
+        tx: BlockTransaction,
+        accounts: &StateDB,
+        chain_id: u64,
+    ) -> Result<String, String> {
+        let tx_hash = tx.compute_hash();
+        if self.transactions.contains_key(&tx_hash) {
+            return Err(format!("Transaction {} is already pending", tx_hash));
+        }
+
+        if tx.chain_id != chain_id {
+            return Err(format!(
+                "Transaction is for chain {}, expected {}",
+                tx.chain_id, chain_id
+            ));
+        }
+
+        // Signature and balance checks, as in `BlockTransaction::is_valid`
+        tx.validate(accounts)?;
+
+        let account_nonce = accounts.nonce(&tx.sender);
+        if tx.nonce < account_nonce {
+            return Err(format!(
+                "Nonce {} is stale (account nonce is {})",
+                tx.nonce, account_nonce
+            ));
+        }
+
+        let pending = self.senders.get(&tx.sender);
+        let pending_count = pending.map_or(0, |nonces| nonces.len());
+
+        // Same sender and nonce: only a higher fee may replace the pending entry
+        if let Some(existing_hash) = pending.and_then(|nonces| nonces.get(&tx.nonce)) {
+            let existing_fee = self.transactions[existing_hash].fee;
+            if tx.fee <= existing_fee {
+                return Err(format!(
+                    "Replacement for nonce {} must pay more than {}",
+                    tx.nonce, existing_fee
+                ));
+            }
+            let existing_hash = existing_hash.clone();
+            self.remove(&existing_hash);
+            self.insert(tx_hash.clone(), tx);
+            return Ok(tx_hash);
+        }
+
+        let next_nonce = account_nonce + pending_count as u64;
+        if tx.nonce > next_nonce {
+            return Err(format!(
+                "Nonce {} leaves a gap (next expected nonce is {})",
+                tx.nonce, next_nonce
+            ));
+        }
+
+        if pending_count >= self.config.max_per_sender {
+            return Err(format!(
+                "Sender already has {} pending transactions",
+                pending_count
+            ));
+        }
+
+        if self.transactions.len() >= self.config.max_transactions {
+            self.evict_for(&tx)?;
+        }
+
+        self.insert(tx_hash.clone(), tx);
+        Ok(tx_hash)
+    }
+
+    // Pick the best transactions for the next block: the highest fee ready
+    // transaction first, respecting each sender's nonce order, and only those
+    // that still apply cleanly on top of the ones already picked. They are
+    // tried against `accounts` under a snapshot that is reverted before returning.
+    pub fn block_template(
+        &self,
+        accounts: &mut StateDB,
+        max_transactions: usize,
+    ) -> Vec<BlockTransaction> {
+        let template_snapshot = accounts.snapshot();
+        let mut template = vec![];
+
+        // Each sender contributes its lowest pending nonce, if that nonce is next in line
+        let mut ready = BinaryHeap::new();
+        for (sender, nonces) in &self.senders {
+            if let Some((&nonce, hash)) = nonces.iter().next() {
+                if nonce == accounts.nonce(sender) {
+                    ready.push(self.priority(hash));
+                }
+            }
+        }
+
+        while template.len() < max_transactions {
+            let (_, Reverse(hash)) = match ready.pop() {
+                Some(entry) => entry,
+                None => break,
+            };
+            let tx = &self.transactions[&hash];
+
+            // A failing transaction blocks the rest of its sender's sequence
+            let snapshot = accounts.snapshot();
+            let applied = tx.validate(accounts).and_then(|_| tx.execute(accounts));
+            if applied.is_err() {
+                accounts.revert(snapshot);
+                continue;
+            }
+            accounts.commit(snapshot);
+
+            if let Some(next_hash) = self.senders[&tx.sender].get(&(tx.nonce + 1)) {
+                ready.push(self.priority(next_hash));
+            }
+            template.push(tx.clone());
+        }
+
+        accounts.revert(template_snapshot);
+        template
+    }
+
+    // Drop transactions that made it into a block
+    pub fn remove_included(&mut self, txns: &[BlockTransaction]) {
+        for tx in txns {
+            self.remove(&tx.compute_hash());
+        }
+    }
+
+    // Drop transactions whose nonce has already been used on chain
+    pub fn prune(&mut self, accounts: &StateDB) {
+        let stale: Vec<String> = self
+            .transactions
+            .iter()
+            .filter(|(_, tx)| tx.nonce < accounts.nonce(&tx.sender))
+            .map(|(hash, _)| hash.clone())
+            .collect();
+
+        for hash in stale {
+            self.remove(&hash);
+        }
+    }
+
+    // Make room for `tx` by evicting the lowest-fee entry in the pool. The rest
+    // of that sender's sequence goes with it, since it could not be mined
+    // without the evicted nonce and would otherwise sit behind a gap. Entries of
+    // `tx`'s own sender are never evicted, for the same reason.
+    fn evict_for(&mut self, tx: &BlockTransaction) -> Result<(), String> {
+        let candidate = self
+            .transactions
+            .iter()
+            .filter(|(_, pending)| pending.sender != tx.sender)
+            .map(|(hash, _)| self.priority(hash))
+            .min();
+
+        match candidate {
+            Some((fee, Reverse(hash))) if fee < tx.fee => {
+                let evicted = &self.transactions[&hash];
+                let sequence: Vec<String> = self.senders[&evicted.sender]
+                    .range(evicted.nonce..)
+                    .map(|(_, hash)| hash.clone())
+                    .collect();
+                for hash in sequence {
+                    self.remove(&hash);
+                }
+                Ok(())
+            }
+            _ => Err("Mempool is full and the transaction fee is too low".to_string()),
+        }
+    }
+
+    // Heap ordering: highest fee first, ties broken by the smallest hash
+    fn priority(&self, hash: &str) -> (Amount, Reverse<String>) {
+        (self.transactions[hash].fee, Reverse(hash.to_string()))
+    }
+
+    fn insert(&mut self, tx_hash: String, tx: BlockTransaction) {
+        self.senders
+            .entry(tx.sender)
+            .or_default()
+            .insert(tx.nonce, tx_hash.clone());
+        self.transactions.insert(tx_hash, tx);
+    }
+
+    fn remove(&mut self, tx_hash: &str) -> Option<BlockTransaction> {
+        let tx = self.transactions.remove(tx_hash)?;
+        if let Some(nonces) = self.senders.get_mut(&tx.sender) {
+            nonces.remove(&tx.nonce);
+            if nonces.is_empty() {
+                self.senders.remove(&tx.sender);
+            }
+        }
+        Some(tx)
+    }
+}
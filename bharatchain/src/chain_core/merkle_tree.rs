@@ -1,183 +1,367 @@
-// use serde::{Deserialize, Serialize};
-// use sha3::{Digest, Keccak256};
-// use std::collections::HashMap;
-// use std::fmt;
-
-// // Define the types of nodes in the tree
-// #[derive(Debug, Clone, Serialize, Deserialize)]
-// pub enum Node {
-//     Leaf {
-//         key: String,
-//         value: String,
-//     },
-//     Extension {
-//         prefix: String,
-//         child: String,
-//     },
-//     Branch {
-//         children: [Option<String>; 16], // this stores niggle of 16 char long hash
-//         value: Option<String>,
-//     },
-// }
-
-// pub struct MerklePatriciaTree {
-//     root: String,                 // The root hash of the tree
-//     nodes: HashMap<String, Node>, // Store nodes by their hash
-// }
-
-// impl MerklePatriciaTree {
-//     // Create a new, empty Merkle Patricia Tree
-//     pub fn new() -> Self {
-//         MerklePatriciaTree {
-//             root: String::new(),
-//             nodes: HashMap::new(),
-//         }
-//     }
-
-//     // Insert a key-value pair into the Merkle Patricia Tree
-//     pub fn insert(&mut self, key: String, value: String) {
-//         let node_hash = self.insert_node(&self.root, &key, &value);
-//         self.root = node_hash;
-//     }
-
-//     fn insert_node(&mut self, node_hash: &str, key: &str, value: &str) -> String {
-//         if node_hash.is_empty() {
-//             return self.create_leaf(key, value);
-//         }
-
-//         let node = self.nodes.get(node_hash).unwrap().clone();
-
-//         match node {
-//             Node::Leaf {
-//                 key: node_key,
-//                 value: node_value,
-//             } => {
-//                 if node_key == *key {
-//                     return self.create_leaf(key, value);
-//                 } else {
-//                     let new_branch = self.create_branch(node_key, node_value, key, value);
-//                     self.create_hash(new_branch)
-//                 }
-//             }
-//             Node::Extension { prefix, child } => {
-//                 if key.starts_with(&prefix) {
-//                     let child_hash = self.insert_node(&child, &key[prefix.len()..], value);
-//                     let new_extension = Node::Extension {
-//                         prefix,
-//                         child: child_hash,
-//                     };
-//                     return self.create_hash(new_extension);
-//                 } else {
-//                     let new_branch = self.create_branch(value, new_key, new_value)
-//                     return self.create_hash(new_branch);
-//                 }
-//             }
-//             Node::Branch {
-//                 mut children,
-//                 value: branch_value,
-//             } => {
-//                 let idx = key.chars().next().unwrap_or('0') as usize;
-//                 let child_hash =
-//                     self.insert_node(&children[idx].as_deref().unwrap_or(""), &key[1..], value);
-//                 children[idx] = Some(child_hash);
-//                 let new_branch = Node::Branch {
-//                     children,
-//                     value: Some(value.to_string()),
-//                 };
-//                 self.create_hash(new_branch)
-//             }
-//         }
-//     }
-
-//     // Helper function to create a leaf node (key-value pair)
-//     fn create_leaf(&mut self, key: &str, value: &str) -> String {
-//         let leaf = Node::Leaf {
-//             key: key.to_string(),
-//             value: value.to_string(),
-//         };
-//         self.create_hash(leaf)
-//     }
-
-//     // Create a branch node (with 16 possible children)
-//     fn create_branch(&mut self, value: String, new_key: &str, new_value: &str) -> Node {
-//         let mut children = [
-//             None, None, None, None, None, None, None, None, None, None, None, None, None, None,
-//             None, None,
-//         ];
-//         children[0] = Some(self.create_leaf(new_key, new_value));
-//         Node::Branch {
-//             children,
-//             value: Some(value),
-//         }
-//     }
-
-//     // Generate a hash for a node (using Keccak256)
-//     fn create_hash(&mut self, node: Node) -> String {
-//         let serialized_node = serde_json::to_string(&node).unwrap();
-//         let hash = Keccak256::digest(serialized_node.as_bytes());
-//         let hash_str = format!("{:x}", hash);
-//         self.nodes.insert(hash_str.clone(), node);
-//         hash_str
-//     }
-
-//     // Retrieve a value for a given key from the tree
-//     pub fn get(&self, key: &str) -> Option<String> {
-//         self.get_node(&self.root, key)
-//     }
-
-//     fn get_node(&self, node_hash: &str, key: &str) -> Option<String> {
-//         let node = self.nodes.get(node_hash)?;
-
-//         match node {
-//             Node::Leaf {
-//                 key: node_key,
-//                 value,
-//             } => {
-//                 if node_key == key {
-//                     Some(value.clone())
-//                 } else {
-//                     None
-//                 }
-//             }
-//             Node::Extension { prefix, child } => {
-//                 if key.starts_with(prefix) {
-//                     self.get_node(child, &key[prefix.len()..])
-//                 } else {
-//                     None
-//                 }
-//             }
-//             Node::Branch { children, value } => {
-//                 let idx = key.chars().next().unwrap_or('0') as usize;
-//                 match &children[idx] {
-//                     Some(child_hash) => self.get_node(child_hash, &key[1..]),
-//                     None => value.clone(),
-//                 }
-//             }
-//         }
-//     }
-// }
-
-// impl fmt::Debug for MerklePatriciaTree {
-//     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-//         write!(f, "Root Hash: {}", self.root)
-//     }
-// }
-
-// // Main function to demonstrate usage
-// fn main() {
-//     let mut tree = MerklePatriciaTree::new();
-
-//     // Insert key-value pairs
-//     tree.insert("key1".to_string(), "value1".to_string());
-//     tree.insert("key2".to_string(), "value2".to_string());
-
-//     // Retrieve value for a key
-//     if let Some(value) = tree.get("key1") {
-//         println!("Found value: {}", value);
-//     } else {
-//         println!("Key not found");
-//     }
-
-//     // Display the root hash of the tree
-//     println!("{:?}", tree);
-// }
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// Define the types of nodes in the tree. Paths are strings of hex nibbles
+// ('0'..='f'), one character per nibble of the key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Node {
+    Leaf {
+        path: String, // Remaining key nibbles below the parent
+        value: String,
+    },
+    Extension {
+        prefix: String, // Shared nibbles, always followed by a branch
+        child: String,
+    },
+    Branch {
+        children: Box<[Option<String>; 16]>, // Child hash for each of the 16 nibble values
+        value: Option<String>,               // Value of the key that ends at this branch
+    },
+}
+
+// Hex-nibble Merkle Patricia Trie. Nodes are immutable and stored by their
+// Keccak-256 hash, so the trie shape (and therefore the root) depends only on
+// the set of keys and values, never on insertion order. Nodes replaced by an
+// update are kept until `prune`, so until then any earlier root can be restored
+// with `set_root`.
+#[derive(Clone, Default)]
+pub struct MerklePatriciaTree {
+    root: Option<String>,         // The root hash of the tree, `None` when empty
+    nodes: HashMap<String, Node>, // Store nodes by their hash
+}
+
+impl MerklePatriciaTree {
+    // Create a new, empty Merkle Patricia Tree
+    pub fn new() -> Self {
+        MerklePatriciaTree {
+            root: None,
+            nodes: HashMap::new(),
+        }
+    }
+
+    // Root hash of the empty trie: Keccak-256 of the empty string
+    pub fn empty_root() -> String {
+        format!("{:x}", Keccak256::digest([]))
+    }
+
+    // Deterministic root hash of the current contents
+    pub fn root_hash(&self) -> String {
+        self.root.clone().unwrap_or_else(Self::empty_root)
+    }
+
+    // Move the trie back (or forward) to a root produced earlier by this tree
+    pub fn set_root(&mut self, root: &str) -> Result<(), String> {
+        if root == Self::empty_root() {
+            self.root = None;
+            return Ok(());
+        }
+        if !self.nodes.contains_key(root) {
+            return Err(format!("Unknown trie root {}", root));
+        }
+        self.root = Some(root.to_string());
+        Ok(())
+    }
+
+    // Drop every node that is not reachable from the current root, e.g. the
+    // nodes replaced by earlier updates. Earlier roots can no longer be
+    // restored afterwards.
+    pub fn prune(&mut self) {
+        let mut live = HashSet::new();
+        let mut pending: Vec<String> = self.root.iter().cloned().collect();
+
+        while let Some(hash) = pending.pop() {
+            match &self.nodes[&hash] {
+                Node::Leaf { .. } => {}
+                Node::Extension { child, .. } => pending.push(child.clone()),
+                Node::Branch { children, .. } => pending.extend(children.iter().flatten().cloned()),
+            }
+            live.insert(hash);
+        }
+
+        self.nodes.retain(|hash, _| live.contains(hash));
+    }
+
+    // Number of nodes held, including replaced ones that have not been pruned
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // Insert a key-value pair into the Merkle Patricia Tree
+    pub fn insert(&mut self, key: &str, value: String) {
+        let path = hex::encode(key);
+        let root = self.root.clone();
+        self.root = Some(self.insert_node(root.as_deref(), &path, value));
+    }
+
+    // Retrieve a value for a given key from the tree
+    pub fn get(&self, key: &str) -> Option<String> {
+        let path = hex::encode(key);
+        self.get_node(self.root.as_deref()?, &path)
+    }
+
+    // Remove a key, returning whether it was present
+    pub fn delete(&mut self, key: &str) -> bool {
+        let path = hex::encode(key);
+        let root = match self.root.clone() {
+            Some(root) => root,
+            None => return false,
+        };
+
+        let (new_root, removed) = self.delete_node(&root, &path);
+        self.root = new_root;
+        removed
+    }
+
+    fn insert_node(&mut self, node_hash: Option<&str>, path: &str, value: String) -> String {
+        let node_hash = match node_hash {
+            Some(hash) => hash,
+            None => return self.create_leaf(path, value),
+        };
+
+        match self.nodes[node_hash].clone() {
+            Node::Leaf {
+                path: leaf_path,
+                value: leaf_value,
+            } => {
+                if leaf_path == path {
+                    return self.create_leaf(path, value);
+                }
+
+                // Split the leaf into a branch holding both keys
+                let common = common_prefix_len(&leaf_path, path);
+                let mut children: Box<[Option<String>; 16]> = Default::default();
+                let mut branch_value = None;
+
+                for (rest, value) in [(&leaf_path[common..], leaf_value), (&path[common..], value)]
+                {
+                    match rest.chars().next() {
+                        Some(nibble) => {
+                            children[nibble_index(nibble)] =
+                                Some(self.create_leaf(&rest[1..], value))
+                        }
+                        None => branch_value = Some(value),
+                    }
+                }
+
+                let branch = self.create_hash(Node::Branch {
+                    children,
+                    value: branch_value,
+                });
+                self.create_extension(&path[..common], branch)
+            }
+            Node::Extension { prefix, child } => {
+                let common = common_prefix_len(&prefix, path);
+                if common == prefix.len() {
+                    let child_hash = self.insert_node(Some(&child), &path[common..], value);
+                    return self.create_extension(&prefix, child_hash);
+                }
+
+                // The key diverges inside the extension: split it around a new branch
+                let mut children: Box<[Option<String>; 16]> = Default::default();
+                let mut branch_value = None;
+
+                let prefix_rest = &prefix[common..];
+                children[nibble_index(prefix_rest.chars().next().unwrap())] =
+                    Some(self.create_extension(&prefix_rest[1..], child));
+
+                let path_rest = &path[common..];
+                match path_rest.chars().next() {
+                    Some(nibble) => {
+                        children[nibble_index(nibble)] =
+                            Some(self.create_leaf(&path_rest[1..], value))
+                    }
+                    None => branch_value = Some(value),
+                }
+
+                let branch = self.create_hash(Node::Branch {
+                    children,
+                    value: branch_value,
+                });
+                self.create_extension(&path[..common], branch)
+            }
+            Node::Branch {
+                mut children,
+                value: branch_value,
+            } => {
+                let branch_value = match path.chars().next() {
+                    Some(nibble) => {
+                        let idx = nibble_index(nibble);
+                        let child = children[idx].take();
+                        children[idx] = Some(self.insert_node(child.as_deref(), &path[1..], value));
+                        branch_value
+                    }
+                    None => Some(value),
+                };
+
+                self.create_hash(Node::Branch {
+                    children,
+                    value: branch_value,
+                })
+            }
+        }
+    }
+
+    // Returns the new subtree hash (`None` if it became empty) and whether the key was found
+    fn delete_node(&mut self, node_hash: &str, path: &str) -> (Option<String>, bool) {
+        let unchanged = (Some(node_hash.to_string()), false);
+
+        match self.nodes[node_hash].clone() {
+            Node::Leaf {
+                path: leaf_path, ..
+            } => {
+                if leaf_path == path {
+                    (None, true)
+                } else {
+                    unchanged
+                }
+            }
+            Node::Extension { prefix, child } => {
+                if !path.starts_with(&prefix) {
+                    return unchanged;
+                }
+
+                match self.delete_node(&child, &path[prefix.len()..]) {
+                    (_, false) => unchanged,
+                    (None, true) => (None, true),
+                    (Some(child_hash), true) => (Some(self.join_path(&prefix, &child_hash)), true),
+                }
+            }
+            Node::Branch {
+                mut children,
+                mut value,
+            } => {
+                match path.chars().next() {
+                    Some(nibble) => {
+                        let idx = nibble_index(nibble);
+                        let child = match &children[idx] {
+                            Some(child) => child.clone(),
+                            None => return unchanged,
+                        };
+                        let (new_child, removed) = self.delete_node(&child, &path[1..]);
+                        if !removed {
+                            return unchanged;
+                        }
+                        children[idx] = new_child;
+                    }
+                    None => {
+                        if value.take().is_none() {
+                            return unchanged;
+                        }
+                    }
+                }
+
+                (Some(self.collapse_branch(children, value)), true)
+            }
+        }
+    }
+
+    // Rebuild a branch after a deletion, collapsing it if it has a single entry left
+    fn collapse_branch(
+        &mut self,
+        children: Box<[Option<String>; 16]>,
+        value: Option<String>,
+    ) -> String {
+        let mut remaining = children
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, child)| child.as_ref().map(|hash| (idx, hash.clone())));
+
+        match (remaining.next(), remaining.next(), value) {
+            (None, _, Some(value)) => self.create_leaf("", value),
+            (Some((idx, child)), None, None) => {
+                let nibble = format!("{:x}", idx);
+                self.join_path(&nibble, &child)
+            }
+            (_, _, value) => self.create_hash(Node::Branch { children, value }),
+        }
+    }
+
+    // Prepend `prefix` to the subtree at `child`, merging it into leaves and extensions
+    fn join_path(&mut self, prefix: &str, child: &str) -> String {
+        match self.nodes[child].clone() {
+            Node::Leaf { path, value } => self.create_leaf(&format!("{}{}", prefix, path), value),
+            Node::Extension {
+                prefix: child_prefix,
+                child,
+            } => self.create_extension(&format!("{}{}", prefix, child_prefix), child),
+            Node::Branch { .. } => self.create_extension(prefix, child.to_string()),
+        }
+    }
+
+    // Helper function to create a leaf node (key-value pair)
+    fn create_leaf(&mut self, path: &str, value: String) -> String {
+        let leaf = Node::Leaf {
+            path: path.to_string(),
+            value,
+        };
+        self.create_hash(leaf)
+    }
+
+    // Create an extension over `child`, or return `child` itself for an empty prefix
+    fn create_extension(&mut self, prefix: &str, child: String) -> String {
+        if prefix.is_empty() {
+            return child;
+        }
+        self.create_hash(Node::Extension {
+            prefix: prefix.to_string(),
+            child,
+        })
+    }
+
+    // Generate a hash for a node (using Keccak256)
+    fn create_hash(&mut self, node: Node) -> String {
+        let serialized_node = serde_json::to_string(&node).unwrap();
+        let hash = Keccak256::digest(serialized_node.as_bytes());
+        let hash_str = format!("{:x}", hash);
+        self.nodes.insert(hash_str.clone(), node);
+        hash_str
+    }
+
+    fn get_node(&self, node_hash: &str, path: &str) -> Option<String> {
+        let node = self.nodes.get(node_hash)?;
+
+        match node {
+            Node::Leaf {
+                path: leaf_path,
+                value,
+            } => {
+                if leaf_path == path {
+                    Some(value.clone())
+                } else {
+                    None
+                }
+            }
+            Node::Extension { prefix, child } => {
+                if path.starts_with(prefix.as_str()) {
+                    self.get_node(child, &path[prefix.len()..])
+                } else {
+                    None
+                }
+            }
+            Node::Branch { children, value } => match path.chars().next() {
+                Some(nibble) => {
+                    let child = children[nibble_index(nibble)].as_ref()?;
+                    self.get_node(child, &path[1..])
+                }
+                None => value.clone(),
+            },
+        }
+    }
+}
+
+impl fmt::Debug for MerklePatriciaTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Root Hash: {}", self.root_hash())
+    }
+}
+
+// Branch slot for a hex nibble character
+fn nibble_index(nibble: char) -> usize {
+    nibble.to_digit(16).expect("trie paths are hex nibbles") as usize
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::block::{BlockHeader, DataBlock, TransactionReceipt};
+use super::chain::{BlockStatus, SharedChain};
+use super::helper::get_current_timestamp;
+
+// Hashes a worker tries between checks of the stop flags
+const CHECK_INTERVAL: u32 = 4096;
+
+// Shared flag that stops a mining job, e.g. when a competing block for the
+// same height is accepted. Clones refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken(Arc::new(AtomicBool::new(false)))
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MiningStats {
+    pub hashes: u64,
+    pub elapsed: Duration,
+}
+
+impl MiningStats {
+    // Hashes per second over the whole job
+    pub fn hash_rate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.hashes as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MiningOutcome {
+    Mined(Box<DataBlock>, MiningStats),
+    Cancelled(MiningStats),
+}
+
+// Proof-of-work miner. Each round the 32-bit nonce space is split across the
+// worker threads (worker `i` tries nonces i, i + threads, ...); if a round ends
+// without a solution the header is changed by rolling the timestamp forward,
+// or the extra nonce when the clock has not moved, and a new round starts.
+#[derive(Debug, Clone)]
+pub struct Miner {
+    threads: usize,
+    max_nonce: u32, // Highest nonce tried in a round before the header is rolled
+}
+
+impl Default for Miner {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Miner::new(threads)
+    }
+}
+
+impl Miner {
+    pub fn new(threads: usize) -> Self {
+        Miner {
+            threads: threads.max(1),
+            max_nonce: u32::MAX,
+        }
+    }
+
+    // End each round after nonce `max_nonce` instead of the full 32-bit space.
+    // Smaller rounds are only meant for tests of the header rolling.
+    pub fn with_max_nonce(mut self, max_nonce: u32) -> Self {
+        self.max_nonce = max_nonce;
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    // Search for a nonce that puts the block hash at or below `block.header.bits`
+    pub fn mine(&self, mut block: DataBlock, cancel: &CancelToken) -> MiningOutcome {
+        let started = Instant::now();
+        let hashes = AtomicU64::new(0);
+        let stats = |hashes: &AtomicU64| MiningStats {
+            hashes: hashes.load(Ordering::Relaxed),
+            elapsed: started.elapsed(),
+        };
+
+        loop {
+            if let Some(nonce) = self.mine_round(&block.header, cancel, &hashes) {
+                block.header.nounce = nonce;
+                block.block_hash = block.calculate_hash();
+                return MiningOutcome::Mined(Box::new(block), stats(&hashes));
+            }
+
+            if cancel.is_cancelled() {
+                return MiningOutcome::Cancelled(stats(&hashes));
+            }
+
+            // Nonce space exhausted: change the header so the next round hashes new data
+            let now = get_current_timestamp();
+            if now > block.header.timestamp {
+                block.header.timestamp = now;
+            } else {
+                block.header.extra_nonce += 1;
+            }
+        }
+    }
+
+    // Try every nonce for the current header; returns the winning nonce, if any
+    fn mine_round(
+        &self,
+        header: &BlockHeader,
+        cancel: &CancelToken,
+        hashes: &AtomicU64,
+    ) -> Option<u32> {
+        let found = AtomicBool::new(false);
+        let solution = Mutex::new(None);
+
+        thread::scope(|scope| {
+            for worker in 0..self.threads {
+                let (found, solution) = (&found, &solution);
+                // Workers only need the header, never the transactions
+                let mut candidate = header.clone();
+                let step = self.threads as u32;
+                let max_nonce = self.max_nonce;
+
+                scope.spawn(move || {
+                    let mut nonce = worker as u32;
+                    let mut since_check = 0;
+                    while nonce <= max_nonce {
+                        candidate.nounce = nonce;
+                        since_check += 1;
+                        if candidate.meets_target(&candidate.hash()) {
+                            *solution.lock().unwrap() = Some(nonce);
+                            found.store(true, Ordering::Relaxed);
+                            break;
+                        }
+
+                        if since_check == CHECK_INTERVAL {
+                            hashes.fetch_add(u64::from(since_check), Ordering::Relaxed);
+                            since_check = 0;
+                            if found.load(Ordering::Relaxed) || cancel.is_cancelled() {
+                                break;
+                            }
+                        }
+
+                        nonce = match nonce.checked_add(step) {
+                            Some(next) => next,
+                            None => break,
+                        };
+                    }
+                    hashes.fetch_add(u64::from(since_check), Ordering::Relaxed);
+                });
+            }
+        });
+
+        solution.into_inner().unwrap()
+    }
+}
+
+// Mine the next block for a chain shared with other services. The chain is only
+// locked while the block is prepared and submitted, not while hashing, and the
+// job is cancelled if another block is accepted in the meantime. Returns `None`
+// if mining was cancelled or another block at the same height won.
+pub fn mine_shared(
+    chain: &SharedChain,
+    miner: &Miner,
+    max_transactions: usize,
+) -> Result<Option<Vec<TransactionReceipt>>, String> {
+    let (block, cancel) = {
+        let mut guard = chain
+            .lock()
+            .map_err(|_| "Chain lock poisoned".to_string())?;
+        let chain = &mut *guard;
+        let txns = chain
+            .mempool
+            .block_template(&mut chain.accounts, max_transactions);
+        chain.prepare_block(txns)?
+    };
+
+    match miner.mine(block, &cancel) {
+        MiningOutcome::Mined(block, _) => {
+            let status = chain
+                .lock()
+                .map_err(|_| "Chain lock poisoned".to_string())?
+                .submit_block(*block)?;
+            match status {
+                BlockStatus::Connected(receipts) => Ok(Some(receipts)),
+                _ => Ok(None),
+            }
+        }
+        MiningOutcome::Cancelled(_) => Ok(None),
+    }
+}
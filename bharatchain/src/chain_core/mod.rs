@@ -1,6 +1,17 @@
 pub mod account;
+pub mod address;
+pub mod amount;
 pub mod block;
+pub mod block_tree;
 pub mod chain;
+pub mod difficulty;
+pub mod encoding;
+pub mod genesis;
 pub mod helper;
+pub mod mempool;
 pub mod merkle_tree;
+pub mod miner;
+pub mod reward;
+pub mod state_db;
+pub mod storage;
 pub mod transaction;
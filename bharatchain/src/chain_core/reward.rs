@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+use super::amount::Amount;
+
+// Block subsidy paid to miners, halving every `halving_interval` blocks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewardSchedule {
+    pub initial_subsidy: Amount,
+    pub halving_interval: u64,
+}
+
+impl Default for RewardSchedule {
+    fn default() -> Self {
+        RewardSchedule {
+            initial_subsidy: Amount::from_base_units(50 * super::amount::UNITS_PER_COIN),
+            halving_interval: 210_000,
+        }
+    }
+}
+
+impl RewardSchedule {
+    // Newly minted amount a block at `block_number` may claim
+    pub fn subsidy_at(&self, block_number: u64) -> Amount {
+        let halvings = block_number.checked_div(self.halving_interval).unwrap_or(0);
+        if halvings >= 64 {
+            return Amount::ZERO;
+        }
+        Amount::from_base_units(self.initial_subsidy.base_units() >> halvings)
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use super::account::Account;
+use super::address::Address;
+
+// An open snapshot of a `StateDB`, returned by `snapshot`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "a snapshot must be committed or reverted"]
+pub struct Snapshot(usize);
+
+// Account state indexed by address. Changes made while a snapshot is open are
+// journaled with the value they replaced, so they can be reverted in one step
+// or read back as the set of accounts a block changed. Snapshots nest;
+// committing or reverting one also closes every snapshot taken after it.
+#[derive(Debug, Clone, Default)]
+pub struct StateDB {
+    accounts: HashMap<Address, Account>,
+    journal: Vec<(Address, Option<Account>)>, // Previous value of each change, oldest first
+    snapshots: Vec<usize>,                    // Journal length when each open snapshot was taken
+}
+
+impl StateDB {
+    pub fn new() -> Self {
+        StateDB::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.accounts.contains_key(address)
+    }
+
+    pub fn get(&self, address: &Address) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    pub fn get_mut(&mut self, address: &Address) -> Option<&mut Account> {
+        let account = self.accounts.get(address)?;
+        if !self.snapshots.is_empty() {
+            self.journal.push((*address, Some(account.clone())));
+        }
+        self.accounts.get_mut(address)
+    }
+
+    // Add an empty account, failing if the address already has one
+    pub fn create(&mut self, address: Address) -> Result<&mut Account, String> {
+        if self.contains(&address) {
+            return Err(format!("Account {} already exists", address));
+        }
+        Ok(self.get_or_create(address))
+    }
+
+    // The account for `address`, created empty if it does not exist yet
+    pub fn get_or_create(&mut self, address: Address) -> &mut Account {
+        if self.contains(&address) {
+            return self.get_mut(&address).expect("account exists");
+        }
+        if !self.snapshots.is_empty() {
+            self.journal.push((address, None));
+        }
+        self.accounts
+            .entry(address)
+            .or_insert_with(|| Account::new(address))
+    }
+
+    // Put `account` in place of any account with the same address
+    pub fn insert(&mut self, account: Account) -> Option<Account> {
+        let previous = self.accounts.insert(account.address, account.clone());
+        if !self.snapshots.is_empty() {
+            self.journal.push((account.address, previous.clone()));
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, address: &Address) -> Option<Account> {
+        let previous = self.accounts.remove(address)?;
+        if !self.snapshots.is_empty() {
+            self.journal.push((*address, Some(previous.clone())));
+        }
+        Some(previous)
+    }
+
+    // Next nonce the account must use; unknown accounts start at 0
+    pub fn nonce(&self, address: &Address) -> u64 {
+        self.get(address).map_or(0, |account| account.nonce)
+    }
+
+    // Every account, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = &Account> {
+        self.accounts.values()
+    }
+
+    pub fn snapshot(&mut self) -> Snapshot {
+        self.snapshots.push(self.journal.len());
+        Snapshot(self.snapshots.len() - 1)
+    }
+
+    // Keep the changes made since `snapshot`. They stay journaled while an
+    // older snapshot is open, so it can still revert them.
+    pub fn commit(&mut self, snapshot: Snapshot) {
+        self.close(snapshot);
+        if self.snapshots.is_empty() {
+            self.journal.clear();
+        }
+    }
+
+    // Undo every change made since `snapshot`
+    pub fn revert(&mut self, snapshot: Snapshot) {
+        let start = self.close(snapshot);
+        for (address, previous) in self.journal.drain(start..).rev() {
+            match previous {
+                Some(account) => self.accounts.insert(address, account),
+                None => self.accounts.remove(&address),
+            };
+        }
+    }
+
+    // Accounts changed since `snapshot`, each with its value when the snapshot
+    // was taken (`None` for accounts created since). Applying these with
+    // `restore` undoes the changes even after they are committed.
+    pub fn changes_since(&self, snapshot: Snapshot) -> Vec<(Address, Option<Account>)> {
+        let start = self.snapshots[snapshot.0];
+        let mut seen = HashSet::new();
+        self.journal[start..]
+            .iter()
+            .filter(|(address, _)| seen.insert(*address))
+            .cloned()
+            .collect()
+    }
+
+    // Put back the accounts recorded by `changes_since`
+    pub fn restore(&mut self, changes: Vec<(Address, Option<Account>)>) {
+        for (address, previous) in changes {
+            match previous {
+                Some(account) => {
+                    self.insert(account);
+                }
+                None => {
+                    self.remove(&address);
+                }
+            }
+        }
+    }
+
+    // Close `snapshot` and every later one, returning where its journal starts
+    fn close(&mut self, snapshot: Snapshot) -> usize {
+        assert!(
+            snapshot.0 < self.snapshots.len(),
+            "snapshot was already closed"
+        );
+        let start = self.snapshots[snapshot.0];
+        self.snapshots.truncate(snapshot.0);
+        start
+    }
+}
+
+impl FromIterator<Account> for StateDB {
+    fn from_iter<I: IntoIterator<Item = Account>>(accounts: I) -> Self {
+        StateDB {
+            accounts: accounts
+                .into_iter()
+                .map(|account| (account.address, account))
+                .collect(),
+            ..StateDB::default()
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::block::DataBlock;
+use super::encoding;
+
+// Name of the append-only block log inside the store directory
+const LOG_FILE: &str = "blocks.log";
+
+// Every record is laid out as [payload length: u32 BE][crc32 of payload: u32 BE][payload],
+// where the payload is the canonical encoding of the block
+const RECORD_HEADER_LEN: u64 = 8;
+
+// Append-only on-disk log of every block that has been on the main chain, in
+// the order they were connected, so replaying it reproduces any
+// reorganizations. Since the log also holds blocks that were later
+// disconnected, lookup by block number goes through the chain's main chain
+// rather than the store. Each record carries a checksum so a torn final record
+// left by a crash is detected and truncated on open; damage anywhere before the
+// end of the log is reported instead of discarding blocks. The log is locked
+// while the store is open.
+#[derive(Debug)]
+pub struct BlockStore {
+    path: PathBuf,
+    file: File,
+    offsets: Vec<u64>,                  // Offset of each record, in log order
+    hash_index: HashMap<String, usize>, // Block hash -> record index
+}
+
+impl BlockStore {
+    // Open (or create) the store in `dir`, rebuilding the indexes and dropping
+    // a torn record at the end of the log.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(dir.as_ref())?;
+        let path = dir.as_ref().join(LOG_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+
+        // Only one process may use a store: another writer would interleave
+        // records, and a reader could truncate a record still being written
+        file.try_lock().map_err(|e| match e {
+            TryLockError::WouldBlock => io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is in use by another process", path.display()),
+            ),
+            TryLockError::Error(e) => e,
+        })?;
+
+        let mut store = BlockStore {
+            path,
+            file: file.try_clone()?,
+            offsets: vec![],
+            hash_index: HashMap::new(),
+        };
+
+        let file_len = file.metadata()?.len();
+        let mut offset = 0;
+        file.seek(SeekFrom::Start(0))?;
+
+        while offset < file_len {
+            match read_record(&mut file)? {
+                Some(block) => {
+                    store.index_block(&block, offset)?;
+                    offset = file.stream_position()?;
+                }
+                None => {
+                    eprintln!(
+                        "Block store: truncating torn write at offset {} of {}",
+                        offset,
+                        store.path.display()
+                    );
+                    file.set_len(offset)?;
+                    file.sync_all()?;
+                    break;
+                }
+            }
+        }
+
+        Ok(store)
+    }
+
+    // Append a block and flush it to disk before returning
+    pub fn append(&mut self, block: &DataBlock) -> io::Result<()> {
+        self.check_order(block)?;
+        let payload = encoding::encode(block);
+        let offset = self.file.seek(SeekFrom::End(0))?;
+
+        let mut record = Vec::with_capacity(RECORD_HEADER_LEN as usize + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        record.extend_from_slice(&crc32fast::hash(&payload).to_be_bytes());
+        record.extend_from_slice(&payload);
+
+        if let Err(e) = self
+            .file
+            .write_all(&record)
+            .and_then(|_| self.file.sync_data())
+        {
+            // Drop any part of the record that reached the file so the next
+            // append does not land after a torn record
+            let _ = self.file.set_len(offset);
+            return Err(e);
+        }
+
+        self.index_block(block, offset)
+    }
+
+    // Number of records in the store
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    // Record at `index` in log order
+    pub fn get(&self, index: usize) -> io::Result<Option<DataBlock>> {
+        match self.offsets.get(index) {
+            Some(&offset) => self.read_at(offset).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_by_hash(&self, block_hash: &str) -> io::Result<Option<DataBlock>> {
+        match self.hash_index.get(block_hash) {
+            Some(&index) => self.get(index),
+            None => Ok(None),
+        }
+    }
+
+    // Load every block in log order, e.g. to replay the chain on startup
+    pub fn load_all(&self) -> io::Result<Vec<DataBlock>> {
+        (0..self.len())
+            .map(|index| {
+                self.get(index)?
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "missing block"))
+            })
+            .collect()
+    }
+
+    fn index_block(&mut self, block: &DataBlock, offset: u64) -> io::Result<()> {
+        self.check_order(block)?;
+        self.hash_index
+            .insert(block.block_hash.clone(), self.offsets.len());
+        self.offsets.push(offset);
+        Ok(())
+    }
+
+    // Every block after the first must follow its parent, and appear only once
+    fn check_order(&self, block: &DataBlock) -> io::Result<()> {
+        let out_of_order = if self.offsets.is_empty() {
+            block.header.block_number != 0
+        } else {
+            !self.hash_index.contains_key(&block.header.previous_hash)
+                || self.hash_index.contains_key(&block.block_hash)
+        };
+        if out_of_order {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Block store out of order: block {} ({}) at record {}",
+                    block.header.block_number,
+                    block.block_hash,
+                    self.offsets.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64) -> io::Result<DataBlock> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        read_record(&mut file)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Corrupted block record"))
+    }
+}
+
+// Read one record at the current position. Returns `None` if the record runs
+// past the end of the file, or is the last record and fails its checksum: both
+// are a torn final write. A bad checksum with more records after it, or an
+// intact record that does not decode, is an error so no valid block is dropped.
+fn read_record(file: &mut File) -> io::Result<Option<DataBlock>> {
+    let mut header = [0u8; RECORD_HEADER_LEN as usize];
+    if !read_full(file, &mut header)? {
+        return Ok(None);
+    }
+
+    let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let checksum = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+
+    // A garbage length from a torn header must not trigger a huge allocation
+    let position = file.stream_position()?;
+    if position + len as u64 > file.metadata()?.len() {
+        return Ok(None);
+    }
+
+    let mut payload = vec![0u8; len];
+    if !read_full(file, &mut payload)? {
+        return Ok(None);
+    }
+    if crc32fast::hash(&payload) != checksum {
+        if file.stream_position()? == file.metadata()?.len() {
+            return Ok(None);
+        }
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Block record at offset {} fails its checksum",
+                position - RECORD_HEADER_LEN
+            ),
+        ));
+    }
+
+    encoding::decode(&payload).map(Some).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Block record does not decode: {}", e),
+        )
+    })
+}
+
+// Like `read_exact`, but reports a short read as `false` instead of an error
+fn read_full(file: &mut File, buf: &mut [u8]) -> io::Result<bool> {
+    match file.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
@@ -1,103 +1,225 @@
+use secp256k1::ecdsa::Signature;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fmt::Debug;
 
-use super::account::Account;
+use super::address::Address;
+use super::amount::{Amount, AmountError};
+use super::block::{BLOCK_VERSION, BLOCK_VERSION_LEGACY};
+use super::encoding::{self, Decode, Decoder, Encode, Encoder};
 use super::helper;
+use super::state_db::StateDB;
+
+// Sender of the reward transaction a miner places first in each block
+pub const COINBASE_SENDER: Address = Address::ZERO;
+
+// Prefix of coinbase transaction ids, which end in the block number
+const COINBASE_ID_PREFIX: &str = "coinbase";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 
 pub struct BlockTransaction {
     pub id: String,
-    pub sender: String,
-    pub receiver: String,
-    pub amount: f64,
+    pub chain_id: u64, // Network the transaction is valid on, prevents cross-chain replay
+    pub nonce: u64,    // Must equal the sender account's nonce when the transaction is applied
+    pub sender: Address,
+    pub receiver: Address,
+    pub amount: Amount,
+    pub fee: Amount, // Offered to the miner; the mempool mines higher fees first
     pub timestamp: u64,
+    pub public_key: String, // Hex encoded compressed secp256k1 public key of the sender
+    pub signature: String,  // Hex encoded compact ECDSA signature over `signing_hash`
 }
 
 impl BlockTransaction {
-    pub fn new(sender: String, receiver: String, amount: f64) -> Self {
+    pub fn new(
+        sender: Address,
+        receiver: Address,
+        amount: Amount,
+        nonce: u64,
+        chain_id: u64,
+    ) -> Self {
         let time_stamp = helper::get_current_timestamp();
         BlockTransaction {
+            // A sender never reuses a nonce, so (sender, nonce) uniquely names the transaction
+            id: format!("{}:{}", sender, nonce),
+            chain_id,
+            nonce,
             sender,
             receiver,
             amount,
-            id: time_stamp.to_string(),
+            fee: Amount::ZERO,
             timestamp: time_stamp,
+            public_key: String::new(),
+            signature: String::new(),
         }
     }
 
-    // Helper function to create a transaction hash based on its content
+    // Reward transaction crediting `miner` with the block subsidy plus fees. The
+    // block number in the id keeps every coinbase hash unique.
+    pub fn coinbase(miner: Address, amount: Amount, block_number: u64, chain_id: u64) -> Self {
+        let mut tx = BlockTransaction::new(COINBASE_SENDER, miner, amount, block_number, chain_id);
+        tx.id = format!("{}:{}", COINBASE_ID_PREFIX, block_number);
+        tx
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.sender == COINBASE_SENDER
+    }
+
+    // Amount debited from the sender: the transfer plus the fee
+    pub fn total_cost(&self) -> Result<Amount, AmountError> {
+        self.amount.checked_add(self.fee)
+    }
+
+    // Set the fee offered to the miner (before signing)
+    pub fn with_fee(mut self, fee: Amount) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    // Transaction hash: SHA-256 of the canonical encoding of every field
     pub fn compute_hash(&self) -> String {
-        let block_data = format!(
-            "{}{}{}{}{}",
-            self.id, self.timestamp, self.receiver, self.amount, self.sender
-        );
-        let mut hasher = Sha256::new();
-        hasher.update(block_data);
-        let result = hasher.finalize();
-        format!("{:x}", result)
+        format!("{:x}", Sha256::digest(encoding::encode(self)))
     }
 
-    // Validate that the sender has enough funds
-    pub fn is_valid(&self, accounts: &mut Vec<Account>) -> bool {
-        // It sayes account creation txn received.
-        if self.sender.to_lowercase() == "system" {
-            return true;
-        }
+    // Hash the signature commits to: the canonical encoding of every field
+    // except the public key and signature themselves
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut out = Encoder::new();
+        out.put_u8(encoding::ENCODING_VERSION);
+        self.encode_unsigned(&mut out);
+        Sha256::digest(out.into_bytes()).into()
+    }
+
+    fn encode_unsigned(&self, out: &mut Encoder) {
+        out.put_str(&self.id);
+        out.put_u64(self.chain_id);
+        out.put_u64(self.nonce);
+        self.sender.encode_to(out);
+        self.receiver.encode_to(out);
+        out.put_u64(self.amount.base_units());
+        out.put_u64(self.fee.base_units());
+        out.put_u64(self.timestamp);
+    }
+
+    // Sign the transaction with the sender's secret key and attach the public key
+    pub fn sign(&mut self, secret_key: &SecretKey) {
+        let secp = Secp256k1::new();
+        let public_key = PublicKey::from_secret_key(&secp, secret_key);
+        let message = Message::from_slice(&self.signing_hash()).expect("hash is 32 bytes");
+        let signature = secp.sign_ecdsa(&message, secret_key);
 
-        let sender_account = accounts.iter_mut().find(|a| a.address == self.sender);
-        match sender_account {
-            Some(account) => account.balance >= self.amount,
-            None => false,
+        self.public_key = hex::encode(public_key.serialize());
+        self.signature = hex::encode(signature.serialize_compact());
+    }
+
+    // Check the signature and that the signing key belongs to `sender`
+    pub fn verify_signature(&self) -> Result<(), String> {
+        let public_key_bytes =
+            hex::decode(&self.public_key).map_err(|e| format!("Invalid public key hex: {}", e))?;
+        let public_key = PublicKey::from_slice(&public_key_bytes)
+            .map_err(|e| format!("Invalid public key: {}", e))?;
+
+        if Address::from_public_key(&public_key) != self.sender {
+            return Err("Public key does not match sender address".to_string());
         }
+
+        let signature_bytes =
+            hex::decode(&self.signature).map_err(|e| format!("Invalid signature hex: {}", e))?;
+        let signature = Signature::from_compact(&signature_bytes)
+            .map_err(|e| format!("Invalid signature: {}", e))?;
+
+        let message = Message::from_slice(&self.signing_hash()).expect("hash is 32 bytes");
+        Secp256k1::verification_only()
+            .verify_ecdsa(&message, &signature, &public_key)
+            .map_err(|_| "Signature verification failed".to_string())
     }
 
-    pub fn execute(&self, accounts: &mut Vec<Account>) -> Result<(), String> {
-        print!(
-            "\n ------- Executing transaction from {} -------- \n ",
-            self.sender
-        );
-        if self.sender.to_lowercase() == "system" {
-            // Special case: Create a new account
-            let acc = accounts.iter().find(|a| a.address == self.receiver);
-            match acc {
-                Some(_a) => {
-                    let new_acc: Option<Account> =
-                        Account::from_secret_key(&self.receiver, self.amount).ok();
-                    match new_acc {
-                        Some(ac) => {
-                            accounts.push(ac);
-                            println!(
-                                "Account created | at: address = {}, initial balance = {}",
-                                self.receiver, self.amount
-                            );
-                            return Ok(());
-                        }
-                        None => (),
-                    }
-                }
-                None => {
-                    println!("Transaction ABORT | Account creation failed with Rreason:- Account mismatch");
-                    ()
-                }
-            }
+    // Validate the sender's signature and that the sender has enough funds
+    pub fn is_valid(&self, accounts: &StateDB) -> bool {
+        self.validate(accounts).is_ok()
+    }
+
+    // Same checks as `is_valid`, but reports why the transaction is invalid
+    pub fn validate(&self, accounts: &StateDB) -> Result<(), String> {
+        // A coinbase is only valid as the first transaction of a block, where
+        // `DataBlock::apply_transactions` checks its claim
+        if self.is_coinbase() {
+            return Err("Coinbase transaction outside of a block".to_string());
         }
 
-        let mut mutable_accounts_list = accounts.iter_mut(); // iter_mut() for mutable reference
+        self.verify_signature()?;
 
-        let sender_account = mutable_accounts_list.find(|a| a.address == self.sender);
-        let receiver_account = mutable_accounts_list.find(|a| a.address == self.receiver);
+        let total_cost = self.total_cost().map_err(|e| e.to_string())?;
+        match accounts.get(&self.sender) {
+            Some(account) if account.balance >= total_cost => Ok(()),
+            Some(_) => Err("Insufficient funds".to_string()),
+            None => Err("Sender account not found".to_string()),
+        }
+    }
 
-        match (sender_account, receiver_account) {
-            (Some(sender), Some(receiver)) => {
-                sender.debit(self.amount)?;
-                receiver.credit(self.amount);
-                Ok(())
-            }
-            _ => Err("Sender or Receiver account not found".to_string()),
+    // Move the amount and fee out of the sender's account and the amount into
+    // the receiver's, creating it if the address has not been seen before.
+    // Sender and receiver may be the same account. An error can leave the
+    // sender debited, so callers apply transactions under a `StateDB` snapshot
+    // and revert on failure.
+    pub fn execute(&self, accounts: &mut StateDB) -> Result<(), String> {
+        let total_cost = self.total_cost().map_err(|e| e.to_string())?;
+
+        let sender = accounts
+            .get_mut(&self.sender)
+            .ok_or("Sender account not found")?;
+
+        // Each nonce is used exactly once, in order
+        if self.nonce != sender.nonce {
+            return Err(format!(
+                "Transaction {} has nonce {}, expected {}",
+                self.id, self.nonce, sender.nonce
+            ));
         }
+        sender.debit(total_cost).map_err(|e| e.to_string())?;
+        sender.nonce += 1;
+
+        accounts
+            .get_or_create(self.receiver)
+            .credit(self.amount)
+            .map_err(|e| e.to_string())
+    }
+
+    // Credit a coinbase reward to the miner, creating the miner's account if needed
+    pub fn execute_coinbase(&self, accounts: &mut StateDB) -> Result<(), String> {
+        accounts
+            .get_or_create(self.receiver)
+            .credit(self.amount)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Encode for BlockTransaction {
+    fn encode_to(&self, out: &mut Encoder) {
+        self.encode_unsigned(out);
+        out.put_str(&self.public_key);
+        out.put_str(&self.signature);
+    }
+}
+
+impl Decode for BlockTransaction {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        Ok(BlockTransaction {
+            id: input.get_str()?,
+            chain_id: input.get_u64()?,
+            nonce: input.get_u64()?,
+            sender: Address::decode_from(input)?,
+            receiver: Address::decode_from(input)?,
+            amount: Amount::from_base_units(input.get_u64()?),
+            fee: Amount::from_base_units(input.get_u64()?),
+            timestamp: input.get_u64()?,
+            public_key: input.get_str()?,
+            signature: input.get_str()?,
+        })
     }
 }
 
@@ -108,22 +230,64 @@ pub enum Node {
     Branch { children: Vec<Option<String>> },
 }
 
-// Merkle tree structure for storing transaction hashes
-#[derive(Debug)]
+// One step of an inclusion proof: the sibling hash and which side it sits on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofStep {
+    pub hash: String,
+    pub is_left: bool, // True if the sibling is the left input of the parent hash
+}
+
+// Inclusion proof for a transaction hash, ordered from the leaf up to the root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub version: u32, // Block version, which selects the Merkle construction
+    pub transaction_hash: String,
+    pub path: Vec<ProofStep>,
+}
+
+// Merkle tree structure for storing transaction hashes. The construction is
+// selected by the block version:
+// - legacy (v1): parent = SHA256(left || right) with the last hash duplicated on
+//   odd levels, which lets two different transaction lists share a root.
+// - v2: leaves and inner nodes are hashed with distinct prefixes and an odd
+//   node is promoted to the next level unchanged instead of being duplicated.
+#[derive(Debug, Default)]
 pub struct MerkleTree {
     root: String,                 // Merkle root hash
+    version: u32,                 // Block version whose construction is used
     nodes: HashMap<String, Node>, // Hashmap to store nodes by their hash
 }
 
+// Domain separation prefixes for the v2 construction
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+// Root of a legacy block without transactions
+const LEGACY_EMPTY_ROOT: &str = "0x00000000";
+
 impl MerkleTree {
-    // Create a new Merkle tree (empty)
+    // Create a new Merkle tree (empty) using the current block version
     pub fn new() -> Self {
+        Self::with_version(BLOCK_VERSION)
+    }
+
+    // Create a Merkle tree using the construction of the given block version
+    pub fn with_version(version: u32) -> Self {
         MerkleTree {
             root: String::new(),
+            version,
             nodes: HashMap::new(),
         }
     }
 
+    // Root of a tree without transactions: SHA-256 of the empty string
+    pub fn empty_root(version: u32) -> String {
+        if version == BLOCK_VERSION_LEGACY {
+            return LEGACY_EMPTY_ROOT.to_string();
+        }
+        format!("{:x}", Sha256::digest([]))
+    }
+
     // Insert transactions into the Merkle tree
     pub fn process_transactions(&mut self, transactions: Vec<BlockTransaction>) {
         let transaction_hashes: Vec<String> = transactions
@@ -135,7 +299,19 @@ impl MerkleTree {
 
     // Build the Merkle tree from transaction hashes and compute the root
     pub fn build_merkle_tree(&mut self, transaction_hashes: Vec<String>) -> String {
-        let mut current_level = transaction_hashes;
+        let mut current_level: Vec<String> = transaction_hashes
+            .into_iter()
+            .map(|transaction_hash| {
+                let leaf_hash = leaf_hash(self.version, &transaction_hash);
+                self.nodes
+                    .insert(leaf_hash.clone(), Node::Leaf { transaction_hash });
+                leaf_hash
+            })
+            .collect();
+
+        if current_level.is_empty() {
+            return Self::empty_root(self.version);
+        }
 
         // Loop until there is only one node left, the root of the tree
         while current_level.len() > 1 {
@@ -143,9 +319,13 @@ impl MerkleTree {
 
             for chunk in current_level.chunks(2) {
                 let left = chunk[0].clone();
-                let right = chunk.get(1).cloned().unwrap_or_else(|| left.clone());
-
-                let combined_hash = self.hash_pair(left, right);
+                let combined_hash = match chunk.get(1).cloned() {
+                    Some(right) => self.hash_pair(left, right),
+                    None if self.version == BLOCK_VERSION_LEGACY => {
+                        self.hash_pair(left.clone(), left)
+                    }
+                    None => left, // Promote the odd node unchanged
+                };
                 next_level.push(combined_hash);
             }
 
@@ -153,18 +333,12 @@ impl MerkleTree {
         }
 
         // The remaining item is the root of the Merkle tree
-        if current_level.len() > 0 {
-            return current_level[0].clone();
-        }
-
-        return "0x00000000".to_string();
+        current_level.remove(0)
     }
 
     // Hash two transaction hashes together to form the parent node
     fn hash_pair(&mut self, left: String, right: String) -> String {
-        let combined = format!("{}{}", left, right);
-        let hash = Sha256::digest(combined.as_bytes());
-        let hash_str = format!("{:x}", hash);
+        let hash_str = node_hash(self.version, &left, &right);
         self.nodes.insert(
             hash_str.clone(),
             Node::Branch {
@@ -178,4 +352,82 @@ impl MerkleTree {
     pub fn get_merkle_root(&self) -> String {
         self.root.clone()
     }
+
+    // Build an inclusion proof for a transaction hash by walking the recorded
+    // branch nodes from the leaf up to the root
+    pub fn get_proof(&self, transaction_hash: &str) -> Option<MerkleProof> {
+        let leaf = leaf_hash(self.version, transaction_hash);
+        if !matches!(self.nodes.get(&leaf), Some(Node::Leaf { .. })) {
+            return None;
+        }
+
+        // Map each child to its parent and sibling
+        let mut parents: HashMap<&str, (&str, ProofStep)> = HashMap::new();
+        for (parent, node) in &self.nodes {
+            if let Node::Branch { children } = node {
+                if let [Some(left), Some(right)] = children.as_slice() {
+                    let right_step = ProofStep {
+                        hash: right.clone(),
+                        is_left: false,
+                    };
+                    let left_step = ProofStep {
+                        hash: left.clone(),
+                        is_left: true,
+                    };
+                    parents.insert(left, (parent, right_step));
+                    parents.entry(right).or_insert((parent, left_step));
+                }
+            }
+        }
+
+        let mut path = vec![];
+        let mut current = leaf.as_str();
+        while let Some((parent, step)) = parents.get(current) {
+            path.push(step.clone());
+            current = parent;
+        }
+
+        Some(MerkleProof {
+            version: self.version,
+            transaction_hash: transaction_hash.to_string(),
+            path,
+        })
+    }
+}
+
+// Leaf hash of a transaction: the transaction hash itself for legacy blocks
+fn leaf_hash(version: u32, transaction_hash: &str) -> String {
+    if version == BLOCK_VERSION_LEGACY {
+        return transaction_hash.to_string();
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(transaction_hash.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+// Hash two child hashes together to form the parent hash
+fn node_hash(version: u32, left: &str, right: &str) -> String {
+    let mut hasher = Sha256::new();
+    if version != BLOCK_VERSION_LEGACY {
+        hasher.update([NODE_PREFIX]);
+    }
+    hasher.update(left.as_bytes());
+    hasher.update(right.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+// Check an inclusion proof against a block's Merkle root without the block body
+pub fn verify_merkle_proof(proof: &MerkleProof, merkle_root: &str) -> bool {
+    let leaf = leaf_hash(proof.version, &proof.transaction_hash);
+    let computed_root = proof.path.iter().fold(leaf, |current, step| {
+        if step.is_left {
+            node_hash(proof.version, &step.hash, &current)
+        } else {
+            node_hash(proof.version, &current, &step.hash)
+        }
+    });
+
+    computed_root == merkle_root
 }
@@ -0,0 +1,373 @@
+use serde_json::{json, Value};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+use super::{print_json, CliError, EXIT_INVALID_CHAIN, EXIT_NOT_FOUND};
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::DataBlock;
+use bharatchain::chain_core::chain::{BharatChain, SharedChain};
+use bharatchain::chain_core::encoding;
+use bharatchain::chain_core::genesis::GenesisConfig;
+use bharatchain::chain_core::miner::{mine_shared, Miner};
+use bharatchain::chain_core::transaction::BlockTransaction;
+use bharatchain::p2p::node::{NodeConfig, P2pNode};
+use bharatchain::rpc::client;
+use bharatchain::rpc::server::RpcServer;
+use bharatchain::wallet::Wallet;
+
+// Copy of the genesis configuration kept in the data directory
+const GENESIS_FILE: &str = "genesis.json";
+
+// Most mempool transactions put in one mined block
+const MAX_BLOCK_TRANSACTIONS: usize = 1000;
+
+pub struct NodeOptions {
+    pub p2p_addr: SocketAddr,
+    pub rpc_addr: SocketAddr,
+    pub peers: Vec<SocketAddr>,
+    pub miner_address: Option<String>,
+    pub threads: Option<usize>,
+}
+
+pub fn init(data_dir: &Path, genesis_path: &Path) -> Result<Value, CliError> {
+    let genesis_file = data_dir.join(GENESIS_FILE);
+    if genesis_file.exists() {
+        return Err(format!("{} is already initialized", data_dir.display()).into());
+    }
+
+    // The genesis file marks the directory as initialized, so it is only
+    // written once the chain has opened and a failed init can be retried
+    let genesis = GenesisConfig::load(genesis_path)?;
+    let chain = BharatChain::open(data_dir, genesis)?;
+    fs::copy(genesis_path, &genesis_file)
+        .map_err(|e| format!("Failed to write {}: {}", genesis_file.display(), e))?;
+
+    Ok(json!({
+        "dataDir": data_dir,
+        "chainId": chain.chain_id,
+        "genesisHash": chain.get_latest_block().block_hash,
+    }))
+}
+
+pub async fn run_node(
+    data_dir: &Path,
+    options: NodeOptions,
+    pretty: bool,
+) -> Result<Value, CliError> {
+    let mut chain = open_chain(data_dir)?;
+    chain.miner_address = options
+        .miner_address
+        .as_deref()
+        .map(parse_address)
+        .transpose()?;
+    let chain: SharedChain = Arc::new(Mutex::new(chain));
+
+    let p2p_listener = bind(options.p2p_addr).await?;
+    let rpc_listener = bind(options.rpc_addr).await?;
+    let p2p_addr = p2p_listener.local_addr().map_err(|e| e.to_string())?;
+    let rpc_addr = rpc_listener.local_addr().map_err(|e| e.to_string())?;
+
+    let node = P2pNode::new(chain.clone(), NodeConfig::default());
+    tokio::spawn(node.clone().serve(p2p_listener));
+    tokio::spawn(RpcServer::new(chain.clone()).serve(rpc_listener));
+    for peer in &options.peers {
+        if let Err(e) = node.connect(*peer).await {
+            eprintln!("Failed to connect to peer {}: {}", peer, e);
+        }
+    }
+
+    if options.miner_address.is_some() {
+        let miner = options.threads.map_or_else(Miner::default, Miner::new);
+        let (chain, node) = (chain.clone(), node.clone());
+        thread::spawn(move || loop {
+            // Blocks mined while catching up would only be orphaned
+            if node.is_syncing() {
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+            if let Err(e) = mine_shared(&chain, &miner, MAX_BLOCK_TRANSACTIONS) {
+                eprintln!("Mining failed: {}", e);
+                thread::sleep(Duration::from_secs(1));
+            }
+        });
+    }
+
+    let height = chain.lock().map_err(|_| "Chain lock poisoned")?.height();
+    print_json(
+        &json!({
+            "event": "started",
+            "p2pAddr": p2p_addr,
+            "rpcAddr": rpc_addr,
+            "height": height,
+            "mining": options.miner_address.is_some(),
+        }),
+        pretty,
+    );
+
+    tokio::signal::ctrl_c()
+        .await
+        .map_err(|e| format!("Failed to wait for shutdown: {}", e))?;
+    let height = chain.lock().map_err(|_| "Chain lock poisoned")?.height();
+    Ok(json!({ "event": "stopped", "height": height }))
+}
+
+pub fn mine(
+    data_dir: &Path,
+    blocks: u64,
+    miner_address: Option<String>,
+    threads: Option<usize>,
+) -> Result<Value, CliError> {
+    let mut chain = open_chain(data_dir)?;
+    chain.miner_address = miner_address.as_deref().map(parse_address).transpose()?;
+    if let Some(threads) = threads {
+        chain.miner = Miner::new(threads);
+    }
+
+    let mut mined = vec![];
+    for _ in 0..blocks {
+        chain.mine_pending_block(MAX_BLOCK_TRANSACTIONS)?;
+        let block = chain.get_latest_block();
+        mined.push(json!({
+            "blockNumber": block.header.block_number,
+            "blockHash": block.block_hash,
+        }));
+    }
+    Ok(json!({ "mined": mined, "height": chain.height() }))
+}
+
+pub struct Transfer {
+    pub from: String,
+    pub to: String,
+    pub amount: String,
+    pub fee: String,
+}
+
+pub async fn send(
+    rpc: &str,
+    keystore: &Path,
+    password: &str,
+    transfer: Transfer,
+) -> Result<Value, CliError> {
+    let sender = parse_address(&transfer.from)?;
+    let to = parse_address(&transfer.to)?;
+    let Transfer { amount, fee, .. } = transfer;
+    let amount: Amount = amount
+        .parse()
+        .map_err(|e| format!("Invalid amount: {}", e))?;
+    let fee: Amount = fee.parse().map_err(|e| format!("Invalid fee: {}", e))?;
+
+    // Unlock first, so a wrong password fails before anything is sent
+    let wallet = Wallet::open(keystore)?;
+    let secret_key = wallet.unlock(&sender, password)?;
+
+    let chain_id = client::call(rpc, "chainId", json!([]))
+        .await?
+        .as_u64()
+        .ok_or("Node returned an invalid chain id")?;
+    let nonce = client::call(rpc, "getNonce", json!([sender]))
+        .await?
+        .as_u64()
+        .ok_or("Node returned an invalid nonce")?;
+
+    let mut tx = BlockTransaction::new(sender, to, amount, nonce, chain_id).with_fee(fee);
+    tx.sign(&secret_key);
+    let raw = hex::encode(encoding::encode(&tx));
+    let tx_hash = client::call(rpc, "sendRawTransaction", json!([raw])).await?;
+
+    Ok(json!({
+        "transactionHash": tx_hash,
+        "from": sender,
+        "to": to,
+        "amount": amount,
+        "fee": fee,
+        "nonce": nonce,
+    }))
+}
+
+pub async fn balance(
+    data_dir: &Path,
+    rpc: Option<&str>,
+    address: String,
+) -> Result<Value, CliError> {
+    let address = parse_address(&address)?;
+    // Accounts the chain has never seen hold nothing
+    let balance = match query(data_dir, rpc, "getBalance", json!([address])).await? {
+        Value::Null => json!(Amount::ZERO),
+        balance => balance,
+    };
+    Ok(json!({ "address": address, "balance": balance }))
+}
+
+pub async fn block(data_dir: &Path, rpc: Option<&str>, id: &str) -> Result<Value, CliError> {
+    let block = match id.parse::<u64>() {
+        Ok(block_number) => query(data_dir, rpc, "getBlockByNumber", json!([block_number])).await?,
+        Err(_) => query(data_dir, rpc, "getBlockByHash", json!([id])).await?,
+    };
+    found(block, || format!("Block {} not found", id))
+}
+
+pub async fn transaction(
+    data_dir: &Path,
+    rpc: Option<&str>,
+    tx_hash: &str,
+) -> Result<Value, CliError> {
+    let found_tx = query(data_dir, rpc, "getTransaction", json!([tx_hash])).await?;
+    found(found_tx, || format!("Transaction {} not found", tx_hash))
+}
+
+pub fn verify_chain(data_dir: &Path) -> Result<Value, CliError> {
+    // Opening replays the stored blocks; `verify` then checks the result again from genesis
+    let genesis = load_genesis(data_dir)?;
+    let chain =
+        BharatChain::open(data_dir, genesis).map_err(|e| CliError::new(EXIT_INVALID_CHAIN, e))?;
+    chain.verify().map_err(|e| {
+        CliError::new(
+            EXIT_INVALID_CHAIN,
+            format!("Chain failed verification: {}", e),
+        )
+    })?;
+
+    let tip = chain.get_latest_block();
+    Ok(json!({
+        "valid": true,
+        "height": chain.height(),
+        "tipHash": tip.block_hash,
+        "totalWork": chain.total_work().to_string(),
+    }))
+}
+
+// Exported chains are the main chain from genesis, each block framed as
+// [length: u32 BE][canonical encoding of the block]
+pub fn export(data_dir: &Path, file: &Path) -> Result<Value, CliError> {
+    let chain = open_chain(data_dir)?;
+
+    let mut out = vec![];
+    for block_number in 0..=chain.height() {
+        let block = chain
+            .get_block_by_number(block_number)
+            .ok_or_else(|| format!("Block {} is missing", block_number))?;
+        let payload = encoding::encode(block);
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(&payload);
+    }
+    fs::write(file, out).map_err(|e| format!("Failed to write {}: {}", file.display(), e))?;
+
+    Ok(json!({
+        "file": file,
+        "blocks": chain.height() + 1,
+        "tipHash": chain.get_latest_block().block_hash,
+    }))
+}
+
+pub fn import(data_dir: &Path, file: &Path) -> Result<Value, CliError> {
+    let data = fs::read(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let mut chain = open_chain(data_dir)?;
+
+    let (mut imported, mut skipped) = (0, 0);
+    let mut rest = data.as_slice();
+    while !rest.is_empty() {
+        let block = next_block(&mut rest)?;
+        let block_number = block.header.block_number;
+
+        if block_number == 0 && block.block_hash != chain.get_block_by_number(0).unwrap().block_hash
+        {
+            return Err("Export was made from a different genesis block".into());
+        }
+        if chain.get_known_block(&block.block_hash).is_some() {
+            skipped += 1;
+            continue;
+        }
+
+        chain
+            .submit_block(block)
+            .map_err(|e| format!("Block {} was rejected: {}", block_number, e))?;
+        imported += 1;
+    }
+
+    Ok(json!({
+        "imported": imported,
+        "skipped": skipped,
+        "height": chain.height(),
+        "tipHash": chain.get_latest_block().block_hash,
+    }))
+}
+
+// Addresses are checked locally, so a mistyped one never reaches a node
+fn parse_address(value: &str) -> Result<Address, CliError> {
+    value
+        .parse()
+        .map_err(|e| CliError::from(format!("{}: {}", e, value)))
+}
+
+fn open_chain(data_dir: &Path) -> Result<BharatChain, CliError> {
+    let genesis = load_genesis(data_dir)?;
+    Ok(BharatChain::open(data_dir, genesis)?)
+}
+
+fn load_genesis(data_dir: &Path) -> Result<GenesisConfig, CliError> {
+    let genesis_file = data_dir.join(GENESIS_FILE);
+    if !genesis_file.exists() {
+        return Err(format!(
+            "{} is not initialized; run `bharatchain init` first",
+            data_dir.display()
+        )
+        .into());
+    }
+    Ok(GenesisConfig::load(genesis_file)?)
+}
+
+async fn bind(addr: SocketAddr) -> Result<TcpListener, CliError> {
+    TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to listen on {}: {}", addr, e).into())
+}
+
+// Call a JSON-RPC method on a running node, or answer it from the data
+// directory through the same handler
+async fn query(
+    data_dir: &Path,
+    rpc: Option<&str>,
+    method: &str,
+    params: Value,
+) -> Result<Value, CliError> {
+    if let Some(addr) = rpc {
+        return Ok(client::call(addr, method, params).await?);
+    }
+
+    let chain: SharedChain = Arc::new(Mutex::new(open_chain(data_dir)?));
+    let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+    let response = RpcServer::new(chain)
+        .handle_body(request.to_string().as_bytes())
+        .await
+        .ok_or("RPC handler gave no response")?;
+    Ok(client::into_result(response)?)
+}
+
+fn found(value: Value, missing: impl FnOnce() -> String) -> Result<Value, CliError> {
+    match value {
+        Value::Null => Err(CliError::new(EXIT_NOT_FOUND, missing())),
+        value => Ok(value),
+    }
+}
+
+// Decode the next framed block and advance past it
+fn next_block(rest: &mut &[u8]) -> Result<DataBlock, CliError> {
+    if rest.len() < 4 {
+        return Err("Export file ends in a truncated record".into());
+    }
+    let (len, tail) = rest.split_at(4);
+    let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    if tail.len() < len {
+        return Err("Export file ends in a truncated record".into());
+    }
+
+    let (payload, tail) = tail.split_at(len);
+    *rest = tail;
+    Ok(encoding::decode(payload).map_err(|e| format!("Invalid block in export: {}", e))?)
+}
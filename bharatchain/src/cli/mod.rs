@@ -0,0 +1,338 @@
+use clap::{Parser, Subcommand};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+mod commands;
+mod wallet;
+
+// Exit codes besides 0 for success; clap exits with 2 on usage errors
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_INVALID_CHAIN: u8 = 3;
+pub const EXIT_NOT_FOUND: u8 = 4;
+
+// A failed command: the message reported to the user and the exit code
+#[derive(Debug)]
+pub struct CliError {
+    pub code: u8,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(code: u8, message: impl Into<String>) -> Self {
+        CliError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::new(EXIT_FAILURE, message)
+    }
+}
+
+impl From<&str> for CliError {
+    fn from(message: &str) -> Self {
+        CliError::new(EXIT_FAILURE, message)
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "bharatchain",
+    version,
+    about = "BharatChain node and command-line tools",
+    after_help = "Every command prints its result as JSON on stdout, or {\"error\": ...} on stderr.\n\
+                  Exit codes: 0 success, 1 failure, 2 usage error, 3 invalid chain, 4 not found."
+)]
+pub struct Cli {
+    /// Directory holding the genesis file and the block store
+    #[arg(
+        long,
+        global = true,
+        env = "BHARATCHAIN_DATA_DIR",
+        default_value = "bharatchain-data"
+    )]
+    pub data_dir: PathBuf,
+
+    /// Directory of encrypted wallet keys [default: <DATA_DIR>/keystore]
+    #[arg(long, global = true, env = "BHARATCHAIN_KEYSTORE")]
+    pub keystore: Option<PathBuf>,
+
+    /// Indent the JSON output
+    #[arg(long, global = true)]
+    pub pretty: bool,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create the data directory from a genesis file
+    Init {
+        /// Genesis configuration (JSON)
+        #[arg(long)]
+        genesis: PathBuf,
+    },
+
+    /// Run a node: block sync and gossip with peers, the JSON-RPC server and optionally a miner
+    RunNode {
+        /// Address to accept peer connections on
+        #[arg(long, default_value = "0.0.0.0:30333")]
+        p2p_addr: SocketAddr,
+
+        /// Address to serve JSON-RPC on
+        #[arg(long, default_value = "127.0.0.1:8545")]
+        rpc_addr: SocketAddr,
+
+        /// Peer to connect to at startup; may be repeated
+        #[arg(long = "peer")]
+        peers: Vec<SocketAddr>,
+
+        /// Mine blocks, paying the rewards to this address
+        #[arg(long, value_name = "ADDRESS")]
+        mine: Option<String>,
+
+        /// Mining threads [default: one per CPU]
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+
+    /// Mine blocks on the local chain
+    Mine {
+        /// Number of blocks to mine
+        #[arg(long, default_value_t = 1)]
+        blocks: u64,
+
+        /// Address paid the block rewards
+        #[arg(long)]
+        miner_address: Option<String>,
+
+        /// Mining threads [default: one per CPU]
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+
+    /// Manage the keys in the wallet
+    Wallet {
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+
+    /// Sign a transfer with a wallet key and submit it to a running node
+    Send {
+        /// Sending address; its key must be in the wallet
+        #[arg(long)]
+        from: String,
+
+        /// Receiving address
+        #[arg(long)]
+        to: String,
+
+        /// Amount to transfer
+        #[arg(long)]
+        amount: String,
+
+        /// Fee offered to the miner
+        #[arg(long, default_value = "0")]
+        fee: String,
+
+        /// File holding the wallet password [default: $BHARATCHAIN_PASSWORD, or a prompt]
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+
+        /// JSON-RPC address of the node
+        #[arg(long, default_value = "127.0.0.1:8545")]
+        rpc: String,
+    },
+
+    /// Show an account's balance
+    Balance {
+        address: String,
+
+        /// Ask a running node instead of reading the data directory
+        #[arg(long)]
+        rpc: Option<String>,
+    },
+
+    /// Look up a block by number or hash
+    Block {
+        id: String,
+
+        /// Ask a running node instead of reading the data directory
+        #[arg(long)]
+        rpc: Option<String>,
+    },
+
+    /// Look up a mined transaction by hash
+    Tx {
+        hash: String,
+
+        /// Ask a running node instead of reading the data directory
+        #[arg(long)]
+        rpc: Option<String>,
+    },
+
+    /// Replay the stored chain and check every block
+    VerifyChain,
+
+    /// Write the main chain to a file
+    Export { file: PathBuf },
+
+    /// Validate and add the blocks from an exported file
+    Import { file: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WalletCommand {
+    /// Generate a key and store it encrypted
+    New {
+        /// File holding the password [default: $BHARATCHAIN_PASSWORD, or a prompt]
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+    },
+
+    /// List the addresses the wallet holds keys for
+    List,
+
+    /// Encrypt an existing secret key into the wallet
+    Import {
+        /// File holding the secret key (hex) [default: $BHARATCHAIN_SECRET_KEY, or a prompt]
+        #[arg(long)]
+        key_file: Option<PathBuf>,
+
+        /// File holding the password [default: $BHARATCHAIN_PASSWORD, or a prompt]
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+    },
+
+    /// Generate a BIP-39 seed phrase to back up; store its keys with `wallet recover`
+    Mnemonic {
+        /// Words in the phrase: 12, 15, 18, 21 or 24
+        #[arg(long, default_value_t = 24)]
+        words: usize,
+    },
+
+    /// Derive keys from a seed phrase along BIP-44 paths and store them encrypted
+    Recover {
+        /// File holding the seed phrase [default: $BHARATCHAIN_MNEMONIC, or a prompt]
+        #[arg(long)]
+        mnemonic_file: Option<PathBuf>,
+
+        /// Ask for the phrase's BIP-39 passphrase [default: $BHARATCHAIN_MNEMONIC_PASSPHRASE]
+        #[arg(long)]
+        passphrase: bool,
+
+        /// Account in the path m/44'/1947'/ACCOUNT'/0/INDEX
+        #[arg(long, default_value_t = 0)]
+        account: u32,
+
+        /// Number of addresses to derive, from index 0
+        #[arg(long, default_value_t = 1)]
+        count: u32,
+
+        /// File holding the password [default: $BHARATCHAIN_PASSWORD, or a prompt]
+        #[arg(long)]
+        password_file: Option<PathBuf>,
+    },
+}
+
+// Run a command, returning the JSON to print on success
+pub async fn run(cli: Cli) -> Result<Value, CliError> {
+    let data_dir = cli.data_dir.as_path();
+    let keystore = cli
+        .keystore
+        .clone()
+        .unwrap_or_else(|| data_dir.join("keystore"));
+    match cli.command {
+        Command::Init { genesis } => commands::init(data_dir, &genesis),
+        Command::RunNode {
+            p2p_addr,
+            rpc_addr,
+            peers,
+            mine,
+            threads,
+        } => {
+            let options = commands::NodeOptions {
+                p2p_addr,
+                rpc_addr,
+                peers,
+                miner_address: mine,
+                threads,
+            };
+            commands::run_node(data_dir, options, cli.pretty).await
+        }
+        Command::Mine {
+            blocks,
+            miner_address,
+            threads,
+        } => commands::mine(data_dir, blocks, miner_address, threads),
+        Command::Wallet { command } => match command {
+            WalletCommand::New { password_file } => {
+                wallet::new_key(&keystore, password_file.as_deref())
+            }
+            WalletCommand::List => wallet::list(&keystore),
+            WalletCommand::Import {
+                key_file,
+                password_file,
+            } => wallet::import(&keystore, key_file.as_deref(), password_file.as_deref()),
+            WalletCommand::Mnemonic { words } => wallet::new_mnemonic(words),
+            WalletCommand::Recover {
+                mnemonic_file,
+                passphrase,
+                account,
+                count,
+                password_file,
+            } => {
+                let options = wallet::Recovery {
+                    passphrase,
+                    account,
+                    count,
+                };
+                wallet::recover(
+                    &keystore,
+                    mnemonic_file.as_deref(),
+                    password_file.as_deref(),
+                    options,
+                )
+            }
+        },
+        Command::Send {
+            from,
+            to,
+            amount,
+            fee,
+            password_file,
+            rpc,
+        } => {
+            let password = wallet::read_password(password_file.as_deref(), false)?;
+            let transfer = commands::Transfer {
+                from,
+                to,
+                amount,
+                fee,
+            };
+            commands::send(&rpc, &keystore, &password, transfer).await
+        }
+        Command::Balance { address, rpc } => {
+            commands::balance(data_dir, rpc.as_deref(), address).await
+        }
+        Command::Block { id, rpc } => commands::block(data_dir, rpc.as_deref(), &id).await,
+        Command::Tx { hash, rpc } => commands::transaction(data_dir, rpc.as_deref(), &hash).await,
+        Command::VerifyChain => commands::verify_chain(data_dir),
+        Command::Export { file } => commands::export(data_dir, &file),
+        Command::Import { file } => commands::import(data_dir, &file),
+    }
+}
+
+pub fn print_json(value: &Value, pretty: bool) {
+    let text = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    println!("{}", text.expect("JSON values always serialize"));
+}
@@ -0,0 +1,120 @@
+use secp256k1::SecretKey;
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use super::CliError;
+use bharatchain::wallet::hd::{DerivationPath, ExtendedPrivateKey};
+use bharatchain::wallet::mnemonic::Mnemonic;
+use bharatchain::wallet::Wallet;
+
+// Which keys `recover` derives from a seed phrase
+pub struct Recovery {
+    pub passphrase: bool, // Ask for the BIP-39 passphrase
+    pub account: u32,
+    pub count: u32,
+}
+
+pub fn new_key(keystore: &Path, password_file: Option<&Path>) -> Result<Value, CliError> {
+    let wallet = Wallet::open(keystore)?;
+    let password = read_password(password_file, true)?;
+    let address = wallet.generate(&password)?;
+    Ok(json!({ "address": address, "keystore": keystore }))
+}
+
+pub fn list(keystore: &Path) -> Result<Value, CliError> {
+    let addresses = Wallet::open(keystore)?.addresses()?;
+    Ok(json!({ "addresses": addresses, "keystore": keystore }))
+}
+
+pub fn import(
+    keystore: &Path,
+    key_file: Option<&Path>,
+    password_file: Option<&Path>,
+) -> Result<Value, CliError> {
+    let wallet = Wallet::open(keystore)?;
+    let key = read_secret(key_file, "BHARATCHAIN_SECRET_KEY", "Secret key (hex): ")?;
+    let secret_key = hex::decode(key.trim().trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| SecretKey::from_slice(&bytes).ok())
+        .ok_or("Invalid secret key")?;
+
+    let password = read_password(password_file, true)?;
+    let address = wallet.import(&secret_key, &password)?;
+    Ok(json!({ "address": address, "keystore": keystore }))
+}
+
+pub fn new_mnemonic(words: usize) -> Result<Value, CliError> {
+    let mnemonic = Mnemonic::generate(words)?;
+    let path = DerivationPath::bip44(0, false, 0)?;
+    let key = ExtendedPrivateKey::from_seed(&mnemonic.to_seed(""))?.derive_path(&path)?;
+    Ok(json!({
+        "mnemonic": mnemonic.phrase(),
+        "path": path.to_string(),
+        "address": key.address(),
+    }))
+}
+
+// Store the first `count` receiving keys of an account, skipping any the
+// wallet already holds, so recovering again is harmless
+pub fn recover(
+    keystore: &Path,
+    mnemonic_file: Option<&Path>,
+    password_file: Option<&Path>,
+    recovery: Recovery,
+) -> Result<Value, CliError> {
+    let wallet = Wallet::open(keystore)?;
+    let phrase = read_secret(mnemonic_file, "BHARATCHAIN_MNEMONIC", "Seed phrase: ")?;
+    let mnemonic = Mnemonic::parse(&phrase)?;
+    let passphrase = if recovery.passphrase {
+        read_secret(None, "BHARATCHAIN_MNEMONIC_PASSPHRASE", "Passphrase: ")?
+    } else {
+        String::new()
+    };
+    let password = read_password(password_file, true)?;
+
+    let master = ExtendedPrivateKey::from_seed(&mnemonic.to_seed(&passphrase))?;
+    let mut keys = vec![];
+    for index in 0..recovery.count {
+        let path = DerivationPath::bip44(recovery.account, false, index)?;
+        let key = master.derive_path(&path)?;
+        let address = key.address();
+        let stored = !wallet.contains(&address);
+        if stored {
+            wallet.import(&key.secret_key, &password)?;
+        }
+        keys.push(json!({ "address": address, "path": path.to_string(), "stored": stored }));
+    }
+    Ok(json!({ "keys": keys, "keystore": keystore }))
+}
+
+// Wallet password from a file, the environment or, failing both, a prompt.
+// New passwords are asked for twice.
+pub fn read_password(password_file: Option<&Path>, confirm: bool) -> Result<String, CliError> {
+    let interactive = password_file.is_none() && env::var_os("BHARATCHAIN_PASSWORD").is_none();
+    let password = read_secret(password_file, "BHARATCHAIN_PASSWORD", "Password: ")?;
+
+    if interactive && confirm {
+        let again = rpassword::prompt_password("Repeat password: ")
+            .map_err(|e| format!("Failed to read password: {}", e))?;
+        if again != password {
+            return Err("Passwords do not match".into());
+        }
+    }
+    Ok(password)
+}
+
+// Read a secret without putting it on the command line: from a file (the
+// first line), an environment variable, or a prompt that does not echo
+fn read_secret(file: Option<&Path>, var: &str, prompt: &str) -> Result<String, CliError> {
+    if let Some(file) = file {
+        let contents = fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        return Ok(contents.lines().next().unwrap_or_default().to_string());
+    }
+    if let Ok(value) = env::var(var) {
+        return Ok(value);
+    }
+    Ok(rpassword::prompt_password(prompt).map_err(|e| format!("Failed to read input: {}", e))?)
+}
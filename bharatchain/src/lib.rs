@@ -0,0 +1,4 @@
+pub mod chain_core;
+pub mod p2p;
+pub mod rpc;
+pub mod wallet;
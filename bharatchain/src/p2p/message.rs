@@ -0,0 +1,273 @@
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::chain_core::block::{BlockHeader, DataBlock};
+use crate::chain_core::encoding::{self, Decode, Decoder, Encode, Encoder};
+use crate::chain_core::transaction::BlockTransaction;
+
+// Version of the peer-to-peer protocol spoken by this node
+pub const PROTOCOL_VERSION: u32 = 1;
+
+// Largest framed message accepted from a peer
+pub const MAX_MESSAGE_SIZE: usize = 8 * 1024 * 1024;
+
+// Most entries in one inventory, getdata or notfound message
+pub const MAX_INVENTORY: usize = 1000;
+
+// Most block hashes announced in reply to one getblocks
+pub const MAX_BLOCKS_PER_INV: usize = 500;
+
+// Most headers sent in reply to one getheaders
+pub const MAX_HEADERS: usize = 2000;
+
+// Most entries in a block locator
+pub const MAX_LOCATOR_LEN: usize = 101;
+
+// Reference to a block or transaction by hash
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InventoryItem {
+    Transaction(String),
+    Block(String),
+}
+
+// Messages exchanged between peers. On the wire each one is framed as
+// [payload length: u32 BE][payload], where the payload is the canonical
+// encoding of the message: a tag byte followed by its fields.
+#[derive(Debug, Clone)]
+pub enum Message {
+    // First message on a connection; both sides send it, then acknowledge the other's
+    Version {
+        version: u32,
+        chain_id: u64,
+        genesis_hash: String,
+        height: u64,
+        nonce: u64, // Random per node, to detect connections to itself
+    },
+    Verack,
+    Ping(u64),
+    Pong(u64),
+    // Announce blocks or transactions the sender has
+    Inv(Vec<InventoryItem>),
+    // Ask for the full blocks or transactions behind inventory entries
+    GetData(Vec<InventoryItem>),
+    // Reply to getdata for entries the sender does not have
+    NotFound(Vec<InventoryItem>),
+    // Ask for an inventory of main-chain blocks after the first locator hash
+    // the receiver knows, up to `stop_hash` (empty for as many as allowed)
+    GetBlocks {
+        locator: Vec<String>,
+        stop_hash: String,
+    },
+    Block(Box<DataBlock>),
+    Transaction(Box<BlockTransaction>),
+    // Like getblocks, but asks for the headers themselves
+    GetHeaders {
+        locator: Vec<String>,
+        stop_hash: String,
+    },
+    // Consecutive main-chain headers, oldest first
+    Headers(Vec<BlockHeader>),
+}
+
+impl Message {
+    // Name used in logs
+    pub fn command(&self) -> &'static str {
+        match self {
+            Message::Version { .. } => "version",
+            Message::Verack => "verack",
+            Message::Ping(_) => "ping",
+            Message::Pong(_) => "pong",
+            Message::Inv(_) => "inv",
+            Message::GetData(_) => "getdata",
+            Message::NotFound(_) => "notfound",
+            Message::GetBlocks { .. } => "getblocks",
+            Message::Block(_) => "block",
+            Message::Transaction(_) => "tx",
+            Message::GetHeaders { .. } => "getheaders",
+            Message::Headers(_) => "headers",
+        }
+    }
+}
+
+impl Encode for InventoryItem {
+    fn encode_to(&self, out: &mut Encoder) {
+        match self {
+            InventoryItem::Transaction(hash) => {
+                out.put_u8(1);
+                out.put_str(hash);
+            }
+            InventoryItem::Block(hash) => {
+                out.put_u8(2);
+                out.put_str(hash);
+            }
+        }
+    }
+}
+
+impl Decode for InventoryItem {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        match input.get_u8()? {
+            1 => Ok(InventoryItem::Transaction(input.get_str()?)),
+            2 => Ok(InventoryItem::Block(input.get_str()?)),
+            kind => Err(format!("Unknown inventory type {}", kind)),
+        }
+    }
+}
+
+impl Encode for Message {
+    fn encode_to(&self, out: &mut Encoder) {
+        match self {
+            Message::Version {
+                version,
+                chain_id,
+                genesis_hash,
+                height,
+                nonce,
+            } => {
+                out.put_u8(0);
+                out.put_u32(*version);
+                out.put_u64(*chain_id);
+                out.put_str(genesis_hash);
+                out.put_u64(*height);
+                out.put_u64(*nonce);
+            }
+            Message::Verack => out.put_u8(1),
+            Message::Ping(nonce) => {
+                out.put_u8(2);
+                out.put_u64(*nonce);
+            }
+            Message::Pong(nonce) => {
+                out.put_u8(3);
+                out.put_u64(*nonce);
+            }
+            Message::Inv(items) => {
+                out.put_u8(4);
+                out.put_list(items);
+            }
+            Message::GetData(items) => {
+                out.put_u8(5);
+                out.put_list(items);
+            }
+            Message::NotFound(items) => {
+                out.put_u8(6);
+                out.put_list(items);
+            }
+            Message::GetBlocks { locator, stop_hash } => {
+                out.put_u8(7);
+                out.put_list(locator);
+                out.put_str(stop_hash);
+            }
+            Message::Block(block) => {
+                out.put_u8(8);
+                block.encode_to(out);
+            }
+            Message::Transaction(tx) => {
+                out.put_u8(9);
+                tx.encode_to(out);
+            }
+            Message::GetHeaders { locator, stop_hash } => {
+                out.put_u8(10);
+                out.put_list(locator);
+                out.put_str(stop_hash);
+            }
+            Message::Headers(headers) => {
+                out.put_u8(11);
+                out.put_list(headers);
+            }
+        }
+    }
+}
+
+impl Decode for Message {
+    fn decode_from(input: &mut Decoder) -> Result<Self, String> {
+        let message = match input.get_u8()? {
+            0 => Message::Version {
+                version: input.get_u32()?,
+                chain_id: input.get_u64()?,
+                genesis_hash: input.get_str()?,
+                height: input.get_u64()?,
+                nonce: input.get_u64()?,
+            },
+            1 => Message::Verack,
+            2 => Message::Ping(input.get_u64()?),
+            3 => Message::Pong(input.get_u64()?),
+            4 => Message::Inv(get_inventory(input)?),
+            5 => Message::GetData(get_inventory(input)?),
+            6 => Message::NotFound(get_inventory(input)?),
+            7 => Message::GetBlocks {
+                locator: get_locator(input)?,
+                stop_hash: input.get_str()?,
+            },
+            8 => Message::Block(Box::new(DataBlock::decode_from(input)?)),
+            9 => Message::Transaction(Box::new(BlockTransaction::decode_from(input)?)),
+            10 => Message::GetHeaders {
+                locator: get_locator(input)?,
+                stop_hash: input.get_str()?,
+            },
+            11 => {
+                let headers: Vec<BlockHeader> = input.get_list()?;
+                if headers.len() > MAX_HEADERS {
+                    return Err(format!("Headers message has {} entries", headers.len()));
+                }
+                Message::Headers(headers)
+            }
+            tag => return Err(format!("Unknown message type {}", tag)),
+        };
+        Ok(message)
+    }
+}
+
+fn get_locator(input: &mut Decoder) -> Result<Vec<String>, String> {
+    let locator: Vec<String> = input.get_list()?;
+    if locator.len() > MAX_LOCATOR_LEN {
+        return Err(format!("Block locator has {} entries", locator.len()));
+    }
+    Ok(locator)
+}
+
+fn get_inventory(input: &mut Decoder) -> Result<Vec<InventoryItem>, String> {
+    let items: Vec<InventoryItem> = input.get_list()?;
+    if items.len() > MAX_INVENTORY {
+        return Err(format!("Inventory has {} entries", items.len()));
+    }
+    Ok(items)
+}
+
+// Write one framed message
+pub async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &Message,
+) -> Result<(), String> {
+    let payload = encoding::encode(message);
+    if payload.len() > MAX_MESSAGE_SIZE {
+        return Err(format!(
+            "Message {} is too large to send",
+            message.command()
+        ));
+    }
+
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    writer
+        .write_all(&frame)
+        .await
+        .map_err(|e| format!("Failed to send {}: {}", message.command(), e))
+}
+
+// Read one framed message, rejecting oversized or malformed frames
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Message, String> {
+    let len = reader
+        .read_u32()
+        .await
+        .map_err(|e| format!("Failed to read message: {}", e))? as usize;
+    if len > MAX_MESSAGE_SIZE {
+        return Err(format!("Message of {} bytes is too large", len));
+    }
+
+    let mut payload = vec![0u8; len];
+    reader
+        .read_exact(&mut payload)
+        .await
+        .map_err(|e| format!("Failed to read message: {}", e))?;
+    encoding::decode(&payload)
+}
@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, error::TrySendError, Sender};
+use tokio::sync::Notify;
+use tokio::time;
+
+use super::message::{
+    read_message, write_message, InventoryItem, Message, MAX_BLOCKS_PER_INV, MAX_HEADERS,
+    MAX_INVENTORY, PROTOCOL_VERSION,
+};
+use super::sync::{BlockSync, SyncActions, SyncConfig};
+use crate::chain_core::chain::{BharatChain, ChainEvent, SharedChain};
+
+#[derive(Debug, Clone)]
+pub struct NodeConfig {
+    pub handshake_timeout: Duration,
+    pub ping_interval: Duration, // A peer that has not answered a ping by the next one is dropped
+    pub max_queued_messages: usize, // Messages waiting to be sent to one peer before it is dropped
+    pub sync: SyncConfig,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        NodeConfig {
+            handshake_timeout: Duration::from_secs(10),
+            ping_interval: Duration::from_secs(30),
+            max_queued_messages: 1024,
+            sync: SyncConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerInfo {
+    pub addr: SocketAddr,
+    pub inbound: bool, // The peer connected to us
+    pub version: u32,
+    pub height: u64, // Height the peer reported in its handshake
+}
+
+// Work queued for a peer's connection task
+#[derive(Debug)]
+enum PeerCommand {
+    Send(Message),
+    Disconnect(String),
+}
+
+#[derive(Debug)]
+struct Peer {
+    info: PeerInfo,
+    outbound: Sender<PeerCommand>,
+    disconnect: Arc<Notify>, // Stops the connection task when the queue is full
+}
+
+impl Peer {
+    // Queue a command, disconnecting a peer that lets its queue fill up. A
+    // closed queue means the connection is already shutting down.
+    fn send(&self, command: PeerCommand) {
+        if let Err(TrySendError::Full(_)) = self.outbound.try_send(command) {
+            self.disconnect.notify_one();
+        }
+    }
+}
+
+type PeerMap = Arc<Mutex<HashMap<u64, Peer>>>;
+
+// Peer-to-peer node gossiping blocks and transactions over TCP. Every block
+// the chain connects and every transaction it accepts, from a peer, the RPC
+// server or a local miner, is announced to all peers, which fetch what they
+// are missing with getdata. A node that is behind a peer catches up with the
+// headers-first download in `BlockSync`.
+#[derive(Clone)]
+pub struct P2pNode {
+    chain: SharedChain,
+    config: NodeConfig,
+    nonce: u64, // Sent in our version message to detect connections to ourselves
+    peers: PeerMap,
+    next_peer_id: Arc<AtomicU64>,
+    sync: Arc<Mutex<BlockSync>>, // Always locked before `chain` when both are held
+}
+
+impl P2pNode {
+    pub fn new(chain: SharedChain, config: NodeConfig) -> Self {
+        let node = P2pNode {
+            chain,
+            sync: Arc::new(Mutex::new(BlockSync::new(config.sync.clone()))),
+            config,
+            nonce: rand::random(),
+            peers: Arc::new(Mutex::new(HashMap::new())),
+            next_peer_id: Arc::new(AtomicU64::new(0)),
+        };
+        node.relay_chain_events();
+        node
+    }
+
+    // Accept peers until the listener fails; each one is served on its own task
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            let node = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = node.start_peer(stream, addr, true).await {
+                    eprintln!("Peer {} rejected: {}", addr, e);
+                }
+            });
+        }
+    }
+
+    // Connect to a peer and complete the handshake; the connection is then
+    // served in the background
+    pub async fn connect(&self, addr: SocketAddr) -> Result<PeerInfo, String> {
+        let stream = TcpStream::connect(addr)
+            .await
+            .map_err(|e| format!("Failed to connect to {}: {}", addr, e))?;
+        self.start_peer(stream, addr, false).await
+    }
+
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        self.peers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|peer| peer.info.clone())
+            .collect()
+    }
+
+    pub fn peer_count(&self) -> usize {
+        self.peers.lock().unwrap().len()
+    }
+
+    // Whether the initial block download is still running
+    pub fn is_syncing(&self) -> bool {
+        self.sync.lock().unwrap().is_syncing()
+    }
+
+    // Queue a message for every connected peer
+    pub fn broadcast(&self, message: Message) {
+        broadcast(&self.peers, message);
+    }
+
+    // Announce chain events to peers from a dedicated thread, since the
+    // chain's event channel is blocking. The thread ends with the chain.
+    fn relay_chain_events(&self) {
+        let events = self.chain.lock().expect("chain lock poisoned").subscribe();
+        let peers = self.peers.clone();
+
+        thread::spawn(move || {
+            for event in events {
+                let items: Vec<InventoryItem> = match event {
+                    ChainEvent::BlockConnected { block_hash, .. } => {
+                        vec![InventoryItem::Block(block_hash)]
+                    }
+                    ChainEvent::Reorganized(reorg) => reorg
+                        .connected
+                        .into_iter()
+                        .map(InventoryItem::Block)
+                        .collect(),
+                    ChainEvent::TransactionAccepted { tx_hash } => {
+                        vec![InventoryItem::Transaction(tx_hash)]
+                    }
+                };
+                for chunk in items.chunks(MAX_INVENTORY) {
+                    broadcast(&peers, Message::Inv(chunk.to_vec()));
+                }
+            }
+        });
+    }
+
+    async fn start_peer(
+        &self,
+        mut stream: TcpStream,
+        addr: SocketAddr,
+        inbound: bool,
+    ) -> Result<PeerInfo, String> {
+        let (version, height) = time::timeout(self.config.handshake_timeout, async {
+            self.handshake(&mut stream).await
+        })
+        .await
+        .map_err(|_| "Handshake timed out".to_string())??;
+
+        let info = PeerInfo {
+            addr,
+            inbound,
+            version,
+            height,
+        };
+        eprintln!("Connected to peer {} at height {}", addr, height);
+
+        // Register before returning, so broadcasts from now on reach the peer
+        let (outbound, queue) = mpsc::channel(self.config.max_queued_messages.max(1));
+        let disconnect = Arc::new(Notify::new());
+        let id = self.next_peer_id.fetch_add(1, Ordering::Relaxed);
+        self.peers.lock().unwrap().insert(
+            id,
+            Peer {
+                info: info.clone(),
+                outbound,
+                disconnect: disconnect.clone(),
+            },
+        );
+
+        let actions = {
+            let mut sync = self.sync.lock().unwrap();
+            sync.peer_connected(id, height, &*self.lock()?)
+        };
+        self.dispatch(actions);
+
+        let node = self.clone();
+        tokio::spawn(async move {
+            let result = node.peer_loop(id, stream, queue, disconnect).await;
+            node.peers.lock().unwrap().remove(&id);
+            if let Err(e) = result {
+                eprintln!("Peer {} disconnected: {}", addr, e);
+            }
+
+            let actions = {
+                let mut sync = node.sync.lock().unwrap();
+                let chain = node.chain.lock().unwrap();
+                sync.peer_disconnected(id, &chain)
+            };
+            node.dispatch(actions);
+        });
+        Ok(info)
+    }
+
+    // Exchange version messages and acknowledgements. Returns the peer's
+    // protocol version and height.
+    async fn handshake(&self, stream: &mut TcpStream) -> Result<(u32, u64), String> {
+        let (chain_id, genesis_hash, our_height) = {
+            let chain = self.lock()?;
+            (
+                chain.chain_id,
+                chain.chain[0].block_hash.clone(),
+                chain.height(),
+            )
+        };
+        let version = Message::Version {
+            version: PROTOCOL_VERSION,
+            chain_id,
+            genesis_hash: genesis_hash.clone(),
+            height: our_height,
+            nonce: self.nonce,
+        };
+        write_message(stream, &version).await?;
+
+        let (version, height) = match read_message(stream).await? {
+            Message::Version {
+                version,
+                chain_id: peer_chain_id,
+                genesis_hash: peer_genesis_hash,
+                height,
+                nonce,
+            } => {
+                if nonce == self.nonce {
+                    return Err("Connected to ourselves".to_string());
+                }
+                if version != PROTOCOL_VERSION {
+                    return Err(format!("Unsupported protocol version {}", version));
+                }
+                if peer_chain_id != chain_id {
+                    return Err(format!(
+                        "Peer is on chain {}, expected {}",
+                        peer_chain_id, chain_id
+                    ));
+                }
+                if peer_genesis_hash != genesis_hash {
+                    return Err(format!(
+                        "Peer has genesis block {}, expected {}",
+                        peer_genesis_hash, genesis_hash
+                    ));
+                }
+                (version, height)
+            }
+            other => return Err(format!("Expected version, got {}", other.command())),
+        };
+
+        write_message(stream, &Message::Verack).await?;
+        match read_message(stream).await? {
+            Message::Verack => Ok((version, height)),
+            other => Err(format!("Expected verack, got {}", other.command())),
+        }
+    }
+
+    // Serve an established connection until either side drops it, or
+    // `disconnect` is notified
+    async fn peer_loop(
+        &self,
+        id: u64,
+        stream: TcpStream,
+        mut queue: mpsc::Receiver<PeerCommand>,
+        disconnect: Arc<Notify>,
+    ) -> Result<(), String> {
+        let (mut reader, mut writer) = stream.into_split();
+
+        // Reads are not cancellation safe, so they run on their own task
+        let (inbound_sender, mut inbound) = mpsc::channel(16);
+        let reader_task = tokio::spawn(async move {
+            loop {
+                let message = read_message(&mut reader).await;
+                let failed = message.is_err();
+                if inbound_sender.send(message).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        let mut ping = time::interval(self.config.ping_interval);
+        ping.tick().await;
+        let mut pending_ping = None;
+
+        // Every connection checks for stalled downloads, so no peer can hold up sync
+        let sync = &self.config.sync;
+        let mut stall_check = time::interval(sync.block_timeout.min(sync.headers_timeout) / 4);
+
+        // Dropping the loop on disconnect also abandons a write the peer is not reading
+        let serve = async {
+            loop {
+                tokio::select! {
+                    message = inbound.recv() => {
+                        let replies = match message {
+                            Some(Ok(Message::Pong(nonce))) => {
+                                if pending_ping == Some(nonce) {
+                                    pending_ping = None;
+                                }
+                                Ok(vec![])
+                            }
+                            Some(Ok(message)) => {
+                            // Handling a message can wait on the chain lock or connect
+                            // blocks, which writes to disk, so it runs off the async workers
+                            let node = self.clone();
+                            tokio::task::spawn_blocking(move || node.handle_message(id, message))
+                                .await
+                                .unwrap_or_else(|e| Err(format!("Message handler failed: {}", e)))
+                        }
+                            Some(Err(e)) => Err(e),
+                            None => Err("Connection closed".to_string()),
+                        };
+                        let sent = match replies {
+                            Ok(replies) => write_all(&mut writer, &replies).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = sent {
+                            break Err(e);
+                        }
+                    }
+                    Some(command) = queue.recv() => match command {
+                        PeerCommand::Send(message) => {
+                            if let Err(e) = write_message(&mut writer, &message).await {
+                                break Err(e);
+                            }
+                        }
+                        PeerCommand::Disconnect(reason) => break Err(reason),
+                    },
+                    _ = stall_check.tick() => {
+                        let actions = self.sync.lock().unwrap().check_stalls(Instant::now());
+                        self.dispatch(actions);
+                    }
+                    _ = ping.tick() => {
+                        if pending_ping.is_some() {
+                            break Err("Peer did not answer ping".to_string());
+                        }
+                        let nonce = rand::random();
+                        pending_ping = Some(nonce);
+                        if let Err(e) = write_message(&mut writer, &Message::Ping(nonce)).await {
+                            break Err(e);
+                        }
+                    }
+                }
+            }
+        };
+        let result = tokio::select! {
+            result = serve => result,
+            _ = disconnect.notified() => Err("Peer is not reading its messages".to_string()),
+        };
+
+        reader_task.abort();
+        result
+    }
+
+    // Handle a message received from `peer` after the handshake, returning the replies
+    fn handle_message(&self, peer: u64, message: Message) -> Result<Vec<Message>, String> {
+        match message {
+            Message::Version { .. } | Message::Verack => Err(format!(
+                "Unexpected {} after the handshake",
+                message.command()
+            )),
+            Message::Ping(nonce) => Ok(vec![Message::Pong(nonce)]),
+            Message::Pong(_) => Ok(vec![]),
+            Message::NotFound(items) => {
+                let actions = self.sync.lock().unwrap().not_found(peer, &items);
+                self.dispatch(actions);
+                Ok(vec![])
+            }
+            Message::Inv(items) => {
+                let chain = self.lock()?;
+                let wanted: Vec<InventoryItem> = items
+                    .iter()
+                    .filter(|item| match item {
+                        InventoryItem::Block(hash) => {
+                            chain.get_known_block(hash).is_none()
+                                && !chain.block_tree().is_invalid(hash)
+                        }
+                        InventoryItem::Transaction(hash) => !chain.mempool.contains(hash),
+                    })
+                    .cloned()
+                    .collect();
+
+                if wanted.is_empty() {
+                    return Ok(vec![]);
+                }
+                Ok(vec![Message::GetData(wanted)])
+            }
+            Message::GetData(items) => {
+                let chain = self.lock()?;
+                let mut replies = vec![];
+                let mut missing = vec![];
+                for item in items {
+                    match &item {
+                        InventoryItem::Block(hash) => match chain.get_known_block(hash) {
+                            Some(block) => replies.push(Message::Block(Box::new(block.clone()))),
+                            None => missing.push(item),
+                        },
+                        InventoryItem::Transaction(hash) => match chain.mempool.get(hash) {
+                            Some(tx) => replies.push(Message::Transaction(Box::new(tx.clone()))),
+                            None => missing.push(item),
+                        },
+                    }
+                }
+                if !missing.is_empty() {
+                    replies.push(Message::NotFound(missing));
+                }
+                Ok(replies)
+            }
+            Message::GetBlocks { locator, stop_hash } => {
+                let chain = self.lock()?;
+                let blocks = chain.locate_blocks(&locator, &stop_hash, MAX_BLOCKS_PER_INV);
+                if blocks.is_empty() {
+                    return Ok(vec![]);
+                }
+                Ok(vec![Message::Inv(
+                    blocks
+                        .iter()
+                        .map(|block| InventoryItem::Block(block.block_hash.clone()))
+                        .collect(),
+                )])
+            }
+            Message::GetHeaders { locator, stop_hash } => {
+                let chain = self.lock()?;
+                let headers = chain
+                    .locate_blocks(&locator, &stop_hash, MAX_HEADERS)
+                    .iter()
+                    .map(|block| block.header.clone())
+                    .collect();
+                Ok(vec![Message::Headers(headers)])
+            }
+            Message::Headers(headers) => {
+                let actions = {
+                    let mut sync = self.sync.lock().unwrap();
+                    sync.headers_received(peer, headers, &*self.lock()?)?
+                };
+                self.dispatch(actions);
+                Ok(vec![])
+            }
+            Message::Block(block) => {
+                let mut sync = self.sync.lock().unwrap();
+                let mut chain = self.lock()?;
+                if sync.is_requested(peer, &block.calculate_hash()) {
+                    let actions = sync.block_received(peer, *block, &mut chain);
+                    drop((sync, chain));
+                    self.dispatch(actions);
+                    return Ok(vec![]);
+                }
+
+                if chain.get_known_block(&block.block_hash).is_some() {
+                    return Ok(vec![]);
+                }
+                // Missing ancestors: fetch the headers between our chain and this block
+                if chain.get_known_block(&block.header.previous_hash).is_none() {
+                    let mut actions = SyncActions::default();
+                    sync.request_headers(peer, &chain, &mut actions);
+                    drop((sync, chain));
+                    self.dispatch(actions);
+                    return Ok(vec![]);
+                }
+
+                // Connecting a block also cancels any local mining job on the old tip
+                let block_hash = block.block_hash.clone();
+                if let Err(e) = chain.submit_block(*block) {
+                    eprintln!("Rejected block {} from peer: {}", block_hash, e);
+                }
+                Ok(vec![])
+            }
+            Message::Transaction(tx) => {
+                let tx_hash = tx.compute_hash();
+                if let Err(e) = self.lock()?.submit_transaction(*tx) {
+                    eprintln!("Rejected transaction {} from peer: {}", tx_hash, e);
+                }
+                Ok(vec![])
+            }
+        }
+    }
+
+    // Queue the messages and disconnections a sync step asked for
+    fn dispatch(&self, actions: SyncActions) {
+        let peers = self.peers.lock().unwrap();
+        let commands = actions
+            .send
+            .into_iter()
+            .map(|(peer, message)| (peer, PeerCommand::Send(message)))
+            .chain(
+                actions
+                    .disconnect
+                    .into_iter()
+                    .map(|(peer, reason)| (peer, PeerCommand::Disconnect(reason))),
+            );
+        for (peer, command) in commands {
+            if let Some(peer) = peers.get(&peer) {
+                peer.send(command);
+            }
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, BharatChain>, String> {
+        self.chain
+            .lock()
+            .map_err(|_| "Chain lock poisoned".to_string())
+    }
+}
+
+async fn write_all(writer: &mut OwnedWriteHalf, messages: &[Message]) -> Result<(), String> {
+    for message in messages {
+        write_message(writer, message).await?;
+    }
+    Ok(())
+}
+
+fn broadcast(peers: &PeerMap, message: Message) {
+    for peer in peers.lock().unwrap().values() {
+        peer.send(PeerCommand::Send(message.clone()));
+    }
+}
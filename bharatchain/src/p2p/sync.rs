@@ -0,0 +1,499 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use super::message::{InventoryItem, Message, MAX_HEADERS, MAX_INVENTORY};
+use crate::chain_core::block::{BlockHeader, DataBlock};
+use crate::chain_core::chain::{
+    median_timestamp, BharatChain, MAX_FUTURE_BLOCK_TIME, MEDIAN_TIME_SPAN,
+};
+use crate::chain_core::helper::get_current_timestamp;
+
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    pub headers_timeout: Duration, // A peer that has not answered getheaders within this is stalling
+    pub block_timeout: Duration,   // Likewise for each block requested with getdata
+    pub max_blocks_in_flight: usize, // Blocks requested from one peer at a time
+    pub download_window: usize,    // Blocks past the next one to apply that may be requested
+    pub max_queued_headers: usize, // Validated headers held until their blocks are applied
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            headers_timeout: Duration::from_secs(10),
+            block_timeout: Duration::from_secs(10),
+            max_blocks_in_flight: 16,
+            download_window: 1024,
+            max_queued_headers: 10 * MAX_HEADERS,
+        }
+    }
+}
+
+// What the node should do after a sync step
+#[derive(Debug, Default)]
+pub struct SyncActions {
+    pub send: Vec<(u64, Message)>,
+    pub disconnect: Vec<(u64, String)>, // Peers that stalled or sent invalid data, with the reason
+}
+
+#[derive(Debug, Default)]
+struct SyncPeer {
+    height: u64,                // Height reported in the peer's handshake
+    in_flight: usize,           // Blocks requested from the peer and not yet received
+    not_found: HashSet<String>, // Blocks the peer said it does not have
+}
+
+// Headers-first block download. Headers are fetched from one peer, starting
+// from a locator, and checked as they arrive with the rules `is_valid` applies
+// to headers: links, timestamps, the retarget schedule and proof-of-work. Their
+// bodies are then requested from every peer in parallel, a few at a time per
+// peer, and applied to the chain in order as soon as each block's parent is
+// in. Peers that stall are dropped and their requests given to others; peers
+// that send invalid headers or blocks are dropped along with the data. At most
+// `max_queued_headers` headers wait for their blocks: more are only asked for
+// once a full batch fits, and a peer sending past the cap is dropped.
+#[derive(Debug, Default)]
+pub struct BlockSync {
+    config: SyncConfig,
+    peers: HashMap<u64, SyncPeer>,
+    headers: VecDeque<(String, BlockHeader)>, // Validated headers whose blocks are not yet applied
+    header_source: Option<u64>,               // Peer the pending headers came from
+    header_request: Option<(u64, Instant)>,   // Outstanding getheaders: peer and when it was sent
+    deferred_headers: Option<u64>, // Peer to ask for more headers once the queue has room
+    in_flight: HashMap<String, (u64, Instant)>, // Block hash -> peer asked and when
+    received: HashMap<String, (u64, DataBlock)>, // Blocks waiting for their parent, with their sender
+}
+
+impl BlockSync {
+    pub fn new(config: SyncConfig) -> Self {
+        BlockSync {
+            config,
+            ..BlockSync::default()
+        }
+    }
+
+    // Whether blocks are still being downloaded or applied
+    pub fn is_syncing(&self) -> bool {
+        self.header_request.is_some() || !self.headers.is_empty()
+    }
+
+    // Number of validated headers whose blocks are not applied yet
+    pub fn pending_blocks(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn peer_connected(&mut self, peer: u64, height: u64, chain: &BharatChain) -> SyncActions {
+        self.peers.insert(
+            peer,
+            SyncPeer {
+                height,
+                ..SyncPeer::default()
+            },
+        );
+
+        let mut actions = SyncActions::default();
+        if height > self.best_height(chain) {
+            self.request_headers(peer, chain, &mut actions);
+        }
+        self.schedule(&mut actions);
+        actions
+    }
+
+    // Forget the peer, hand its requests to others and, if it was serving
+    // headers, continue from another peer that is ahead of us
+    pub fn peer_disconnected(&mut self, peer: u64, chain: &BharatChain) -> SyncActions {
+        self.peers.remove(&peer);
+        self.in_flight.retain(|_, (asked, _)| *asked != peer);
+        if self.header_source == Some(peer) {
+            self.header_source = None;
+        }
+        if self.deferred_headers == Some(peer) {
+            self.deferred_headers = None;
+        }
+
+        let mut actions = SyncActions::default();
+        if self.header_request.is_some_and(|(asked, _)| asked == peer) {
+            self.header_request = None;
+        }
+        if self.header_request.is_none() {
+            let best_height = self.best_height(chain);
+            let next = self
+                .peers
+                .iter()
+                .find(|(_, state)| state.height > best_height)
+                .map(|(&id, _)| id);
+            if let Some(next) = next {
+                self.request_headers(next, chain, &mut actions);
+            }
+        }
+        self.schedule(&mut actions);
+        actions
+    }
+
+    // Ask `peer` for headers, e.g. after it announced a block whose parent we
+    // do not have. Does nothing while another header request is outstanding,
+    // and waits for blocks to be applied while the queue has no room.
+    pub fn request_headers(&mut self, peer: u64, chain: &BharatChain, actions: &mut SyncActions) {
+        if self.header_request.is_some() {
+            return;
+        }
+        if !self.has_header_room() {
+            self.deferred_headers = Some(peer);
+            return;
+        }
+        self.deferred_headers = None;
+
+        let mut locator = chain.block_locator();
+        if let Some((tail, _)) = self.headers.back() {
+            locator.insert(0, tail.clone());
+        }
+        self.header_request = Some((peer, Instant::now()));
+        actions.send.push((
+            peer,
+            Message::GetHeaders {
+                locator,
+                stop_hash: String::new(),
+            },
+        ));
+    }
+
+    // Validate and queue headers received from `peer`. An error means the
+    // peer sent invalid headers and should be dropped.
+    pub fn headers_received(
+        &mut self,
+        peer: u64,
+        headers: Vec<BlockHeader>,
+        chain: &BharatChain,
+    ) -> Result<SyncActions, String> {
+        // Only answers to our own request count
+        if self.header_request.is_none_or(|(asked, _)| asked != peer) {
+            return Ok(SyncActions::default());
+        }
+        self.header_request = None;
+
+        let mut actions = SyncActions::default();
+        if headers.is_empty() {
+            self.schedule(&mut actions);
+            return Ok(actions);
+        }
+
+        // Headers that do not continue the pending ones start a new branch
+        // from a block we already have
+        let continues = self
+            .headers
+            .back()
+            .is_some_and(|(tail, _)| *tail == headers[0].previous_hash);
+        if !continues {
+            if chain.get_known_block(&headers[0].previous_hash).is_none() {
+                return Err(format!(
+                    "Headers start from unknown block {}",
+                    headers[0].previous_hash
+                ));
+            }
+            self.reset();
+        }
+
+        // Only asked for when a full batch fits, so anything longer is the peer's doing
+        if self.headers.len() + headers.len() > self.header_capacity() {
+            self.reset();
+            return Err(format!(
+                "Sent {} headers with room for {}",
+                headers.len(),
+                self.header_capacity() - self.headers.len()
+            ));
+        }
+
+        let full = headers.len() == MAX_HEADERS;
+        if let Err(e) = self.append_headers(headers, chain) {
+            self.reset();
+            return Err(e);
+        }
+        self.header_source = Some(peer);
+
+        // A full batch means the peer has more
+        if full {
+            self.request_headers(peer, chain, &mut actions);
+        }
+        self.schedule(&mut actions);
+        Ok(actions)
+    }
+
+    // Whether the block with this header hash was requested from `peer`
+    pub fn is_requested(&self, peer: u64, block_hash: &str) -> bool {
+        self.in_flight
+            .get(block_hash)
+            .is_some_and(|(asked, _)| *asked == peer)
+    }
+
+    // Accept a block this sync requested from `peer` (see `is_requested`) and
+    // apply every block that is now ready
+    pub fn block_received(
+        &mut self,
+        peer: u64,
+        block: DataBlock,
+        chain: &mut BharatChain,
+    ) -> SyncActions {
+        let block_hash = block.calculate_hash();
+        if self.in_flight.remove(&block_hash).is_none() {
+            return SyncActions::default();
+        }
+        if let Some(state) = self.peers.get_mut(&peer) {
+            state.in_flight -= 1;
+        }
+
+        let mut actions = SyncActions::default();
+        if block.block_hash != block_hash
+            || block.body.merkle_root(block.header.version) != block.header.merkle_root
+        {
+            actions.disconnect.push((
+                peer,
+                format!("Block {} does not match its header", block_hash),
+            ));
+            self.schedule(&mut actions);
+            return actions;
+        }
+        self.received.insert(block_hash, (peer, block));
+
+        while let Some((next_hash, _)) = self.headers.front() {
+            let Some((sender, block)) = self.received.remove(next_hash) else {
+                break;
+            };
+            let next_hash = next_hash.clone();
+            self.headers.pop_front();
+
+            // Already arrived another way, e.g. announced by a peer
+            if chain.get_known_block(&next_hash).is_some() {
+                continue;
+            }
+
+            // The headers were valid but the block is not: drop the peer that
+            // sent it, the peer that sent its header, and the rest of the branch
+            if let Err(e) = chain.submit_block(block) {
+                let reason = format!("Invalid block {}: {}", next_hash, e);
+                actions.disconnect.push((sender, reason.clone()));
+                if let Some(source) = self.header_source.filter(|&source| source != sender) {
+                    actions.disconnect.push((source, reason));
+                }
+                self.reset();
+                break;
+            }
+        }
+
+        // Applied blocks may have made room for the headers we held off on
+        if let Some(peer) = self.deferred_headers {
+            if self.has_header_room() {
+                self.request_headers(peer, chain, &mut actions);
+            }
+        }
+
+        self.schedule(&mut actions);
+        actions
+    }
+
+    // The peer does not have some blocks we asked it for: ask someone else
+    pub fn not_found(&mut self, peer: u64, items: &[InventoryItem]) -> SyncActions {
+        for item in items {
+            if let InventoryItem::Block(hash) = item {
+                if self
+                    .in_flight
+                    .get(hash)
+                    .is_some_and(|(asked, _)| *asked == peer)
+                {
+                    self.in_flight.remove(hash);
+                    if let Some(state) = self.peers.get_mut(&peer) {
+                        state.in_flight -= 1;
+                        state.not_found.insert(hash.clone());
+                    }
+                }
+            }
+        }
+
+        let mut actions = SyncActions::default();
+        self.schedule(&mut actions);
+        actions
+    }
+
+    // Drop peers that have not answered a request in time. Their requests are
+    // handed to other peers once the node reports them disconnected.
+    pub fn check_stalls(&mut self, now: Instant) -> SyncActions {
+        let mut actions = SyncActions::default();
+
+        if let Some((peer, asked)) = self.header_request {
+            if now.duration_since(asked) > self.config.headers_timeout {
+                actions
+                    .disconnect
+                    .push((peer, "Stalled answering getheaders".to_string()));
+            }
+        }
+
+        let mut stalled: Vec<u64> = self
+            .in_flight
+            .values()
+            .filter(|(_, asked)| now.duration_since(*asked) > self.config.block_timeout)
+            .map(|(peer, _)| *peer)
+            .collect();
+        stalled.sort_unstable();
+        stalled.dedup();
+        for peer in stalled {
+            actions
+                .disconnect
+                .push((peer, "Stalled sending blocks".to_string()));
+        }
+
+        actions
+    }
+
+    // Most headers the queue holds; never less than one batch
+    fn header_capacity(&self) -> usize {
+        self.config.max_queued_headers.max(MAX_HEADERS)
+    }
+
+    // Whether a full batch of headers fits in the queue
+    fn has_header_room(&self) -> bool {
+        self.headers.len() + MAX_HEADERS <= self.header_capacity()
+    }
+
+    // Height of the best header we know of
+    fn best_height(&self, chain: &BharatChain) -> u64 {
+        self.headers
+            .back()
+            .map_or(chain.height(), |(_, header)| header.block_number)
+    }
+
+    // Check headers one by one against the previous one and queue them
+    fn append_headers(
+        &mut self,
+        headers: Vec<BlockHeader>,
+        chain: &BharatChain,
+    ) -> Result<(), String> {
+        for header in headers {
+            let (parent_hash, parent) = match self.headers.back() {
+                Some((hash, header)) => (hash.clone(), header.clone()),
+                None => {
+                    let parent = chain
+                        .get_known_block(&header.previous_hash)
+                        .ok_or_else(|| format!("Unknown parent {}", header.previous_hash))?;
+                    (parent.block_hash.clone(), parent.header.clone())
+                }
+            };
+
+            if header.previous_hash != parent_hash || header.block_number != parent.block_number + 1
+            {
+                return Err(format!(
+                    "Header {} does not extend its parent",
+                    header.block_number
+                ));
+            }
+
+            // The same timestamp rules as `check_block`, so we never download
+            // blocks it would reject
+            let first = (parent.block_number + 1).saturating_sub(MEDIAN_TIME_SPAN);
+            let median_time = median_timestamp(
+                (first..=parent.block_number)
+                    .map(|block_number| self.header_at(chain, &parent_hash, block_number).timestamp)
+                    .collect(),
+            );
+            if header.timestamp <= median_time {
+                return Err(format!(
+                    "Header {} has timestamp {}, not after the median time {} of the blocks before it",
+                    header.block_number, header.timestamp, median_time
+                ));
+            }
+            let latest_allowed = get_current_timestamp() + MAX_FUTURE_BLOCK_TIME;
+            if header.timestamp > latest_allowed {
+                return Err(format!(
+                    "Header {} has timestamp {}, too far in the future (latest allowed {})",
+                    header.block_number, header.timestamp, latest_allowed
+                ));
+            }
+
+            let expected_bits = chain.genesis.retarget.next_target(&parent, |block_number| {
+                self.header_at(chain, &parent_hash, block_number)
+            });
+            if header.bits != expected_bits {
+                return Err(format!(
+                    "Header {} has target {}, expected {}",
+                    header.block_number, header.bits, expected_bits
+                ));
+            }
+
+            let hash = header.hash();
+            if !header.meets_target(&hash) {
+                return Err(format!(
+                    "Header {} does not meet its target",
+                    header.block_number
+                ));
+            }
+            self.headers.push_back((hash, header));
+        }
+        Ok(())
+    }
+
+    // Header at `block_number` on the branch of the pending headers, or of
+    // `parent_hash` while there are none
+    fn header_at<'a>(
+        &'a self,
+        chain: &'a BharatChain,
+        parent_hash: &str,
+        block_number: u64,
+    ) -> &'a BlockHeader {
+        let header = match self.headers.front() {
+            Some((_, first)) if block_number >= first.block_number => self
+                .headers
+                .get((block_number - first.block_number) as usize)
+                .map(|(_, header)| header),
+            Some((_, first)) => chain.ancestor(&first.previous_hash, block_number),
+            None => chain.ancestor(parent_hash, block_number),
+        };
+        header.expect("retarget period starts on the same branch")
+    }
+
+    // Request blocks in the download window from peers with free slots
+    fn schedule(&mut self, actions: &mut SyncActions) {
+        let mut requests: HashMap<u64, Vec<InventoryItem>> = HashMap::new();
+        let now = Instant::now();
+
+        for (hash, _) in self.headers.iter().take(self.config.download_window) {
+            if self.in_flight.contains_key(hash) || self.received.contains_key(hash) {
+                continue;
+            }
+
+            let peer = self
+                .peers
+                .iter_mut()
+                .filter(|(_, state)| {
+                    state.in_flight < self.config.max_blocks_in_flight
+                        && !state.not_found.contains(hash)
+                })
+                .min_by_key(|(&id, state)| (state.in_flight, id));
+            let Some((&peer, state)) = peer else {
+                continue;
+            };
+
+            state.in_flight += 1;
+            self.in_flight.insert(hash.clone(), (peer, now));
+            requests
+                .entry(peer)
+                .or_default()
+                .push(InventoryItem::Block(hash.clone()));
+        }
+
+        for (peer, items) in requests {
+            for chunk in items.chunks(MAX_INVENTORY) {
+                actions.send.push((peer, Message::GetData(chunk.to_vec())));
+            }
+        }
+    }
+
+    // Drop all pending headers and downloads
+    fn reset(&mut self) {
+        self.headers.clear();
+        self.received.clear();
+        self.header_source = None;
+        for (_, (peer, _)) in self.in_flight.drain() {
+            if let Some(state) = self.peers.get_mut(&peer) {
+                state.in_flight -= 1;
+            }
+        }
+    }
+}
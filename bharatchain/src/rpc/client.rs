@@ -0,0 +1,55 @@
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// Call a method on the JSON-RPC server at `addr` (host:port) and return its result
+pub async fn call(addr: &str, method: &str, params: Value) -> Result<Value, String> {
+    let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 });
+    let body = request.to_string();
+
+    let mut stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| format!("Failed to connect to RPC server {}: {}", addr, e))?;
+    let http_request = format!(
+        "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        addr,
+        body.len(),
+        body
+    );
+    stream
+        .write_all(http_request.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to send RPC request: {}", e))?;
+
+    // The server closes the connection after each response
+    let mut response = vec![];
+    stream
+        .read_to_end(&mut response)
+        .await
+        .map_err(|e| format!("Failed to read RPC response: {}", e))?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| "Malformed RPC response".to_string())?;
+
+    let status = head.lines().next().unwrap_or_default();
+    if !status.starts_with("HTTP/1.1 200") {
+        return Err(format!("RPC server answered {}", status));
+    }
+
+    let response: Value =
+        serde_json::from_str(body).map_err(|e| format!("Invalid RPC response: {}", e))?;
+    into_result(response)
+}
+
+// Result of a single JSON-RPC response, or its error as a message
+pub fn into_result(response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        return Err(format!(
+            "RPC error {}: {}",
+            error["code"],
+            error["message"].as_str().unwrap_or_default()
+        ));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
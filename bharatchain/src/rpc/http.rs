@@ -0,0 +1,109 @@
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+// Largest request body the server accepts
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+// Longest request line or single header line, including the line ending
+pub const MAX_LINE_SIZE: usize = 8 * 1024;
+
+// Most bytes of header lines a request may send in total
+pub const MAX_HEADERS_SIZE: usize = 64 * 1024;
+
+// Time a client has to send its whole request
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The parts of an HTTP/1.1 request the RPC server cares about
+#[derive(Debug)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+// Read a single HTTP request (request line, headers and a Content-Length body).
+// Lines and headers are size limited; callers bound the time it may take.
+pub async fn read_request(stream: &mut TcpStream) -> Result<HttpRequest, String> {
+    let mut reader = BufReader::new(stream);
+
+    let request_line = read_line(&mut reader)
+        .await
+        .map_err(|e| format!("Failed to read request: {}", e))?;
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return Err("Malformed request line".to_string()),
+    };
+
+    let mut content_length = 0;
+    let mut headers_size = 0;
+    loop {
+        let header = read_line(&mut reader)
+            .await
+            .map_err(|e| format!("Failed to read header: {}", e))?;
+        headers_size += header.len();
+        if headers_size > MAX_HEADERS_SIZE {
+            return Err("Request headers too large".to_string());
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| "Invalid Content-Length".to_string())?;
+            }
+        }
+    }
+
+    if content_length > MAX_BODY_SIZE {
+        return Err("Request body too large".to_string());
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader
+        .read_exact(&mut body)
+        .await
+        .map_err(|e| format!("Failed to read body: {}", e))?;
+
+    Ok(HttpRequest { method, path, body })
+}
+
+// Read one line of at most `MAX_LINE_SIZE` bytes; empty at the end of the stream
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<String, String> {
+    let mut line = String::new();
+    let read = reader
+        .take(MAX_LINE_SIZE as u64)
+        .read_line(&mut line)
+        .await
+        .map_err(|e| e.to_string())?;
+    if read == MAX_LINE_SIZE && !line.ends_with('\n') {
+        return Err(format!("line longer than {} bytes", MAX_LINE_SIZE));
+    }
+    Ok(line)
+}
+
+// Write a complete response and close the exchange
+pub async fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
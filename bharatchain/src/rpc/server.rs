@@ -0,0 +1,266 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io;
+use std::sync::MutexGuard;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+
+use super::http::{read_request, write_response, REQUEST_TIMEOUT};
+use crate::chain_core::address::{Address, AddressError};
+use crate::chain_core::chain::{BharatChain, SharedChain};
+use crate::chain_core::encoding;
+use crate::chain_core::transaction::BlockTransaction;
+
+// Standard JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+// Server defined error: the submitted transaction was rejected
+pub const TRANSACTION_REJECTED: i64 = -32000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// JSON-RPC 2.0 server over HTTP exposing chain queries and transaction submission
+#[derive(Clone)]
+pub struct RpcServer {
+    chain: SharedChain,
+    request_timeout: Duration, // A client that has not sent its whole request by then is dropped
+}
+
+impl RpcServer {
+    pub fn new(chain: SharedChain) -> Self {
+        RpcServer {
+            chain,
+            request_timeout: REQUEST_TIMEOUT,
+        }
+    }
+
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    // Accept connections until the listener fails; each one is served on its own task
+    pub async fn serve(self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream).await {
+                    eprintln!("RPC connection error: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        let request = match time::timeout(self.request_timeout, read_request(&mut stream)).await {
+            Ok(Ok(request)) => request,
+            Ok(Err(e)) => {
+                return write_response(&mut stream, "400 Bad Request", "text/plain", e.as_bytes())
+                    .await
+            }
+            Err(_) => {
+                return write_response(
+                    &mut stream,
+                    "408 Request Timeout",
+                    "text/plain",
+                    b"Timed out reading the request",
+                )
+                .await
+            }
+        };
+
+        if request.method != "POST" {
+            return write_response(
+                &mut stream,
+                "405 Method Not Allowed",
+                "text/plain",
+                b"JSON-RPC requests must use POST",
+            )
+            .await;
+        }
+
+        match self.handle_body(&request.body).await {
+            Some(response) => {
+                let body = serde_json::to_vec(&response)?;
+                write_response(&mut stream, "200 OK", "application/json", &body).await
+            }
+            // Notifications get no JSON-RPC response
+            None => write_response(&mut stream, "204 No Content", "application/json", b"").await,
+        }
+    }
+
+    // Handle a single request or a batch, returning the JSON-RPC response (if any)
+    pub async fn handle_body(&self, body: &[u8]) -> Option<Value> {
+        let request: Value = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+                ))
+            }
+        };
+
+        match request {
+            Value::Array(batch) if !batch.is_empty() => {
+                let mut responses = vec![];
+                for request in batch {
+                    responses.extend(self.handle_request(request).await);
+                }
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            request => self.handle_request(request).await,
+        }
+    }
+
+    async fn handle_request(&self, request: Value) -> Option<Value> {
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(Value::as_str);
+
+        if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") || method.is_none() {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Invalid request"),
+            ));
+        }
+
+        let params = match request.get("params") {
+            None => vec![],
+            Some(Value::Array(params)) => params.clone(),
+            Some(_) => {
+                return Some(error_response(
+                    id.unwrap_or(Value::Null),
+                    RpcError::new(INVALID_PARAMS, "params must be an array"),
+                ))
+            }
+        };
+
+        let result = self.call(method.unwrap(), &params).await;
+
+        // A request without an id is a notification
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    async fn call(&self, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+        match method {
+            "chainId" => Ok(json!(self.lock()?.chain_id)),
+            "chainHeight" => Ok(json!(self.lock()?.height())),
+            "getBlockByNumber" => {
+                let block_number = param_u64(params, 0)?;
+                to_json(self.lock()?.get_block_by_number(block_number))
+            }
+            "getBlockByHash" => {
+                let block_hash = param_str(params, 0)?;
+                to_json(self.lock()?.get_block_by_hash(block_hash))
+            }
+            "getBalance" => {
+                let address = param_address(params, 0)?;
+                to_json(self.lock()?.get_balance(&address))
+            }
+            "getNonce" => {
+                let address = param_address(params, 0)?;
+                let chain = self.lock()?;
+                Ok(json!(chain.mempool.next_nonce(&chain.accounts, &address)))
+            }
+            "getTransaction" => {
+                let tx_hash = param_str(params, 0)?;
+                let chain = self.lock()?;
+                Ok(match chain.get_transaction(tx_hash) {
+                    Some((block, tx)) => json!({
+                        "transactionHash": tx_hash,
+                        "blockNumber": block.header.block_number,
+                        "blockHash": block.block_hash,
+                        "transaction": tx,
+                    }),
+                    None => Value::Null,
+                })
+            }
+            "sendRawTransaction" => {
+                let raw = param_str(params, 0)?;
+                self.send_raw_transaction(raw).await
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            )),
+        }
+    }
+
+    // Decode a hex encoded transaction (in the canonical encoding) and queue it in the mempool
+    async fn send_raw_transaction(&self, raw: &str) -> Result<Value, RpcError> {
+        let bytes = hex::decode(raw.trim_start_matches("0x"))
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid hex: {}", e)))?;
+        let tx: BlockTransaction = encoding::decode(&bytes)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid transaction: {}", e)))?;
+
+        let tx_hash = self
+            .lock()?
+            .submit_transaction(tx)
+            .map_err(|e| RpcError::new(TRANSACTION_REJECTED, e))?;
+        Ok(json!(tx_hash))
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, BharatChain>, RpcError> {
+        self.chain
+            .lock()
+            .map_err(|_| RpcError::new(INTERNAL_ERROR, "Chain lock poisoned"))
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| RpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+fn param_str(params: &[Value], index: usize) -> Result<&str, RpcError> {
+    params.get(index).and_then(Value::as_str).ok_or_else(|| {
+        RpcError::new(
+            INVALID_PARAMS,
+            format!("Expected a string parameter at position {}", index),
+        )
+    })
+}
+
+fn param_address(params: &[Value], index: usize) -> Result<Address, RpcError> {
+    param_str(params, index)?
+        .parse()
+        .map_err(|e: AddressError| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn param_u64(params: &[Value], index: usize) -> Result<u64, RpcError> {
+    params.get(index).and_then(Value::as_u64).ok_or_else(|| {
+        RpcError::new(
+            INVALID_PARAMS,
+            format!(
+                "Expected an unsigned integer parameter at position {}",
+                index
+            ),
+        )
+    })
+}
@@ -0,0 +1,412 @@
+use hmac::{Hmac, Mac};
+use ripemd::Ripemd160;
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+use sha2::{Digest as _, Sha256, Sha512};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::chain_core::address::Address;
+
+// Child numbers from here up derive hardened keys, which need the parent's
+// secret key; below it, children can also be derived from the public key
+pub const HARDENED: u32 = 1 << 31;
+
+// BIP-44 path levels: m / purpose' / coin_type' / account' / change / index
+pub const BIP44_PURPOSE: u32 = 44;
+pub const COIN_TYPE: u32 = 1947;
+
+// Version bytes of serialized extended keys (mainnet "xprv" and "xpub")
+const XPRV_VERSION: [u8; 4] = [0x04, 0x88, 0xad, 0xe4];
+const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+
+// Serialized extended key length before the base58check checksum
+const EXTENDED_KEY_LEN: usize = 78;
+
+// HMAC key turning a seed into the master key
+const MASTER_KEY_SALT: &[u8] = b"Bitcoin seed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChildNumber(u32);
+
+impl ChildNumber {
+    pub fn normal(index: u32) -> Result<Self, String> {
+        if index >= HARDENED {
+            return Err(format!("Child index {} is out of range", index));
+        }
+        Ok(ChildNumber(index))
+    }
+
+    pub fn hardened(index: u32) -> Result<Self, String> {
+        Ok(ChildNumber(ChildNumber::normal(index)?.0 | HARDENED))
+    }
+
+    pub fn is_hardened(&self) -> bool {
+        self.0 & HARDENED != 0
+    }
+
+    // Index without the hardened flag
+    pub fn index(&self) -> u32 {
+        self.0 & !HARDENED
+    }
+
+    pub fn to_u32(&self) -> u32 {
+        self.0
+    }
+}
+
+impl From<u32> for ChildNumber {
+    fn from(value: u32) -> Self {
+        ChildNumber(value)
+    }
+}
+
+impl fmt::Display for ChildNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_hardened() {
+            write!(f, "{}'", self.index())
+        } else {
+            write!(f, "{}", self.index())
+        }
+    }
+}
+
+impl FromStr for ChildNumber {
+    type Err = String;
+
+    // "7" is a normal child; "7'", "7h" and "7H" are hardened
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (index, hardened) = match s.strip_suffix(['\'', 'h', 'H']) {
+            Some(index) => (index, true),
+            None => (s, false),
+        };
+        let index = index
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid child number '{}'", s))?;
+        if hardened {
+            ChildNumber::hardened(index)
+        } else {
+            ChildNumber::normal(index)
+        }
+    }
+}
+
+// Path from the master key down to a descendant, such as "m/44'/1947'/0'/0/0"
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+    // BIP-44 path of the `index`th receiving (or, with `change`, change) key of an account
+    pub fn bip44(account: u32, change: bool, index: u32) -> Result<Self, String> {
+        Ok(DerivationPath(vec![
+            ChildNumber::hardened(BIP44_PURPOSE)?,
+            ChildNumber::hardened(COIN_TYPE)?,
+            ChildNumber::hardened(account)?,
+            ChildNumber::normal(change as u32)?,
+            ChildNumber::normal(index)?,
+        ]))
+    }
+
+    pub fn children(&self) -> &[ChildNumber] {
+        &self.0
+    }
+
+    pub fn child(&self, child: ChildNumber) -> Self {
+        let mut path = self.clone();
+        path.0.push(child);
+        path
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for child in &self.0 {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut parts = s.trim().split('/');
+        if parts.next() != Some("m") {
+            return Err(format!("Derivation path '{}' must start with 'm'", s));
+        }
+        parts
+            .map(ChildNumber::from_str)
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+// BIP-32 extended private key: a secret key plus the chain code needed to
+// derive its children, and its position in the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPrivateKey {
+    pub secret_key: SecretKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: ChildNumber,
+}
+
+impl ExtendedPrivateKey {
+    // Master key of the tree grown from a seed (16 to 64 bytes, such as a
+    // BIP-39 mnemonic's seed)
+    pub fn from_seed(seed: &[u8]) -> Result<Self, String> {
+        if !(16..=64).contains(&seed.len()) {
+            return Err("Seed must be between 16 and 64 bytes".to_string());
+        }
+        let (key, chain_code) = hmac_sha512(MASTER_KEY_SALT, &[seed]);
+        let secret_key = SecretKey::from_slice(&key)
+            .map_err(|_| "Seed gives an invalid master key".to_string())?;
+        Ok(ExtendedPrivateKey {
+            secret_key,
+            chain_code,
+            depth: 0,
+            parent_fingerprint: [0; 4],
+            child_number: ChildNumber(0),
+        })
+    }
+
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self, String> {
+        let index = child.to_u32().to_be_bytes();
+        let (tweak, chain_code) = if child.is_hardened() {
+            let secret = self.secret_key.secret_bytes();
+            hmac_sha512(&self.chain_code, &[&[0], &secret, &index])
+        } else {
+            let public = self.public_key().serialize();
+            hmac_sha512(&self.chain_code, &[&public, &index])
+        };
+
+        // Vanishingly unlikely; BIP-32 says to move on to the next index
+        let secret_key = Scalar::from_be_bytes(tweak)
+            .ok()
+            .and_then(|tweak| self.secret_key.add_tweak(&tweak).ok())
+            .ok_or_else(|| format!("Child {} is not a valid key", child))?;
+
+        Ok(ExtendedPrivateKey {
+            secret_key,
+            chain_code,
+            depth: child_depth(self.depth)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: child,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, String> {
+        path.children()
+            .iter()
+            .try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &self.secret_key)
+    }
+
+    // The matching extended public key, for deriving non-hardened children
+    // without the secret
+    pub fn to_extended_public_key(&self) -> ExtendedPublicKey {
+        ExtendedPublicKey {
+            public_key: self.public_key(),
+            chain_code: self.chain_code,
+            depth: self.depth,
+            parent_fingerprint: self.parent_fingerprint,
+            child_number: self.child_number,
+        }
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.public_key())
+    }
+
+    // Account address controlled by this key
+    pub fn address(&self) -> Address {
+        Address::from_public_key(&self.public_key())
+    }
+}
+
+impl fmt::Display for ExtendedPrivateKey {
+    // Base58check "xprv..." serialization
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut key = [0u8; 33];
+        key[1..].copy_from_slice(&self.secret_key.secret_bytes());
+        let bytes = serialize(
+            XPRV_VERSION,
+            self.depth,
+            self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key,
+        );
+        write!(f, "{}", bs58::encode(bytes).with_check().into_string())
+    }
+}
+
+impl FromStr for ExtendedPrivateKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bytes = deserialize(s, XPRV_VERSION)?;
+        if bytes[45] != 0 {
+            return Err("Extended private key has a malformed key".to_string());
+        }
+        Ok(ExtendedPrivateKey {
+            secret_key: SecretKey::from_slice(&bytes[46..78])
+                .map_err(|e| format!("Invalid extended private key: {}", e))?,
+            chain_code: bytes[13..45].try_into().unwrap(),
+            depth: bytes[4],
+            parent_fingerprint: bytes[5..9].try_into().unwrap(),
+            child_number: ChildNumber(u32::from_be_bytes(bytes[9..13].try_into().unwrap())),
+        })
+    }
+}
+
+// BIP-32 extended public key: derives the public keys, and so the addresses,
+// of non-hardened children
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtendedPublicKey {
+    pub public_key: PublicKey,
+    pub chain_code: [u8; 32],
+    pub depth: u8,
+    pub parent_fingerprint: [u8; 4],
+    pub child_number: ChildNumber,
+}
+
+impl ExtendedPublicKey {
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self, String> {
+        if child.is_hardened() {
+            return Err(format!(
+                "Hardened child {} needs the extended private key",
+                child
+            ));
+        }
+        let index = child.to_u32().to_be_bytes();
+        let (tweak, chain_code) =
+            hmac_sha512(&self.chain_code, &[&self.public_key.serialize(), &index]);
+
+        let public_key = Scalar::from_be_bytes(tweak)
+            .ok()
+            .and_then(|tweak| {
+                self.public_key
+                    .add_exp_tweak(&Secp256k1::verification_only(), &tweak)
+                    .ok()
+            })
+            .ok_or_else(|| format!("Child {} is not a valid key", child))?;
+
+        Ok(ExtendedPublicKey {
+            public_key,
+            chain_code,
+            depth: child_depth(self.depth)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: child,
+        })
+    }
+
+    pub fn derive_path(&self, path: &DerivationPath) -> Result<Self, String> {
+        path.children()
+            .iter()
+            .try_fold(self.clone(), |key, child| key.derive_child(*child))
+    }
+
+    pub fn fingerprint(&self) -> [u8; 4] {
+        fingerprint(&self.public_key)
+    }
+
+    pub fn address(&self) -> Address {
+        Address::from_public_key(&self.public_key)
+    }
+}
+
+impl fmt::Display for ExtendedPublicKey {
+    // Base58check "xpub..." serialization
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = serialize(
+            XPUB_VERSION,
+            self.depth,
+            self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &self.public_key.serialize(),
+        );
+        write!(f, "{}", bs58::encode(bytes).with_check().into_string())
+    }
+}
+
+impl FromStr for ExtendedPublicKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bytes = deserialize(s, XPUB_VERSION)?;
+        Ok(ExtendedPublicKey {
+            public_key: PublicKey::from_slice(&bytes[45..78])
+                .map_err(|e| format!("Invalid extended public key: {}", e))?,
+            chain_code: bytes[13..45].try_into().unwrap(),
+            depth: bytes[4],
+            parent_fingerprint: bytes[5..9].try_into().unwrap(),
+            child_number: ChildNumber(u32::from_be_bytes(bytes[9..13].try_into().unwrap())),
+        })
+    }
+}
+
+// Left half of HMAC-SHA512 over the concatenated `data` is the key material,
+// the right half the chain code
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in data {
+        mac.update(part);
+    }
+    let output = mac.finalize().into_bytes();
+    (
+        output[..32].try_into().unwrap(),
+        output[32..].try_into().unwrap(),
+    )
+}
+
+// First four bytes of HASH160 of the compressed public key; identifies a parent
+fn fingerprint(public_key: &PublicKey) -> [u8; 4] {
+    let hash = Ripemd160::digest(Sha256::digest(public_key.serialize()));
+    hash[..4].try_into().unwrap()
+}
+
+fn child_depth(depth: u8) -> Result<u8, String> {
+    depth
+        .checked_add(1)
+        .ok_or_else(|| "Derivation path is too deep".to_string())
+}
+
+fn serialize(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: [u8; 4],
+    child_number: ChildNumber,
+    chain_code: &[u8; 32],
+    key: &[u8; 33],
+) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(EXTENDED_KEY_LEN);
+    bytes.extend_from_slice(&version);
+    bytes.push(depth);
+    bytes.extend_from_slice(&parent_fingerprint);
+    bytes.extend_from_slice(&child_number.to_u32().to_be_bytes());
+    bytes.extend_from_slice(chain_code);
+    bytes.extend_from_slice(key);
+    bytes
+}
+
+// Decode a base58check extended key, checking its length and version
+fn deserialize(s: &str, version: [u8; 4]) -> Result<Vec<u8>, String> {
+    let bytes = bs58::decode(s)
+        .with_check(None)
+        .into_vec()
+        .map_err(|e| format!("Invalid extended key: {}", e))?;
+    if bytes.len() != EXTENDED_KEY_LEN {
+        return Err("Extended key has the wrong length".to_string());
+    }
+    if bytes[..4] != version {
+        return Err("Extended key has an unexpected version".to_string());
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,244 @@
+use aes::Aes128;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use hmac::Hmac;
+use rand::{rngs::OsRng, RngCore};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest as _, Keccak256};
+use subtle::ConstantTimeEq;
+
+use crate::chain_core::address::Address;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+// Keystore layout version, following the Web3 Secret Storage definition
+pub const KEYSTORE_VERSION: u32 = 3;
+
+// Only cipher supported for the encrypted key
+const CIPHER: &str = "aes-128-ctr";
+
+// Length of the key derived from the password: half encrypts, half authenticates
+const DERIVED_KEY_LEN: usize = 32;
+
+// Password-based key derivation, with its parameters as stored in the file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+pub enum Kdf {
+    Scrypt {
+        dklen: usize,
+        n: u64, // CPU/memory cost; a power of two
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,      // Iterations
+        prf: String, // Only hmac-sha256
+        salt: String,
+    },
+}
+
+impl Kdf {
+    // Scrypt with cost 2^log_n and a fresh random salt. The default cost takes
+    // around a second and 128 MiB; lower costs are only meant for tests.
+    pub fn scrypt(log_n: u8) -> Self {
+        Kdf::Scrypt {
+            dklen: DERIVED_KEY_LEN,
+            n: 1 << log_n,
+            r: 8,
+            p: 1,
+            salt: hex::encode(random_bytes::<32>()),
+        }
+    }
+
+    pub fn pbkdf2(iterations: u32) -> Self {
+        Kdf::Pbkdf2 {
+            dklen: DERIVED_KEY_LEN,
+            c: iterations,
+            prf: "hmac-sha256".to_string(),
+            salt: hex::encode(random_bytes::<32>()),
+        }
+    }
+
+    // Same parameters with a new random salt
+    pub fn with_fresh_salt(&self) -> Self {
+        match self {
+            Kdf::Scrypt { n, .. } => Kdf::scrypt(n.trailing_zeros() as u8),
+            Kdf::Pbkdf2 { c, .. } => Kdf::pbkdf2(*c),
+        }
+    }
+
+    fn derive_key(&self, password: &str) -> Result<Vec<u8>, String> {
+        let mut key = vec![0u8; DERIVED_KEY_LEN];
+        match self {
+            Kdf::Scrypt {
+                dklen,
+                n,
+                r,
+                p,
+                salt,
+            } => {
+                if *dklen != DERIVED_KEY_LEN || !n.is_power_of_two() {
+                    return Err("Unsupported scrypt parameters".to_string());
+                }
+                let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p, *dklen)
+                    .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+                scrypt::scrypt(password.as_bytes(), &decode_hex(salt)?, &params, &mut key)
+                    .map_err(|e| format!("Key derivation failed: {}", e))?;
+            }
+            Kdf::Pbkdf2 {
+                dklen,
+                c,
+                prf,
+                salt,
+            } => {
+                if *dklen != DERIVED_KEY_LEN || prf != "hmac-sha256" {
+                    return Err("Unsupported PBKDF2 parameters".to_string());
+                }
+                pbkdf2::pbkdf2::<Hmac<Sha256>>(
+                    password.as_bytes(),
+                    &decode_hex(salt)?,
+                    *c,
+                    &mut key,
+                )
+                .map_err(|e| format!("Key derivation failed: {}", e))?;
+            }
+        }
+        Ok(key)
+    }
+}
+
+impl Default for Kdf {
+    fn default() -> Self {
+        Kdf::scrypt(scrypt::Params::RECOMMENDED_LOG_N)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeystoreCrypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    pub ciphertext: String,
+    #[serde(flatten)]
+    pub kdf: Kdf,
+    pub mac: String, // Keccak-256 of the second half of the derived key and the ciphertext
+}
+
+// A secret key encrypted under a password, in the Web3 Secret Storage (v3)
+// JSON layout. The key is encrypted with AES-128-CTR under the first half of
+// a key derived from the password; the second half authenticates the
+// ciphertext, so a wrong password is detected before anything is decrypted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub id: String,
+    pub address: Address,
+    pub crypto: KeystoreCrypto,
+}
+
+impl Keystore {
+    pub fn encrypt(secret_key: &SecretKey, password: &str, kdf: Kdf) -> Result<Self, String> {
+        let derived = kdf.derive_key(password)?;
+        let iv = random_bytes::<16>();
+
+        let mut ciphertext = secret_key.secret_bytes().to_vec();
+        Aes128Ctr::new(derived[..16].into(), &iv.into()).apply_keystream(&mut ciphertext);
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            id: random_uuid(),
+            address: address_of(secret_key),
+            crypto: KeystoreCrypto {
+                cipher: CIPHER.to_string(),
+                cipherparams: CipherParams {
+                    iv: hex::encode(iv),
+                },
+                ciphertext: hex::encode(&ciphertext),
+                mac: hex::encode(mac(&derived, &ciphertext)),
+                kdf,
+            },
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<SecretKey, String> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(format!("Unsupported keystore version {}", self.version));
+        }
+        if self.crypto.cipher != CIPHER {
+            return Err(format!("Unsupported cipher {}", self.crypto.cipher));
+        }
+
+        let derived = self.crypto.kdf.derive_key(password)?;
+        let mut plaintext = decode_hex(&self.crypto.ciphertext)?;
+        // Compared in constant time so the check leaks nothing about the expected MAC
+        let expected = decode_hex(&self.crypto.mac)?;
+        if !bool::from(mac(&derived, &plaintext).ct_eq(&expected)) {
+            return Err("Wrong password or corrupted keystore".to_string());
+        }
+
+        let iv: [u8; 16] = decode_hex(&self.crypto.cipherparams.iv)?
+            .try_into()
+            .map_err(|_| "Keystore IV must be 16 bytes".to_string())?;
+        Aes128Ctr::new(derived[..16].into(), &iv.into()).apply_keystream(&mut plaintext);
+
+        let secret_key = SecretKey::from_slice(&plaintext)
+            .map_err(|e| format!("Keystore holds an invalid key: {}", e))?;
+        if address_of(&secret_key) != self.address {
+            return Err("Keystore key does not match its address".to_string());
+        }
+        Ok(secret_key)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("keystores always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid keystore: {}", e))
+    }
+}
+
+// Account address controlled by a secret key
+pub fn address_of(secret_key: &SecretKey) -> Address {
+    Address::from_public_key(&PublicKey::from_secret_key(&Secp256k1::new(), secret_key))
+}
+
+fn mac(derived: &[u8], ciphertext: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&derived[16..32]);
+    hasher.update(ciphertext);
+    hasher.finalize().into()
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+// Random (version 4) UUID identifying the keystore file
+fn random_uuid() -> String {
+    let mut bytes = random_bytes::<16>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value).map_err(|e| format!("Invalid hex in keystore: {}", e))
+}
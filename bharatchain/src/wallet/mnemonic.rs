@@ -0,0 +1,72 @@
+use rand::{rngs::OsRng, RngCore};
+use std::fmt;
+
+// Word counts BIP-39 allows; each word carries 11 bits of entropy and checksum
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+// Words in newly generated phrases: 256 bits of entropy
+pub const DEFAULT_WORD_COUNT: usize = 24;
+
+// A BIP-39 seed phrase from the English wordlist. The phrase encodes random
+// entropy plus a checksum, so a mistyped word is caught when it is parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mnemonic(bip39::Mnemonic);
+
+impl Mnemonic {
+    // A fresh phrase of `word_count` words
+    pub fn generate(word_count: usize) -> Result<Self, String> {
+        if !WORD_COUNTS.contains(&word_count) {
+            return Err(format!(
+                "A mnemonic has 12, 15, 18, 21 or 24 words, not {}",
+                word_count
+            ));
+        }
+        let mut entropy = vec![0u8; word_count / 3 * 4];
+        OsRng.fill_bytes(&mut entropy);
+        Mnemonic::from_entropy(&entropy)
+    }
+
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, String> {
+        bip39::Mnemonic::from_entropy(entropy)
+            .map(Mnemonic)
+            .map_err(|e| format!("Invalid mnemonic entropy: {}", e))
+    }
+
+    // Validate a phrase: every word must be in the wordlist and the checksum
+    // must match. Whitespace between words and letter case do not matter.
+    pub fn parse(phrase: &str) -> Result<Self, String> {
+        let phrase = phrase
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase();
+        bip39::Mnemonic::parse(phrase)
+            .map(Mnemonic)
+            .map_err(|e| format!("Invalid mnemonic: {}", e))
+    }
+
+    pub fn phrase(&self) -> String {
+        self.0.to_string()
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.0.word_count()
+    }
+
+    pub fn entropy(&self) -> Vec<u8> {
+        self.0.to_entropy()
+    }
+
+    // 64-byte seed for BIP-32 derivation: PBKDF2-HMAC-SHA512 of the phrase,
+    // salted with the optional passphrase. Any passphrase gives a valid but
+    // different wallet.
+    pub fn to_seed(&self, passphrase: &str) -> [u8; 64] {
+        self.0.to_seed(passphrase)
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,121 @@
+use rand::rngs::OsRng;
+use secp256k1::SecretKey;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::chain_core::address::Address;
+use crate::chain_core::transaction::BlockTransaction;
+use keystore::{address_of, Kdf, Keystore};
+
+pub mod hd;
+pub mod keystore;
+pub mod mnemonic;
+
+// Keys held in a directory of encrypted keystore files, one per address and
+// named after it. Keys are only decrypted, with their password, to sign.
+#[derive(Debug, Clone)]
+pub struct Wallet {
+    dir: PathBuf,
+    kdf: Kdf, // Key derivation for newly stored keys; each gets a fresh salt
+}
+
+impl Wallet {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create keystore {}: {}", dir.display(), e))?;
+        Ok(Wallet {
+            dir,
+            kdf: Kdf::default(),
+        })
+    }
+
+    // Store new keys with `kdf` instead of the default scrypt parameters
+    pub fn with_kdf(mut self, kdf: Kdf) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    // Generate a key and store it encrypted under `password`, returning its address
+    pub fn generate(&self, password: &str) -> Result<Address, String> {
+        let secret_key = SecretKey::new(&mut OsRng);
+        self.import(&secret_key, password)
+    }
+
+    // Store an existing key encrypted under `password`, returning its address
+    pub fn import(&self, secret_key: &SecretKey, password: &str) -> Result<Address, String> {
+        let address = address_of(secret_key);
+        let path = self.path(&address);
+        if path.exists() {
+            return Err(format!("Wallet already holds {}", address));
+        }
+
+        let keystore = Keystore::encrypt(secret_key, password, self.kdf.with_fresh_salt())?;
+        write_private(&path, keystore.to_json().as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(keystore.address)
+    }
+
+    // Addresses of every key in the wallet, sorted
+    pub fn addresses(&self) -> Result<Vec<Address>, String> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read keystore {}: {}", self.dir.display(), e))?;
+
+        let mut addresses = vec![];
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                addresses.push(read_keystore(&path)?.address);
+            }
+        }
+        addresses.sort();
+        Ok(addresses)
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.path(address).exists()
+    }
+
+    // Decrypt the key for `address`
+    pub fn unlock(&self, address: &Address, password: &str) -> Result<SecretKey, String> {
+        if !self.contains(address) {
+            return Err(format!("Wallet does not hold {}", address));
+        }
+        let keystore = read_keystore(&self.path(address))?;
+        if keystore.address != *address {
+            return Err(format!("Keystore for {} holds another address", address));
+        }
+        keystore.decrypt(password)
+    }
+
+    // Sign a transaction with the key for its sender
+    pub fn sign(&self, tx: &mut BlockTransaction, password: &str) -> Result<(), String> {
+        let secret_key = self.unlock(&tx.sender, password)?;
+        tx.sign(&secret_key);
+        Ok(())
+    }
+
+    fn path(&self, address: &Address) -> PathBuf {
+        self.dir.join(format!("{}.json", address))
+    }
+}
+
+fn read_keystore(path: &Path) -> Result<Keystore, String> {
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Keystore::from_json(&json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Create a file only the owner can read
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
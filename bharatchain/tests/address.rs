@@ -0,0 +1,157 @@
+use bech32::{Bech32, Bech32m, ByteIterExt, Fe32, Fe32IterExt, Hrp};
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::{Address, AddressError, ADDRESS_HRP};
+use bharatchain::chain_core::encoding;
+
+const KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+
+// Hash 00 01 02 .. 1f; reference string from an independent bech32m implementation
+const SEQUENTIAL: &str = "bharat1qqqqzqsrqszsvpcgpy9qkrqdpc83qygjzv2p29shrqv35xcur50p79f49la";
+
+fn sequential_hash() -> [u8; 32] {
+    std::array::from_fn(|i| i as u8)
+}
+
+// String of `hrp` and `data` in 5-bit characters with checksum `Ck`,
+// letting `tweak` change the characters before the checksum is added
+fn encode<Ck: bech32::Checksum>(
+    hrp: &str,
+    data: &[u8],
+    tweak: impl FnOnce(&mut Vec<Fe32>),
+) -> String {
+    let mut fes: Vec<Fe32> = data.iter().copied().bytes_to_fes().collect();
+    tweak(&mut fes);
+    fes.into_iter()
+        .with_checksum::<Ck>(&Hrp::parse(hrp).unwrap())
+        .chars()
+        .collect()
+}
+
+fn payload(version: u8, hash: &[u8]) -> Vec<u8> {
+    let mut data = vec![version];
+    data.extend_from_slice(hash);
+    data
+}
+
+#[test]
+fn known_addresses() {
+    assert_eq!(
+        Address::ZERO.to_string(),
+        "bharat1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqyzfpsr"
+    );
+    assert_eq!(
+        Address::from_hash(sequential_hash()).to_string(),
+        SEQUENTIAL
+    );
+    assert_eq!(
+        SEQUENTIAL.parse::<Address>().unwrap().hash(),
+        &sequential_hash()
+    );
+}
+
+#[test]
+fn round_trips_through_every_form() {
+    let address = Account::from_secret_key(KEY, Default::default())
+        .unwrap()
+        .address;
+    let text = address.to_string();
+    assert!(text.starts_with("bharat1"));
+    assert_eq!(text.parse::<Address>().unwrap(), address);
+    assert!(Address::is_valid(&text));
+
+    // Uppercase is the same address; surrounding whitespace is ignored
+    assert_eq!(text.to_uppercase().parse::<Address>().unwrap(), address);
+    assert_eq!(format!(" {}\n", text).parse::<Address>().unwrap(), address);
+
+    let json = serde_json::to_string(&address).unwrap();
+    assert_eq!(json, format!("\"{}\"", text));
+    assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+
+    let bytes = encoding::encode(&address);
+    assert_eq!(bytes.len(), 1 + 1 + 32); // encoding version, address version, hash
+    assert_eq!(encoding::decode::<Address>(&bytes).unwrap(), address);
+}
+
+#[test]
+fn bad_checksum_is_rejected() {
+    let mut chars: Vec<char> = SEQUENTIAL.chars().collect();
+    let last = chars.len() - 1;
+    chars[last] = if chars[last] == 'q' { 'p' } else { 'q' };
+    let typo: String = chars.into_iter().collect();
+
+    assert_eq!(
+        typo.parse::<Address>(),
+        Err(AddressError::InvalidEncoding(
+            "checksum does not match".to_string()
+        ))
+    );
+    assert!(!Address::is_valid(&typo));
+
+    // The same data under the original bech32 checksum
+    let bech32 = encode::<Bech32>("bharat", &payload(0, &sequential_hash()), |_| {});
+    assert!(bech32.parse::<Address>().is_err());
+}
+
+#[test]
+fn wrong_prefix_is_rejected() {
+    let other = encode::<Bech32m>("bc", &payload(0, &sequential_hash()), |_| {});
+    assert_eq!(
+        other.parse::<Address>(),
+        Err(AddressError::WrongPrefix("bc".to_string()))
+    );
+    assert_eq!(ADDRESS_HRP.to_string(), "bharat");
+}
+
+#[test]
+fn mixed_case_is_rejected() {
+    let mixed = SEQUENTIAL.replacen('q', "Q", 1);
+    assert!(matches!(
+        mixed.parse::<Address>(),
+        Err(AddressError::InvalidEncoding(_))
+    ));
+}
+
+#[test]
+fn unsupported_version_is_rejected() {
+    let v1 = encode::<Bech32m>("bharat", &payload(1, &sequential_hash()), |_| {});
+    assert_eq!(
+        v1.parse::<Address>(),
+        Err(AddressError::UnsupportedVersion(1))
+    );
+
+    let mut bytes = encoding::encode(&Address::ZERO);
+    bytes[1] = 1;
+    assert!(encoding::decode::<Address>(&bytes).is_err());
+}
+
+#[test]
+fn wrong_length_is_rejected() {
+    for len in [0, 20, 31, 33] {
+        let text = encode::<Bech32m>("bharat", &payload(0, &vec![7; len]), |_| {});
+        assert_eq!(
+            text.parse::<Address>(),
+            Err(AddressError::InvalidLength(len)),
+            "{} byte hash",
+            len
+        );
+    }
+    assert_eq!(
+        encode::<Bech32m>("bharat", &[], |_| {}).parse::<Address>(),
+        Err(AddressError::InvalidLength(0))
+    );
+}
+
+#[test]
+fn nonzero_padding_is_rejected() {
+    // 33 bytes fill 53 characters with one bit to spare, which must be zero
+    let padded = encode::<Bech32m>("bharat", &payload(0, &sequential_hash()), |fes| {
+        let last = fes.last_mut().unwrap();
+        *last = Fe32::try_from(last.to_u8() | 1).unwrap();
+    });
+    assert_eq!(
+        padded.parse::<Address>(),
+        Err(AddressError::InvalidEncoding(
+            "non-zero padding".to_string()
+        ))
+    );
+}
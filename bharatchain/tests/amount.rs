@@ -0,0 +1,121 @@
+use bharatchain::chain_core::amount::{Amount, AmountError, UNITS_PER_COIN};
+
+fn parse(s: &str) -> Result<u64, AmountError> {
+    s.parse::<Amount>().map(|amount| amount.base_units())
+}
+
+#[test]
+fn display_trims_trailing_zeros() {
+    assert_eq!(Amount::ZERO.to_string(), "0");
+    assert_eq!(Amount::from_coins(50).unwrap().to_string(), "50");
+    assert_eq!(
+        Amount::from_base_units(30_045_000_000).to_string(),
+        "300.45"
+    );
+    assert_eq!(Amount::from_base_units(1).to_string(), "0.00000001");
+    assert_eq!(Amount::from_base_units(10).to_string(), "0.0000001");
+    assert_eq!(Amount::MAX.to_string(), "184467440737.09551615");
+}
+
+#[test]
+fn parses_decimal_strings() {
+    assert_eq!(parse("300.45"), Ok(30_045_000_000));
+    assert_eq!(parse("50"), Ok(50 * UNITS_PER_COIN));
+    assert_eq!(parse("50."), Ok(50 * UNITS_PER_COIN));
+    assert_eq!(parse(".5"), Ok(UNITS_PER_COIN / 2));
+    assert_eq!(parse("0.00000001"), Ok(1));
+    assert_eq!(parse("007.10"), Ok(710_000_000));
+    assert_eq!(parse(" 1.5\n"), Ok(150_000_000));
+    assert_eq!(parse("184467440737.09551615"), Ok(u64::MAX));
+}
+
+#[test]
+fn display_and_parse_round_trip() {
+    for units in [
+        0,
+        1,
+        99,
+        UNITS_PER_COIN - 1,
+        UNITS_PER_COIN,
+        UNITS_PER_COIN + 1,
+        30_045_000_000,
+        123_456_789_012_345,
+        u64::MAX,
+    ] {
+        let amount = Amount::from_base_units(units);
+        assert_eq!(
+            amount.to_string().parse::<Amount>(),
+            Ok(amount),
+            "{}",
+            units
+        );
+    }
+}
+
+#[test]
+fn more_than_eight_decimals_is_rejected() {
+    assert_eq!(parse("0.000000001"), Err(AmountError::TooManyDecimals));
+    assert_eq!(parse("1.000000000"), Err(AmountError::TooManyDecimals));
+}
+
+#[test]
+fn negative_amounts_are_rejected() {
+    assert_eq!(parse("-1"), Err(AmountError::Negative));
+    assert_eq!(parse(" -0.5"), Err(AmountError::Negative));
+}
+
+#[test]
+fn malformed_strings_are_rejected() {
+    for s in ["", ".", "abc", "1.2.3", "1,5", "+1", "1e8", "0x10", "1 000"] {
+        assert!(
+            matches!(parse(s), Err(AmountError::InvalidFormat(_))),
+            "{:?}",
+            s
+        );
+    }
+}
+
+#[test]
+fn values_beyond_u64_overflow() {
+    // One base unit past the maximum
+    assert_eq!(parse("184467440737.09551616"), Err(AmountError::Overflow));
+    // Whole coins that fit in u64 but not once scaled to base units
+    assert_eq!(parse("184467440738"), Err(AmountError::Overflow));
+    // Whole coins that do not fit in u64 at all
+    assert_eq!(parse("18446744073709551616"), Err(AmountError::Overflow));
+    assert_eq!(Amount::from_coins(u64::MAX), Err(AmountError::Overflow));
+}
+
+#[test]
+fn checked_arithmetic() {
+    let one = Amount::from_base_units(1);
+    assert_eq!(one.checked_add(one), Ok(Amount::from_base_units(2)));
+    assert_eq!(Amount::MAX.checked_add(Amount::ZERO), Ok(Amount::MAX));
+    assert_eq!(Amount::MAX.checked_add(one), Err(AmountError::Overflow));
+
+    assert_eq!(one.checked_sub(one), Ok(Amount::ZERO));
+    assert_eq!(
+        Amount::ZERO.checked_sub(one),
+        Err(AmountError::InsufficientFunds)
+    );
+}
+
+#[test]
+fn serializes_as_a_string() {
+    let amount = Amount::from_base_units(30_045_000_000);
+    assert_eq!(serde_json::to_string(&amount).unwrap(), "\"300.45\"");
+    assert_eq!(
+        serde_json::from_str::<Amount>("\"300.45\"").unwrap(),
+        amount
+    );
+    assert_eq!(
+        serde_json::to_string(&Amount::MAX).unwrap(),
+        "\"184467440737.09551615\""
+    );
+
+    // Numbers are refused so amounts never pass through floating point
+    assert!(serde_json::from_str::<Amount>("300.45").is_err());
+    assert!(serde_json::from_str::<Amount>("30045000000").is_err());
+    assert!(serde_json::from_str::<Amount>("\"-1\"").is_err());
+    assert!(serde_json::from_str::<Amount>("\"0.000000001\"").is_err());
+}
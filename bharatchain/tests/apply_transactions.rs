@@ -0,0 +1,184 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::{DataBlock, ReceiptStatus, TransactionReceipt};
+use bharatchain::chain_core::chain::{BharatChain, DEFAULT_CHAIN_ID};
+use bharatchain::chain_core::genesis::GenesisConfig;
+use bharatchain::chain_core::reward::RewardSchedule;
+use bharatchain::chain_core::state_db::StateDB;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use secp256k1::SecretKey;
+
+// Secret key `n` (1, 2, ...) as hex
+fn key(n: u8) -> String {
+    format!("{:064x}", n)
+}
+
+fn address(n: u8) -> Address {
+    Account::from_secret_key(&key(n), Amount::ZERO)
+        .unwrap()
+        .address
+}
+
+fn coins(amount: &str) -> Amount {
+    amount.parse().unwrap()
+}
+
+// Account 1 holds 100 coins; accounts 2 and 3 are empty
+fn accounts() -> StateDB {
+    [
+        Account::from_secret_key(&key(1), coins("100")).unwrap(),
+        Account::from_secret_key(&key(2), Amount::ZERO).unwrap(),
+        Account::from_secret_key(&key(3), Amount::ZERO).unwrap(),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn transfer(from: u8, to: u8, amount: &str, nonce: u64) -> BlockTransaction {
+    let mut tx = BlockTransaction::new(
+        address(from),
+        address(to),
+        coins(amount),
+        nonce,
+        DEFAULT_CHAIN_ID,
+    );
+    tx.sign(&SecretKey::from_slice(&hex::decode(key(from)).unwrap()).unwrap());
+    tx
+}
+
+fn apply(
+    accounts: &mut StateDB,
+    txns: Vec<BlockTransaction>,
+) -> Result<Vec<TransactionReceipt>, Vec<TransactionReceipt>> {
+    DataBlock::new(1, String::new(), txns).apply_transactions(
+        accounts,
+        &RewardSchedule::default(),
+        DEFAULT_CHAIN_ID,
+    )
+}
+
+fn statuses(receipts: &[TransactionReceipt]) -> Vec<ReceiptStatus> {
+    receipts
+        .iter()
+        .map(|receipt| receipt.status.clone())
+        .collect()
+}
+
+// (balance, nonce) of accounts 1 to 3
+fn state(accounts: &StateDB) -> Vec<(Amount, u64)> {
+    (1..=3)
+        .map(|n| {
+            let account = accounts.get(&address(n)).unwrap();
+            (account.balance, account.nonce)
+        })
+        .collect()
+}
+
+#[test]
+fn transactions_see_the_effects_of_earlier_ones() {
+    // Account 2 can only pay account 3 after it has been paid
+    let mut accounts = accounts();
+    let txns = vec![transfer(1, 2, "60", 0), transfer(2, 3, "50", 0)];
+    let hashes: Vec<String> = txns.iter().map(|tx| tx.compute_hash()).collect();
+
+    let receipts = apply(&mut accounts, txns).unwrap();
+    assert_eq!(
+        statuses(&receipts),
+        vec![ReceiptStatus::Applied, ReceiptStatus::Applied]
+    );
+    let receipt_hashes: Vec<String> = receipts.into_iter().map(|r| r.tx_hash).collect();
+    assert_eq!(receipt_hashes, hashes);
+    assert_eq!(
+        state(&accounts),
+        vec![(coins("40"), 1), (coins("10"), 1), (coins("50"), 0)]
+    );
+
+    // The other way round the second transfer has nothing to spend yet
+    let mut accounts = self::accounts();
+    let receipts = apply(
+        &mut accounts,
+        vec![transfer(2, 3, "50", 0), transfer(1, 2, "60", 0)],
+    )
+    .unwrap_err();
+    assert_eq!(
+        statuses(&receipts),
+        vec![
+            ReceiptStatus::Failed("Insufficient funds".to_string()),
+            ReceiptStatus::Skipped
+        ]
+    );
+}
+
+#[test]
+fn funds_cannot_be_spent_twice_in_one_block() {
+    let mut accounts = accounts();
+    let receipts = apply(
+        &mut accounts,
+        vec![transfer(1, 2, "60", 0), transfer(1, 3, "60", 1)],
+    )
+    .unwrap_err();
+    assert_eq!(
+        statuses(&receipts),
+        vec![
+            ReceiptStatus::Applied,
+            ReceiptStatus::Failed("Insufficient funds".to_string())
+        ]
+    );
+    // The first transfer is rolled back with the rest of the block
+    assert_eq!(state(&accounts), state(&self::accounts()));
+}
+
+#[test]
+fn failure_skips_the_rest_and_leaves_the_state_untouched() {
+    let mut accounts = accounts();
+    let mut forged = transfer(1, 3, "1", 1);
+    forged.amount = coins("2");
+    let receipts = apply(
+        &mut accounts,
+        vec![
+            transfer(1, 2, "10", 0),
+            forged,
+            transfer(1, 3, "10", 1),
+            transfer(2, 3, "5", 0),
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(
+        statuses(&receipts),
+        vec![
+            ReceiptStatus::Applied,
+            ReceiptStatus::Failed("Signature verification failed".to_string()),
+            ReceiptStatus::Skipped,
+            ReceiptStatus::Skipped
+        ]
+    );
+    assert_eq!(state(&accounts), state(&self::accounts()));
+}
+
+#[test]
+fn empty_block_applies_with_no_receipts() {
+    let mut accounts = accounts();
+    assert_eq!(apply(&mut accounts, vec![]).unwrap().len(), 0);
+    assert_eq!(state(&accounts), state(&self::accounts()));
+}
+
+#[test]
+fn chain_refuses_a_block_that_fails_to_apply() {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    chain.accounts = accounts();
+
+    let err = chain
+        .add_block(vec![transfer(1, 2, "60", 0), transfer(1, 3, "60", 1)])
+        .unwrap_err();
+    assert!(err.contains("Insufficient funds"), "{}", err);
+    assert_eq!(chain.chain.len(), 1);
+    assert_eq!(state(&chain.accounts), state(&accounts()));
+
+    let receipts = chain
+        .add_block(vec![transfer(1, 2, "60", 0), transfer(2, 3, "60", 0)])
+        .unwrap();
+    assert_eq!(receipts.len(), 2);
+    assert_eq!(chain.chain.len(), 2);
+    assert_eq!(chain.get_balance(&address(3)), Some(coins("60")));
+}
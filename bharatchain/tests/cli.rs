@@ -0,0 +1,430 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::block::DataBlock;
+use bharatchain::chain_core::chain::DEFAULT_CHAIN_ID;
+use bharatchain::chain_core::genesis::GenesisConfig;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const MINER_KEY: &str = "b0b0000000000000000000000000000000000000000000000000000000000002";
+
+// Standard 12-word test vector phrase
+const MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+// Fresh scratch directory for one test
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bharatchain-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn miner_address() -> String {
+    Account::from_secret_key(MINER_KEY, Default::default())
+        .unwrap()
+        .address
+        .to_string()
+}
+
+// Run the binary on `data_dir`, without picking up settings from the environment
+fn run(data_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bharatchain"))
+        .arg("--data-dir")
+        .arg(data_dir)
+        .args(args)
+        .env_remove("BHARATCHAIN_DATA_DIR")
+        .env_remove("BHARATCHAIN_KEYSTORE")
+        .env_remove("BHARATCHAIN_PASSWORD")
+        .output()
+        .unwrap()
+}
+
+// Exit code and the JSON printed: the result on stdout, or the error on stderr
+fn bharatchain(data_dir: &Path, args: &[&str]) -> (i32, Value) {
+    let output = run(data_dir, args);
+    let code = output.status.code().unwrap();
+    let printed = if code == 0 {
+        &output.stdout
+    } else {
+        &output.stderr
+    };
+    let json = serde_json::from_slice(printed).unwrap_or_else(|e| {
+        panic!(
+            "{:?} printed invalid JSON ({}): {}",
+            args,
+            e,
+            String::from_utf8_lossy(printed)
+        )
+    });
+    (code, json)
+}
+
+// JSON of a command expected to succeed
+fn ok(data_dir: &Path, args: &[&str]) -> Value {
+    let (code, json) = bharatchain(data_dir, args);
+    assert_eq!(code, 0, "{:?} failed: {}", args, json);
+    json
+}
+
+// Error message of a command expected to exit with `code`
+fn error(data_dir: &Path, args: &[&str], code: u8) -> String {
+    let (actual, json) = bharatchain(data_dir, args);
+    assert_eq!(actual, i32::from(code), "{:?} gave {}", args, json);
+    json["error"].as_str().unwrap().to_string()
+}
+
+fn keys(json: &Value) -> Vec<&str> {
+    let mut keys: Vec<&str> = json
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort();
+    keys
+}
+
+// Data directory initialized from the default genesis, with `blocks` mined
+fn chain_dir(name: &str, blocks: u64) -> PathBuf {
+    let dir = scratch_dir(name);
+    let genesis = dir.join("genesis-in.json");
+    fs::write(
+        &genesis,
+        serde_json::to_string(&GenesisConfig::default()).unwrap(),
+    )
+    .unwrap();
+
+    let data_dir = dir.join("data");
+    ok(&data_dir, &["init", "--genesis", genesis.to_str().unwrap()]);
+    if blocks > 0 {
+        let blocks = blocks.to_string();
+        let miner = miner_address();
+        ok(
+            &data_dir,
+            &[
+                "mine",
+                "--blocks",
+                &blocks,
+                "--miner-address",
+                &miner,
+                "--threads",
+                "1",
+            ],
+        );
+    }
+    data_dir
+}
+
+fn is_hash(value: &Value) -> bool {
+    value
+        .as_str()
+        .is_some_and(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[test]
+fn init_prints_the_chain_and_refuses_to_run_twice() {
+    let dir = scratch_dir("init");
+    let genesis = dir.join("genesis.json");
+    fs::write(
+        &genesis,
+        serde_json::to_string(&GenesisConfig::default()).unwrap(),
+    )
+    .unwrap();
+    let data_dir = dir.join("data");
+
+    let json = ok(&data_dir, &["init", "--genesis", genesis.to_str().unwrap()]);
+    assert_eq!(keys(&json), vec!["chainId", "dataDir", "genesisHash"]);
+    assert_eq!(json["chainId"], DEFAULT_CHAIN_ID);
+    assert_eq!(json["dataDir"], data_dir.to_str().unwrap());
+    assert!(is_hash(&json["genesisHash"]));
+
+    let err = error(
+        &data_dir,
+        &["init", "--genesis", genesis.to_str().unwrap()],
+        1,
+    );
+    assert!(err.contains("is already initialized"), "{}", err);
+
+    let err = error(
+        &dir.join("other"),
+        &["init", "--genesis", "missing.json"],
+        1,
+    );
+    assert!(err.contains("Failed to read genesis file"), "{}", err);
+}
+
+#[test]
+fn failed_init_can_be_retried() {
+    let dir = scratch_dir("init-retry");
+    let genesis = dir.join("genesis.json");
+    fs::write(
+        &genesis,
+        serde_json::to_string(&GenesisConfig::default()).unwrap(),
+    )
+    .unwrap();
+    let genesis = genesis.to_str().unwrap();
+    let data_dir = dir.join("data");
+
+    // A directory in place of the block log keeps the chain from opening
+    fs::create_dir_all(data_dir.join("blocks.log")).unwrap();
+    let err = error(&data_dir, &["init", "--genesis", genesis], 1);
+    assert!(err.contains("block store"), "{}", err);
+    assert!(!data_dir.join("genesis.json").exists());
+
+    fs::remove_dir(data_dir.join("blocks.log")).unwrap();
+    ok(&data_dir, &["init", "--genesis", genesis]);
+}
+
+#[test]
+fn commands_fail_with_1_before_init() {
+    let data_dir = scratch_dir("uninitialized");
+    for args in [
+        &["verify-chain"][..],
+        &["mine"],
+        &["block", "0"],
+        &["balance", &miner_address()],
+    ] {
+        let err = error(&data_dir, args, 1);
+        assert!(err.contains("is not initialized"), "{}", err);
+    }
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    let data_dir = scratch_dir("usage");
+    for args in [
+        &[][..],
+        &["no-such-command"],
+        &["balance"],
+        &["mine", "--blocks", "many"],
+        &["wallet", "mnemonic", "--bogus"],
+    ] {
+        let output = run(&data_dir, args);
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(output.stdout.is_empty(), "{:?}", args);
+    }
+}
+
+#[test]
+fn mined_blocks_and_rewards_are_reported() {
+    let data_dir = chain_dir("mine", 0);
+    let miner = miner_address();
+    let json = ok(
+        &data_dir,
+        &[
+            "mine",
+            "--blocks",
+            "2",
+            "--miner-address",
+            &miner,
+            "--threads",
+            "1",
+        ],
+    );
+    assert_eq!(keys(&json), vec!["height", "mined"]);
+    assert_eq!(json["height"], 2);
+    let mined = json["mined"].as_array().unwrap();
+    assert_eq!(mined.len(), 2);
+    for (block, number) in mined.iter().zip(1..) {
+        assert_eq!(keys(block), vec!["blockHash", "blockNumber"]);
+        assert_eq!(block["blockNumber"], number);
+        assert!(is_hash(&block["blockHash"]));
+    }
+
+    let json = ok(&data_dir, &["balance", &miner]);
+    assert_eq!(keys(&json), vec!["address", "balance"]);
+    assert_eq!(json["address"], miner.as_str());
+    assert_eq!(json["balance"], "100");
+
+    // An address with no account reports a zero balance
+    let other = Account::from_secret_key(&format!("{:064x}", 1), Default::default())
+        .unwrap()
+        .address
+        .to_string();
+    assert_eq!(ok(&data_dir, &["balance", &other])["balance"], "0");
+}
+
+#[test]
+fn successful_commands_print_nothing_but_json() {
+    let data_dir = chain_dir("quiet", 0);
+    let miner = miner_address();
+    for args in [
+        &["mine", "--miner-address", &miner, "--threads", "1"][..],
+        &["balance", &miner],
+        &["block", "1"],
+        &["verify-chain"],
+    ] {
+        let output = run(&data_dir, args);
+        assert_eq!(output.status.code(), Some(0), "{:?}", args);
+        assert!(
+            output.stderr.is_empty(),
+            "{:?} wrote to stderr: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+}
+
+#[test]
+fn blocks_and_transactions_are_looked_up_by_number_or_hash() {
+    let data_dir = chain_dir("lookup", 2);
+
+    let by_number = ok(&data_dir, &["block", "2"]);
+    let block: DataBlock = serde_json::from_value(by_number.clone()).unwrap();
+    assert_eq!(block.header.block_number, 2);
+    assert_eq!(block.block_hash, block.calculate_hash());
+    assert_eq!(ok(&data_dir, &["block", &block.block_hash]), by_number);
+
+    let coinbase = block.body.transactions[0].compute_hash();
+    let json = ok(&data_dir, &["tx", &coinbase]);
+    assert_eq!(
+        keys(&json),
+        vec!["blockHash", "blockNumber", "transaction", "transactionHash"]
+    );
+    assert_eq!(json["transactionHash"], coinbase.as_str());
+    assert_eq!(json["blockNumber"], 2);
+    assert_eq!(json["blockHash"], block.block_hash.as_str());
+    assert_eq!(json["transaction"]["amount"], "50");
+}
+
+#[test]
+fn missing_blocks_and_transactions_exit_with_4() {
+    let data_dir = chain_dir("missing", 1);
+    let unknown = "f".repeat(64);
+
+    assert_eq!(error(&data_dir, &["block", "2"], 4), "Block 2 not found");
+    assert_eq!(
+        error(&data_dir, &["block", &unknown], 4),
+        format!("Block {} not found", unknown)
+    );
+    assert_eq!(
+        error(&data_dir, &["tx", &unknown], 4),
+        format!("Transaction {} not found", unknown)
+    );
+}
+
+#[test]
+fn verify_chain_reports_the_tip_and_exits_with_3_when_the_store_does_not_replay() {
+    let data_dir = chain_dir("verify", 2);
+    let tip = ok(&data_dir, &["block", "2"])["block_hash"].clone();
+
+    let json = ok(&data_dir, &["verify-chain"]);
+    assert_eq!(keys(&json), vec!["height", "tipHash", "totalWork", "valid"]);
+    assert_eq!(json["valid"], true);
+    assert_eq!(json["height"], 2);
+    assert_eq!(json["tipHash"], tip);
+    assert!(json["totalWork"].as_str().unwrap().parse::<u128>().unwrap() > 0);
+
+    // The stored blocks no longer match the genesis they are replayed on
+    let genesis = GenesisConfig {
+        timestamp: 1,
+        ..GenesisConfig::default()
+    };
+    fs::write(
+        data_dir.join("genesis.json"),
+        serde_json::to_string(&genesis).unwrap(),
+    )
+    .unwrap();
+    let err = error(&data_dir, &["verify-chain"], 3);
+    assert!(err.contains("different genesis"), "{}", err);
+}
+
+#[test]
+fn exported_chain_imports_into_a_fresh_node() {
+    let source = chain_dir("export", 3);
+    let file = scratch_dir("export-file").join("chain.bin");
+    let file_arg = file.to_str().unwrap();
+    let tip = ok(&source, &["block", "3"])["block_hash"].clone();
+
+    let json = ok(&source, &["export", file_arg]);
+    assert_eq!(keys(&json), vec!["blocks", "file", "tipHash"]);
+    assert_eq!(json["blocks"], 4);
+    assert_eq!(json["file"], file_arg);
+    assert_eq!(json["tipHash"], tip);
+
+    let target = chain_dir("import", 0);
+    let json = ok(&target, &["import", file_arg]);
+    assert_eq!(
+        keys(&json),
+        vec!["height", "imported", "skipped", "tipHash"]
+    );
+    assert_eq!(json["imported"], 3);
+    assert_eq!(json["skipped"], 1);
+    assert_eq!(json["height"], 3);
+    assert_eq!(json["tipHash"], tip);
+
+    // Importing again changes nothing
+    let json = ok(&target, &["import", file_arg]);
+    assert_eq!(
+        (&json["imported"], &json["skipped"]),
+        (&0.into(), &4.into())
+    );
+
+    fs::write(&file, b"\x00\x00\x01").unwrap();
+    let err = error(&target, &["import", file_arg], 1);
+    assert_eq!(err, "Export file ends in a truncated record");
+}
+
+#[test]
+fn wallet_commands_report_addresses_and_paths() {
+    let dir = scratch_dir("wallet");
+    let data_dir = dir.join("data");
+    let password = dir.join("password");
+    fs::write(&password, "correct horse\n").unwrap();
+    let password = password.to_str().unwrap();
+
+    let json = ok(&data_dir, &["wallet", "mnemonic", "--words", "12"]);
+    assert_eq!(keys(&json), vec!["address", "mnemonic", "path"]);
+    assert_eq!(json["mnemonic"].as_str().unwrap().split(' ').count(), 12);
+    assert_eq!(json["path"], "m/44'/1947'/0'/0/0");
+    assert_eq!(
+        error(&data_dir, &["wallet", "mnemonic", "--words", "13"], 1),
+        "A mnemonic has 12, 15, 18, 21 or 24 words, not 13"
+    );
+
+    let json = ok(&data_dir, &["wallet", "new", "--password-file", password]);
+    assert_eq!(keys(&json), vec!["address", "keystore"]);
+    let generated = json["address"].clone();
+
+    let phrase = dir.join("phrase");
+    fs::write(&phrase, MNEMONIC).unwrap();
+    let recover = [
+        "wallet",
+        "recover",
+        "--mnemonic-file",
+        phrase.to_str().unwrap(),
+        "--password-file",
+        password,
+    ];
+    let json = ok(&data_dir, &recover);
+    assert_eq!(keys(&json), vec!["keys", "keystore"]);
+    let key = &json["keys"][0];
+    assert_eq!(keys(key), vec!["address", "path", "stored"]);
+    assert_eq!(key["path"], "m/44'/1947'/0'/0/0");
+    assert_eq!(key["stored"], true);
+    let recovered = key["address"].clone();
+
+    // Recovering again keeps the stored key
+    assert_eq!(ok(&data_dir, &recover)["keys"][0]["stored"], false);
+
+    let json = ok(&data_dir, &["wallet", "list"]);
+    let mut listed = json["addresses"].as_array().unwrap().clone();
+    listed.sort_by_key(|address| address.to_string());
+    let mut expected = vec![generated, recovered];
+    expected.sort_by_key(|address| address.to_string());
+    assert_eq!(listed, expected);
+}
+
+#[test]
+fn pretty_output_is_indented_json() {
+    let data_dir = chain_dir("pretty", 0);
+    let miner = miner_address();
+    let output = run(&data_dir, &["--pretty", "balance", &miner]);
+    assert_eq!(output.status.code(), Some(0));
+    let text = String::from_utf8(output.stdout).unwrap();
+    assert!(text.contains("\n  \"balance\""), "{}", text);
+    let json: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(json["balance"], "0");
+}
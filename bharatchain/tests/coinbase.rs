@@ -0,0 +1,245 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::{DataBlock, ReceiptStatus, TransactionReceipt};
+use bharatchain::chain_core::chain::{BharatChain, DEFAULT_CHAIN_ID};
+use bharatchain::chain_core::genesis::{GenesisAllocation, GenesisConfig};
+use bharatchain::chain_core::reward::RewardSchedule;
+use bharatchain::chain_core::state_db::StateDB;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use secp256k1::SecretKey;
+
+const SENDER_KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+const MINER_KEY: &str = "b0b0000000000000000000000000000000000000000000000000000000000002";
+const CHAIN_ID: u64 = DEFAULT_CHAIN_ID;
+
+fn address(key: &str) -> Address {
+    Account::from_secret_key(key, Default::default())
+        .unwrap()
+        .address
+}
+
+fn coins(amount: &str) -> Amount {
+    amount.parse().unwrap()
+}
+
+// Only the sender is funded; the miner's account starts empty
+fn accounts() -> StateDB {
+    [
+        Account::from_secret_key(SENDER_KEY, coins("100")).unwrap(),
+        Account::from_secret_key(MINER_KEY, Amount::ZERO).unwrap(),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn balance(accounts: &StateDB, key: &str) -> Amount {
+    accounts.get(&address(key)).unwrap().balance
+}
+
+fn nonce(accounts: &StateDB, key: &str) -> u64 {
+    accounts.nonce(&address(key))
+}
+
+// Transfer from the sender paying `fee`
+fn transfer(nonce: u64, fee: &str) -> BlockTransaction {
+    let mut tx = BlockTransaction::new(
+        address(SENDER_KEY),
+        address(MINER_KEY),
+        coins("1"),
+        nonce,
+        CHAIN_ID,
+    )
+    .with_fee(coins(fee));
+    tx.sign(&SecretKey::from_slice(&hex::decode(SENDER_KEY).unwrap()).unwrap());
+    tx
+}
+
+fn coinbase(block_number: u64, amount: &str) -> BlockTransaction {
+    BlockTransaction::coinbase(address(MINER_KEY), coins(amount), block_number, CHAIN_ID)
+}
+
+// Apply a block at `block_number` to a fresh state under the default schedule
+fn apply(
+    block_number: u64,
+    txns: Vec<BlockTransaction>,
+) -> (
+    StateDB,
+    Result<Vec<TransactionReceipt>, Vec<TransactionReceipt>>,
+) {
+    let mut accounts = accounts();
+    let block = DataBlock::new(block_number, String::new(), txns);
+    let result = block.apply_transactions(&mut accounts, &RewardSchedule::default(), CHAIN_ID);
+    (accounts, result)
+}
+
+fn failure(result: Result<Vec<TransactionReceipt>, Vec<TransactionReceipt>>) -> (usize, String) {
+    result
+        .unwrap_err()
+        .into_iter()
+        .enumerate()
+        .find_map(|(index, receipt)| match receipt.status {
+            ReceiptStatus::Failed(e) => Some((index, e)),
+            _ => None,
+        })
+        .unwrap()
+}
+
+#[test]
+fn subsidy_halves_on_schedule() {
+    let rewards = RewardSchedule::default();
+    assert_eq!(rewards.subsidy_at(0), coins("50"));
+    assert_eq!(rewards.subsidy_at(209_999), coins("50"));
+    assert_eq!(rewards.subsidy_at(210_000), coins("25"));
+    assert_eq!(rewards.subsidy_at(420_000), coins("12.5"));
+    assert_eq!(rewards.subsidy_at(32 * 210_000), coins("0.00000001"));
+    assert_eq!(rewards.subsidy_at(33 * 210_000), Amount::ZERO);
+    assert_eq!(rewards.subsidy_at(64 * 210_000), Amount::ZERO);
+    assert_eq!(rewards.subsidy_at(u64::MAX), Amount::ZERO);
+
+    // Without an interval the subsidy never halves
+    let flat = RewardSchedule {
+        initial_subsidy: coins("50"),
+        halving_interval: 0,
+    };
+    assert_eq!(flat.subsidy_at(u64::MAX), coins("50"));
+}
+
+#[test]
+fn coinbase_may_claim_the_subsidy_plus_fees() {
+    let (accounts, result) = apply(
+        1,
+        vec![coinbase(1, "50.3"), transfer(0, "0.1"), transfer(1, "0.2")],
+    );
+    assert!(result.is_ok());
+    // 50 subsidy, 0.3 fees and the 2 coins transferred
+    assert_eq!(balance(&accounts, MINER_KEY), coins("52.3"));
+
+    // Claiming less is allowed; the rest is never minted
+    let (accounts, result) = apply(1, vec![coinbase(1, "10")]);
+    assert!(result.is_ok());
+    assert_eq!(balance(&accounts, MINER_KEY), coins("10"));
+}
+
+#[test]
+fn coinbase_claiming_too_much_is_rejected() {
+    let (accounts, result) = apply(1, vec![coinbase(1, "50.30000001"), transfer(0, "0.3")]);
+    assert_eq!(
+        failure(result),
+        (
+            0,
+            "Coinbase claims 50.30000001 but the block allows at most 50.3".to_string()
+        )
+    );
+    // Nothing is applied
+    assert_eq!(balance(&accounts, MINER_KEY), Amount::ZERO);
+    assert_eq!(nonce(&accounts, SENDER_KEY), 0);
+}
+
+#[test]
+fn coinbase_allowance_follows_the_halving() {
+    let (_, result) = apply(210_000, vec![coinbase(210_000, "25")]);
+    assert!(result.is_ok());
+
+    let (_, result) = apply(210_000, vec![coinbase(210_000, "50")]);
+    let (index, err) = failure(result);
+    assert_eq!(index, 0);
+    assert!(err.contains("allows at most 25"), "{}", err);
+}
+
+#[test]
+fn coinbase_must_be_the_first_transaction() {
+    let (accounts, result) = apply(1, vec![transfer(0, "0"), coinbase(1, "50")]);
+    assert_eq!(
+        failure(result),
+        (
+            1,
+            "Coinbase must be the first transaction in the block".to_string()
+        )
+    );
+    assert_eq!(nonce(&accounts, SENDER_KEY), 0);
+}
+
+#[test]
+fn second_coinbase_is_rejected() {
+    // Each claim alone fits the allowance
+    let (accounts, result) = apply(1, vec![coinbase(1, "25"), coinbase(1, "25")]);
+    assert_eq!(
+        failure(result),
+        (
+            1,
+            "Coinbase must be the first transaction in the block".to_string()
+        )
+    );
+    assert_eq!(balance(&accounts, MINER_KEY), Amount::ZERO);
+}
+
+#[test]
+fn coinbase_is_not_accepted_as_a_pending_transaction() {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    let err = chain.submit_transaction(coinbase(1, "50")).unwrap_err();
+    assert!(
+        err.contains("Coinbase transaction outside of a block"),
+        "{}",
+        err
+    );
+}
+
+// Low difficulty chain paying `MINER_KEY`, halving every two blocks
+fn halving_chain() -> BharatChain {
+    let genesis = GenesisConfig {
+        rewards: RewardSchedule {
+            initial_subsidy: coins("50"),
+            halving_interval: 2,
+        },
+        allocations: vec![GenesisAllocation {
+            address: address(SENDER_KEY),
+            balance: coins("100"),
+        }],
+        ..Default::default()
+    };
+    let mut chain = BharatChain::new(genesis).unwrap();
+    chain.miner_address = Some(address(MINER_KEY));
+    chain
+}
+
+#[test]
+fn mined_blocks_pay_the_scheduled_subsidy() {
+    let mut chain = halving_chain();
+    for _ in 0..5 {
+        chain.mine_pending_block(10).unwrap();
+    }
+
+    let claims: Vec<Amount> = (1..=5)
+        .map(|n| chain.get_block_by_number(n).unwrap().body.transactions[0].amount)
+        .collect();
+    assert_eq!(
+        claims,
+        vec![
+            coins("50"),
+            coins("25"),
+            coins("25"),
+            coins("12.5"),
+            coins("12.5")
+        ]
+    );
+    assert_eq!(chain.get_balance(&address(MINER_KEY)), Some(coins("125")));
+    assert!(chain.is_valid());
+}
+
+#[test]
+fn block_claiming_too_much_is_rejected() {
+    let mut chain = halving_chain();
+
+    let err = chain
+        .add_block(vec![coinbase(1, "51.00000001"), transfer(0, "1")])
+        .unwrap_err();
+    assert!(err.contains("allows at most 51"), "{}", err);
+    assert_eq!(chain.height(), 0);
+
+    // The fee counts towards the allowance
+    chain
+        .add_block(vec![coinbase(1, "51"), transfer(0, "1")])
+        .unwrap();
+    assert_eq!(chain.get_balance(&address(MINER_KEY)), Some(coins("52")));
+}
@@ -0,0 +1,190 @@
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::{DataBlock, BLOCK_VERSION, BLOCK_VERSION_LEGACY};
+use bharatchain::chain_core::chain::{BharatChain, MAX_FUTURE_BLOCK_TIME};
+use bharatchain::chain_core::genesis::GenesisConfig;
+use bharatchain::chain_core::helper::get_current_timestamp;
+use bharatchain::chain_core::miner::{CancelToken, Miner, MiningOutcome};
+use bharatchain::chain_core::transaction::{BlockTransaction, MerkleTree};
+
+// Distinct transactions; their signatures do not matter to the Merkle root
+fn transactions(count: u64) -> Vec<BlockTransaction> {
+    (0..count)
+        .map(|nonce| {
+            BlockTransaction::new(
+                Address::from_hash([1; 32]),
+                Address::from_hash([2; 32]),
+                Amount::ZERO,
+                nonce,
+                1,
+            )
+        })
+        .collect()
+}
+
+// Rebuild `block` under `version`, committing to its transactions with that
+// version's Merkle construction, and mine it again at its target
+fn with_version(mut block: DataBlock, version: u32) -> DataBlock {
+    let hashes = block
+        .body
+        .transactions
+        .iter()
+        .map(|tx| tx.compute_hash())
+        .collect();
+    block.header.version = version;
+    block.header.merkle_root = MerkleTree::with_version(version).build_merkle_tree(hashes);
+    mine(block)
+}
+
+fn mine(block: DataBlock) -> DataBlock {
+    match Miner::new(1).mine(block, &CancelToken::new()) {
+        MiningOutcome::Mined(block, _) => *block,
+        MiningOutcome::Cancelled(_) => panic!("mining was cancelled"),
+    }
+}
+
+// Prepare the next block, let `tamper` change it, then mine it
+fn mine_tampered(chain: &mut BharatChain, tamper: impl FnOnce(&mut DataBlock)) -> DataBlock {
+    let (mut block, _) = chain.prepare_block(vec![]).unwrap();
+    tamper(&mut block);
+    mine(block)
+}
+
+#[test]
+fn legacy_blocks_are_still_accepted() {
+    let block = with_version(
+        DataBlock::new(1, String::new(), transactions(3)),
+        BLOCK_VERSION_LEGACY,
+    );
+    assert_eq!(block.check_merkle_root(), Ok(()));
+
+    // A legacy block is accepted on top of the chain, which stays valid
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    chain.add_block(vec![]).unwrap();
+    let block = mine_tampered(&mut chain, |block| {
+        *block = with_version(block.clone(), BLOCK_VERSION_LEGACY)
+    });
+    chain.submit_block(block).unwrap();
+    chain.add_block(vec![]).unwrap();
+    assert_eq!(chain.chain[2].header.version, BLOCK_VERSION_LEGACY);
+    assert!(chain.is_valid());
+}
+
+#[test]
+fn legacy_block_with_a_repeated_transaction_is_rejected() {
+    let block = with_version(
+        DataBlock::new(1, String::new(), transactions(3)),
+        BLOCK_VERSION_LEGACY,
+    );
+
+    // Repeating the odd last transaction keeps the legacy root and the block hash
+    let mut mutated = block.clone();
+    mutated
+        .body
+        .transactions
+        .push(block.body.transactions[2].clone());
+    assert_eq!(mutated.calculate_hash(), block.block_hash);
+
+    let err = mutated.check_merkle_root().unwrap_err();
+    assert!(err.contains("twice"), "{}", err);
+
+    // The same list is rejected in a current block too
+    let mut current = DataBlock::new(1, String::new(), transactions(3));
+    current
+        .body
+        .transactions
+        .push(current.body.transactions[0].clone());
+    let current = with_version(current, BLOCK_VERSION);
+    assert!(current.check_merkle_root().unwrap_err().contains("twice"));
+}
+
+#[test]
+fn merkle_root_must_match_the_block_version() {
+    let block = DataBlock::new(1, String::new(), transactions(3));
+    assert_eq!(block.check_merkle_root(), Ok(()));
+
+    // A current root under the legacy version label, and the reverse
+    let mut relabelled = block.clone();
+    relabelled.header.version = BLOCK_VERSION_LEGACY;
+    assert!(relabelled
+        .check_merkle_root()
+        .unwrap_err()
+        .contains("Merkle root"));
+    let mut relabelled = with_version(block, BLOCK_VERSION_LEGACY);
+    relabelled.header.version = BLOCK_VERSION;
+    assert!(relabelled
+        .check_merkle_root()
+        .unwrap_err()
+        .contains("Merkle root"));
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    for version in [0, BLOCK_VERSION + 1] {
+        let block = with_version(DataBlock::new(1, String::new(), vec![]), version);
+        let err = block.check_merkle_root().unwrap_err();
+        assert!(err.contains("unknown version"), "{}", err);
+    }
+
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    let block = mine_tampered(&mut chain, |block| {
+        *block = with_version(block.clone(), BLOCK_VERSION + 1)
+    });
+    let err = chain.submit_block(block).unwrap_err();
+    assert!(err.contains("unknown version"), "{}", err);
+    assert_eq!(chain.height(), 0);
+
+    chain.add_block(vec![]).unwrap();
+    chain.chain[1] = with_version(chain.chain[1].clone(), BLOCK_VERSION + 1);
+    assert!(!chain.is_valid());
+}
+
+#[test]
+fn timestamps_must_follow_the_median_and_not_run_ahead_of_the_clock() {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+
+    // Rapidly mined blocks share a clock second but still move past the median
+    for _ in 0..15 {
+        chain.add_block(vec![]).unwrap();
+    }
+    assert!(chain.is_valid());
+    let mut recent: Vec<u64> = chain.chain[5..=15]
+        .iter()
+        .map(|block| block.header.timestamp)
+        .collect();
+    recent.sort_unstable();
+    let median_time = recent[5];
+
+    let block = mine_tampered(&mut chain, |block| block.header.timestamp = 0);
+    let err = chain.submit_block(block).unwrap_err();
+    assert!(err.contains("median time"), "{}", err);
+
+    // The median of the last 11 blocks itself is not late enough
+    let block = mine_tampered(&mut chain, |block| block.header.timestamp = median_time);
+    let err = chain.submit_block(block).unwrap_err();
+    assert!(err.contains("median time"), "{}", err);
+
+    let too_late = get_current_timestamp() + MAX_FUTURE_BLOCK_TIME + 60;
+    let block = mine_tampered(&mut chain, |block| block.header.timestamp = too_late);
+    let err = chain.submit_block(block).unwrap_err();
+    assert!(err.contains("future"), "{}", err);
+
+    let near_future = get_current_timestamp() + 60;
+    let block = mine_tampered(&mut chain, |block| block.header.timestamp = near_future);
+    chain.submit_block(block).unwrap();
+    assert_eq!(chain.height(), 16);
+}
+
+#[test]
+fn chain_with_a_block_before_the_median_is_invalid() {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    for _ in 0..3 {
+        chain.add_block(vec![]).unwrap();
+    }
+
+    // Block 3 stamped no later than the median of blocks 0 to 2
+    let median_time = chain.chain[1].header.timestamp;
+    chain.chain[3].header.timestamp = median_time;
+    chain.chain[3] = mine(chain.chain[3].clone());
+    assert!(!chain.is_valid());
+}
@@ -0,0 +1,189 @@
+use bharatchain::chain_core::block::{BlockHeader, DataBlock};
+use bharatchain::chain_core::chain::BharatChain;
+use bharatchain::chain_core::difficulty::{CompactTarget, RetargetConfig, MAX_TARGET_BITS, U256};
+use bharatchain::chain_core::genesis::GenesisConfig;
+
+// Bitcoin's initial target, well below the proof-of-work limit
+const BITS: CompactTarget = CompactTarget::from_bits(0x1d00_ffff);
+
+// Headers of genesis and blocks 1 to `tip`, block n stamped `timestamp(n)`
+fn chain(tip: u64, timestamp: impl Fn(u64) -> u64) -> Vec<BlockHeader> {
+    (0..=tip)
+        .map(|block_number| {
+            let mut header = DataBlock::new(block_number, String::new(), vec![]).header;
+            header.timestamp = timestamp(block_number);
+            header.bits = BITS;
+            header
+        })
+        .collect()
+}
+
+// Target of the block after the last of `headers`
+fn next_target(config: &RetargetConfig, headers: &[BlockHeader]) -> CompactTarget {
+    let tip = headers.last().unwrap();
+    config.next_target(tip, |block_number| &headers[block_number as usize])
+}
+
+// Target after the first period of the default config, whose blocks 1 and 9
+// were `elapsed` seconds apart; 8 gaps of 10 seconds are expected
+fn retarget_after(bits: CompactTarget, elapsed: u64) -> CompactTarget {
+    let mut headers = chain(9, |n| if n == 9 { 1_000 + elapsed } else { 1_000 });
+    headers[9].bits = bits;
+    next_target(&RetargetConfig::default(), &headers)
+}
+
+fn scaled(bits: CompactTarget, numerator: u64, denominator: u64) -> CompactTarget {
+    let target = bits.to_target().unwrap();
+    CompactTarget::from_target(target * U256::from(numerator) / U256::from(denominator))
+}
+
+fn target(bits: u32) -> Result<U256, String> {
+    CompactTarget::from_bits(bits).to_target()
+}
+
+#[test]
+fn compact_targets_expand_like_bitcoin_nbits() {
+    assert_eq!(target(0x1d00_ffff), Ok(U256::from(0xffff_u32) << 208));
+    assert_eq!(target(0x1b04_04cb), Ok(U256::from(0x04_04cb_u32) << 192));
+    assert_eq!(target(0x207f_ffff), Ok(U256::from(0x7f_ffff_u32) << 232));
+
+    // Small exponents shift the mantissa right instead of left
+    assert_eq!(target(0x0112_3456), Ok(U256::from(0x12_u32)));
+    assert_eq!(target(0x0212_3456), Ok(U256::from(0x1234_u32)));
+    assert_eq!(target(0x0312_3456), Ok(U256::from(0x12_3456_u32)));
+    assert_eq!(target(0x0412_3456), Ok(U256::from(0x1234_5600_u32)));
+    assert_eq!(target(0x0500_9234), Ok(U256::from(0x9234_0000_u32)));
+}
+
+#[test]
+fn compact_targets_round_trip() {
+    for bits in [
+        0x1d00_ffff,
+        0x1b04_04cb,
+        0x207f_ffff,
+        0x0312_3456,
+        0x0412_3456,
+        0x0500_9234,
+    ] {
+        let compact = CompactTarget::from_bits(bits);
+        assert_eq!(
+            CompactTarget::from_target(compact.to_target().unwrap()),
+            compact
+        );
+    }
+
+    // Non-canonical encodings come back in canonical form
+    let canonical = |bits| CompactTarget::from_target(target(bits).unwrap()).bits();
+    assert_eq!(canonical(0x0112_3456), 0x0112_0000);
+    assert_eq!(canonical(0x0212_3456), 0x0212_3400);
+
+    // A mantissa with its top bit set moves a byte into the exponent
+    assert_eq!(
+        CompactTarget::from_target(U256::from(0x80_u32)).bits(),
+        0x0200_8000
+    );
+}
+
+#[test]
+fn invalid_compact_targets_are_rejected() {
+    // Sign bit set with a non-zero mantissa
+    for bits in [0x0492_3456, 0x01fe_dcba, 0x1d80_ffff] {
+        let err = target(bits).unwrap_err();
+        assert!(err.contains("negative"), "{}", err);
+    }
+
+    // Mantissa shifted past 256 bits
+    for bits in [0x2101_0000, 0x2300_0100, 0xff12_3456] {
+        let err = target(bits).unwrap_err();
+        assert!(err.contains("overflows"), "{}", err);
+    }
+
+    // Zero mantissa, a negative zero, or everything shifted out
+    for bits in [0x1d00_0000, 0x0080_0000, 0x0100_3456, 0x0000_0000] {
+        let err = target(bits).unwrap_err();
+        assert!(err.contains("zero"), "{}", err);
+    }
+}
+
+#[test]
+fn work_grows_as_the_target_shrinks() {
+    // Bitcoin's genesis block added 0x100010001 to its chain work
+    assert_eq!(BITS.work(), U256::from(0x1_0001_0001_u64));
+    assert_eq!(MAX_TARGET_BITS.work(), U256::from(2_u32));
+
+    let harder = scaled(BITS, 1, 4);
+    assert!(harder.work() > BITS.work());
+    assert!(BITS.work() > MAX_TARGET_BITS.work());
+
+    // Invalid targets carry no work
+    assert_eq!(CompactTarget::from_bits(0x0492_3456).work(), U256::ZERO);
+    assert_eq!(CompactTarget::from_bits(0x1d00_0000).work(), U256::ZERO);
+}
+
+#[test]
+fn cumulative_work_sums_the_chain() {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    for _ in 0..3 {
+        chain.add_block(vec![]).unwrap();
+    }
+
+    let per_block = MAX_TARGET_BITS.work();
+    for block_number in 0..=3 {
+        assert_eq!(
+            chain.cumulative_work(block_number),
+            Some(per_block * U256::from(block_number + 1))
+        );
+    }
+    assert_eq!(chain.cumulative_work(4), None);
+    assert_eq!(chain.total_work(), per_block * U256::from(4_u32));
+}
+
+#[test]
+fn target_is_kept_between_retarget_heights() {
+    let headers = chain(4, |n| n * 1_000);
+    assert_eq!(next_target(&RetargetConfig::default(), &headers), BITS);
+}
+
+#[test]
+fn target_scales_with_the_observed_block_time() {
+    assert_eq!(retarget_after(BITS, 80), scaled(BITS, 1, 1));
+    assert_eq!(retarget_after(BITS, 160), scaled(BITS, 2, 1));
+    assert_eq!(retarget_after(BITS, 40), scaled(BITS, 1, 2));
+}
+
+#[test]
+fn later_periods_count_the_gap_from_the_previous_period() {
+    // Exactly on schedule: ten gaps of 10 seconds, the first of them between
+    // the last block of the previous period and the first of this one
+    let headers = chain(19, |n| 1_000 + n * 10);
+    assert_eq!(next_target(&RetargetConfig::default(), &headers), BITS);
+
+    // A slow first gap counts toward this period
+    let headers = chain(19, |n| {
+        if n >= 10 {
+            1_100 + n * 10
+        } else {
+            1_000 + n * 10
+        }
+    });
+    assert_eq!(
+        next_target(&RetargetConfig::default(), &headers),
+        scaled(BITS, 2, 1)
+    );
+}
+
+#[test]
+fn retarget_is_clamped_to_the_maximum_adjustment() {
+    // Far too slow: at most 4x easier
+    assert_eq!(retarget_after(BITS, 1_000_000), scaled(BITS, 4, 1));
+    assert_eq!(retarget_after(BITS, 320), scaled(BITS, 4, 1));
+
+    // Far too fast, or timestamps going backwards: at most 4x harder
+    assert_eq!(retarget_after(BITS, 0), scaled(BITS, 1, 4));
+    assert_eq!(retarget_after(BITS, 1), scaled(BITS, 1, 4));
+}
+
+#[test]
+fn retarget_never_exceeds_the_proof_of_work_limit() {
+    assert_eq!(retarget_after(MAX_TARGET_BITS, 1_000_000), MAX_TARGET_BITS);
+}
@@ -0,0 +1,218 @@
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::{BlockHeader, DataBlock};
+use bharatchain::chain_core::difficulty::CompactTarget;
+use bharatchain::chain_core::encoding::{self, Decoder, Encoder, ENCODING_VERSION};
+use bharatchain::chain_core::transaction::BlockTransaction;
+use secp256k1::SecretKey;
+
+const SENDER_KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+
+// Small transaction with every field fixed, so its encoding never changes
+fn sample_transaction() -> BlockTransaction {
+    BlockTransaction {
+        id: "ab:7".to_string(),
+        chain_id: 1,
+        nonce: 7,
+        sender: Address::from_hash([0xab; 32]),
+        receiver: Address::from_hash([0x0c; 32]),
+        amount: Amount::from_base_units(150_000_000),
+        fee: Amount::from_base_units(1_000),
+        timestamp: 1_700_000_000,
+        public_key: String::new(),
+        signature: String::new(),
+    }
+}
+
+fn signed_transaction() -> BlockTransaction {
+    let mut tx = sample_transaction();
+    tx.sign(&SecretKey::from_slice(&hex::decode(SENDER_KEY).unwrap()).unwrap());
+    tx
+}
+
+fn sample_block() -> DataBlock {
+    let mut block = DataBlock::new(
+        3,
+        "11".repeat(32),
+        vec![sample_transaction(), signed_transaction()],
+    );
+    block.header.timestamp = 1_700_000_600;
+    block.header.bits = CompactTarget::from_bits(0x1f00ffff);
+    block.header.nounce = 42;
+    block.header.extra_nonce = 9;
+    block.set_state_root("22".repeat(32));
+    block
+}
+
+fn assert_same_transaction(a: &BlockTransaction, b: &BlockTransaction) {
+    assert_eq!(
+        serde_json::to_value(a).unwrap(),
+        serde_json::to_value(b).unwrap()
+    );
+}
+
+#[test]
+fn transaction_golden_vector() {
+    let tx = sample_transaction();
+    let expected = concat!(
+        "02",                                                               // encoding version
+        "0000000461623a37", // id "ab:7", length prefixed
+        "0000000000000001", // chain id
+        "0000000000000007", // nonce
+        "00",               // sender: address version
+        "abababababababababababababababababababababababababababababababab", // and hash
+        "00",               // receiver: address version
+        "0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c", // and hash
+        "0000000008f0d180", // amount: 1.5 coins
+        "00000000000003e8", // fee: 1000 base units
+        "000000006553f100", // timestamp
+        "00000000",         // public key ""
+        "00000000",         // signature ""
+    );
+
+    assert_eq!(hex::encode(encoding::encode(&tx)), expected);
+    assert_eq!(
+        tx.compute_hash(),
+        "94b0598b8c91d97cc7d45b54e7f38dede8908925bcc2079f0adb618ee80ae37f"
+    );
+}
+
+#[test]
+fn block_header_golden_vector() {
+    let block = sample_block();
+    assert_eq!(
+        block.header.merkle_root,
+        "a60add62ae71524fa8dc06e728e943b47123a785a01526af014377fb69e5326d"
+    );
+    assert_eq!(
+        block.block_hash,
+        "fc752d1699c058ac42abfc19709d845d37d770827d8a9aae46f61b7a60a49c96"
+    );
+}
+
+#[test]
+fn transaction_round_trip() {
+    for tx in [sample_transaction(), signed_transaction()] {
+        let decoded: BlockTransaction = encoding::decode(&encoding::encode(&tx)).unwrap();
+        assert_same_transaction(&decoded, &tx);
+        assert_eq!(decoded.compute_hash(), tx.compute_hash());
+    }
+}
+
+#[test]
+fn block_round_trip() {
+    let block = sample_block();
+    let bytes = encoding::encode(&block);
+    let decoded: DataBlock = encoding::decode(&bytes).unwrap();
+
+    assert_eq!(decoded.block_hash, block.block_hash);
+    assert_eq!(decoded.calculate_hash(), block.block_hash);
+    assert_eq!(decoded.header.bits, block.header.bits);
+    assert_eq!(decoded.header.nounce, 42);
+    assert_eq!(decoded.header.extra_nonce, 9);
+    assert_eq!(decoded.body.transactions.len(), 2);
+    for (a, b) in decoded
+        .body
+        .transactions
+        .iter()
+        .zip(&block.body.transactions)
+    {
+        assert_same_transaction(a, b);
+    }
+    assert_eq!(encoding::encode(&decoded), bytes);
+}
+
+#[test]
+fn standalone_header_hashes_without_its_body() {
+    let block = sample_block();
+    let bytes = encoding::encode(&block.header);
+    assert!(bytes.len() < encoding::encode(&block).len());
+
+    // A header received on its own identifies and proves the block
+    let header: BlockHeader = encoding::decode(&bytes).unwrap();
+    assert_eq!(header.hash(), block.block_hash);
+    assert_eq!(header.merkle_root, block.body.merkle_root(header.version));
+    assert_eq!(encoding::encode(&header), bytes);
+}
+
+#[test]
+fn field_boundaries_do_not_collide() {
+    // ("ab", "c") and ("a", "bc") concatenate to the same string
+    let mut tx = sample_transaction();
+    tx.public_key = "ab".to_string();
+    tx.signature = "c".to_string();
+    let mut shifted = sample_transaction();
+    shifted.public_key = "a".to_string();
+    shifted.signature = "bc".to_string();
+
+    assert_ne!(encoding::encode(&tx), encoding::encode(&shifted));
+    assert_ne!(tx.compute_hash(), shifted.compute_hash());
+
+    // Swapped sender and receiver hold the same bytes in a different order
+    let tx = sample_transaction();
+    let mut swapped = sample_transaction();
+    swapped.sender = tx.receiver;
+    swapped.receiver = tx.sender;
+
+    assert_ne!(encoding::encode(&tx), encoding::encode(&swapped));
+    assert_ne!(tx.compute_hash(), swapped.compute_hash());
+    assert_ne!(tx.signing_hash(), swapped.signing_hash());
+}
+
+#[test]
+fn every_header_field_changes_the_block_hash() {
+    let block = sample_block();
+    let mut variants = vec![];
+
+    let mut b = block.clone();
+    b.header.timestamp += 1;
+    variants.push(b);
+    let mut b = block.clone();
+    b.header.nounce += 1;
+    variants.push(b);
+    let mut b = block.clone();
+    b.header.extra_nonce += 1;
+    variants.push(b);
+    let mut b = block.clone();
+    b.header.bits = CompactTarget::from_bits(0x1f00fffe);
+    variants.push(b);
+    let mut b = block.clone();
+    b.header.previous_hash = "33".repeat(32);
+    variants.push(b);
+
+    for variant in variants {
+        assert_ne!(variant.calculate_hash(), block.block_hash);
+    }
+}
+
+#[test]
+fn decoding_rejects_malformed_input() {
+    let bytes = encoding::encode(&sample_transaction());
+
+    // Truncated at every possible length
+    for len in 0..bytes.len() {
+        assert!(encoding::decode::<BlockTransaction>(&bytes[..len]).is_err());
+    }
+
+    // Trailing bytes
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(encoding::decode::<BlockTransaction>(&trailing).is_err());
+
+    // Unknown version
+    let mut versioned = bytes.clone();
+    versioned[0] = ENCODING_VERSION + 1;
+    assert!(encoding::decode::<BlockTransaction>(&versioned).is_err());
+
+    // Invalid UTF-8 in a string field
+    let mut out = Encoder::new();
+    out.put_bytes(&[0xff, 0xfe]);
+    let invalid = out.into_bytes();
+    assert!(Decoder::new(&invalid).get_str().is_err());
+
+    // A huge list count must fail without allocating for it
+    let mut out = Encoder::new();
+    out.put_u32(u32::MAX);
+    let huge = out.into_bytes();
+    assert!(Decoder::new(&huge).get_list::<BlockTransaction>().is_err());
+}
@@ -0,0 +1,250 @@
+use bharatchain::wallet::hd::{
+    ChildNumber, DerivationPath, ExtendedPrivateKey, ExtendedPublicKey, COIN_TYPE,
+};
+use bharatchain::wallet::keystore::address_of;
+use bharatchain::wallet::mnemonic::Mnemonic;
+
+// BIP-39 reference vectors (github.com/trezor/python-mnemonic, vectors.json),
+// all with the passphrase "TREZOR": entropy, phrase, seed and master key
+const BIP39_PASSPHRASE: &str = "TREZOR";
+const BIP39_VECTORS: [(&str, &str, &str, &str); 6] = [
+    (
+        "00000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        "xprv9s21ZrQH143K3h3fDYiay8mocZ3afhfULfb5GX8kCBdno77K4HiA15Tg23wpbeF1pLfs1c5SPmYHrEpTuuRhxMwvKDwqdKiGJS9XFKzUsAF",
+    ),
+    (
+        "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+        "legal winner thank year wave sausage worth useful legal winner thank yellow",
+        "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        "xprv9s21ZrQH143K2gA81bYFHqU68xz1cX2APaSq5tt6MFSLeXnCKV1RVUJt9FWNTbrrryem4ZckN8k4Ls1H6nwdvDTvnV7zEXs2HgPezuVccsq",
+    ),
+    (
+        "80808080808080808080808080808080",
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage above",
+        "d71de856f81a8acc65e6fc851a38d4d7ec216fd0796d0a6827a3ad6ed5511a30fa280f12eb2e47ed2ac03b5c462a0358d18d69fe4f985ec81778c1b370b652a8",
+        "xprv9s21ZrQH143K2shfP28KM3nr5Ap1SXjz8gc2rAqqMEynmjt6o1qboCDpxckqXavCwdnYds6yBHZGKHv7ef2eTXy461PXUjBFQg6PrwY4Gzq",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+        "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+        "xprv9s21ZrQH143K2V4oox4M8Zmhi2Fjx5XK4Lf7GKRvPSgydU3mjZuKGCTg7UPiBUD7ydVPvSLtg9hjp7MQTYsW67rZHAXeccqYqrsx8LcXnyd",
+    ),
+    (
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+        "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+        "xprv9s21ZrQH143K32qBagUJAMU2LsHg3ka7jqMcV98Y7gVeVyNStwYS3U7yVVoDZ4btbRNf4h6ibWpY22iRmXq35qgLs79f312g2kj5539ebPM",
+    ),
+    (
+        "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+        "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+        "xprv9s21ZrQH143K2WFF16X85T2QCpndrGwx6GueB72Zf3AHwHJaknRXNF37ZmDrtHrrLSHvbuRejXcnYxoZKvRquTPyp2JiNG3XcjQyzSEgqCB",
+    ),
+];
+
+// BIP-32 test vectors 1 and 2: seed, then path with its xpub and xprv
+const BIP32_VECTOR_1_SEED: &str = "000102030405060708090a0b0c0d0e0f";
+const BIP32_VECTOR_1: [(&str, &str, &str); 6] = [
+    (
+        "m",
+        "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+        "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+    ),
+    (
+        "m/0'",
+        "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+        "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+    ),
+    (
+        "m/0'/1",
+        "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+        "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+    ),
+    (
+        "m/0'/1/2'",
+        "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+        "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+    ),
+    (
+        "m/0'/1/2'/2",
+        "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+        "xprvA2JDeKCSNNZky6uBCviVfJSKyQ1mDYahRjijr5idH2WwLsEd4Hsb2Tyh8RfQMuPh7f7RtyzTtdrbdqqsunu5Mm3wDvUAKRHSC34sJ7in334",
+    ),
+    (
+        "m/0'/1/2'/2/1000000000",
+        "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+        "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+    ),
+];
+
+const BIP32_VECTOR_2_SEED: &str = "fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542";
+const BIP32_VECTOR_2: [(&str, &str, &str); 6] = [
+    (
+        "m",
+        "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+        "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+    ),
+    (
+        "m/0",
+        "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+        "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+    ),
+    (
+        "m/0/2147483647'",
+        "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+        "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+    ),
+    (
+        "m/0/2147483647'/1",
+        "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+        "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+    ),
+    (
+        "m/0/2147483647'/1/2147483646'",
+        "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+        "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+    ),
+    (
+        "m/0/2147483647'/1/2147483646'/2",
+        "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+        "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+    ),
+];
+
+fn bip32_vector(seed: &str, vector: &[(&str, &str, &str)]) {
+    let master = ExtendedPrivateKey::from_seed(&hex::decode(seed).unwrap()).unwrap();
+    for (path, xpub, xprv) in vector {
+        let key = master.derive_path(&path.parse().unwrap()).unwrap();
+        assert_eq!(key.to_string(), *xprv, "xprv at {}", path);
+        assert_eq!(
+            key.to_extended_public_key().to_string(),
+            *xpub,
+            "xpub at {}",
+            path
+        );
+
+        // Both serializations parse back to the same key
+        assert_eq!(xprv.parse::<ExtendedPrivateKey>().unwrap(), key);
+        assert_eq!(
+            xpub.parse::<ExtendedPublicKey>().unwrap(),
+            key.to_extended_public_key()
+        );
+    }
+}
+
+#[test]
+fn bip39_reference_vectors() {
+    for (entropy, phrase, seed, xprv) in BIP39_VECTORS {
+        let mnemonic = Mnemonic::from_entropy(&hex::decode(entropy).unwrap()).unwrap();
+        assert_eq!(mnemonic.phrase(), phrase);
+
+        let parsed = Mnemonic::parse(phrase).unwrap();
+        assert_eq!(hex::encode(parsed.entropy()), entropy);
+
+        let derived = parsed.to_seed(BIP39_PASSPHRASE);
+        assert_eq!(hex::encode(derived), seed);
+        assert_eq!(
+            ExtendedPrivateKey::from_seed(&derived).unwrap().to_string(),
+            xprv
+        );
+    }
+}
+
+#[test]
+fn mnemonic_checksum_and_wordlist_are_enforced() {
+    // Last word changed: every word is valid but the checksum is not
+    assert!(Mnemonic::parse(
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon"
+    )
+    .is_err());
+    // Not a wordlist word
+    assert!(Mnemonic::parse(
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon bharat"
+    )
+    .is_err());
+    // Wrong length
+    assert!(Mnemonic::parse("abandon about").is_err());
+
+    // Spacing and case are not significant
+    let phrase = "  Legal winner thank year wave sausage\nworth useful legal winner THANK yellow ";
+    assert_eq!(
+        Mnemonic::parse(phrase).unwrap().phrase(),
+        BIP39_VECTORS[1].1
+    );
+}
+
+#[test]
+fn generated_mnemonics_round_trip() {
+    for words in [12, 24] {
+        let mnemonic = Mnemonic::generate(words).unwrap();
+        assert_eq!(mnemonic.word_count(), words);
+        assert_eq!(Mnemonic::parse(&mnemonic.phrase()).unwrap(), mnemonic);
+    }
+    assert!(Mnemonic::generate(13).is_err());
+}
+
+#[test]
+fn bip32_test_vector_1() {
+    bip32_vector(BIP32_VECTOR_1_SEED, &BIP32_VECTOR_1);
+}
+
+#[test]
+fn bip32_test_vector_2() {
+    bip32_vector(BIP32_VECTOR_2_SEED, &BIP32_VECTOR_2);
+}
+
+#[test]
+fn public_derivation_matches_private_derivation() {
+    let master = ExtendedPrivateKey::from_seed(&hex::decode(BIP32_VECTOR_1_SEED).unwrap()).unwrap();
+    let account = master
+        .derive_path(&"m/44'/1947'/0'".parse().unwrap())
+        .unwrap();
+    let account_public = account.to_extended_public_key();
+
+    let path: DerivationPath = "m/0/5".parse().unwrap();
+    assert_eq!(
+        account_public.derive_path(&path).unwrap(),
+        account.derive_path(&path).unwrap().to_extended_public_key()
+    );
+    assert!(account_public
+        .derive_child(ChildNumber::hardened(0).unwrap())
+        .is_err());
+}
+
+#[test]
+fn derivation_paths_parse_and_print() {
+    let path: DerivationPath = "m/44h/1947H/0'/0/3".parse().unwrap();
+    assert_eq!(path.to_string(), "m/44'/1947'/0'/0/3");
+    assert_eq!(path, DerivationPath::bip44(0, false, 3).unwrap());
+    assert_eq!(
+        DerivationPath::bip44(2, true, 0).unwrap().to_string(),
+        format!("m/44'/{}'/2'/1/0", COIN_TYPE)
+    );
+
+    assert!("44'/0'".parse::<DerivationPath>().is_err());
+    assert!("m/x".parse::<DerivationPath>().is_err());
+    assert!("m/2147483648".parse::<DerivationPath>().is_err());
+    assert_eq!("m".parse::<DerivationPath>().unwrap().children(), &[]);
+}
+
+#[test]
+fn derived_keys_map_to_addresses() {
+    let seed = Mnemonic::parse(BIP39_VECTORS[0].1).unwrap().to_seed("");
+    let master = ExtendedPrivateKey::from_seed(&seed).unwrap();
+
+    let first = master
+        .derive_path(&DerivationPath::bip44(0, false, 0).unwrap())
+        .unwrap();
+    let second = master
+        .derive_path(&DerivationPath::bip44(0, false, 1).unwrap())
+        .unwrap();
+
+    assert_eq!(first.address(), address_of(&first.secret_key));
+    assert_eq!(first.to_extended_public_key().address(), first.address());
+    assert_ne!(first.address(), second.address());
+    assert!(first.address().to_string().starts_with("bharat1"));
+}
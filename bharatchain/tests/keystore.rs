@@ -0,0 +1,139 @@
+use bharatchain::chain_core::address::Address;
+use bharatchain::wallet::keystore::{address_of, Kdf, Keystore, KEYSTORE_VERSION};
+use secp256k1::SecretKey;
+
+const KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+const PASSWORD: &str = "correct horse battery staple";
+const WRONG_PASSWORD_ERR: &str = "Wrong password or corrupted keystore";
+
+fn secret_key() -> SecretKey {
+    SecretKey::from_slice(&hex::decode(KEY).unwrap()).unwrap()
+}
+
+// Cheap parameters of each kind, so the tests don't pay the default cost
+fn kdfs() -> [Kdf; 2] {
+    [Kdf::scrypt(4), Kdf::pbkdf2(16)]
+}
+
+// Flip the lowest bit of the first byte of a hex string
+fn flip_first_bit(value: &mut String) {
+    let mut bytes = hex::decode(&*value).unwrap();
+    bytes[0] ^= 1;
+    *value = hex::encode(bytes);
+}
+
+#[test]
+fn encrypted_key_round_trips() {
+    for kdf in kdfs() {
+        let keystore = Keystore::encrypt(&secret_key(), PASSWORD, kdf.clone()).unwrap();
+        assert_eq!(keystore.version, KEYSTORE_VERSION);
+        assert_eq!(keystore.address, address_of(&secret_key()));
+        assert_eq!(keystore.crypto.kdf, kdf);
+        assert_ne!(keystore.crypto.ciphertext, KEY);
+        assert_eq!(keystore.decrypt(PASSWORD).unwrap(), secret_key());
+
+        let reloaded = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert_eq!(reloaded, keystore);
+        assert_eq!(reloaded.decrypt(PASSWORD).unwrap(), secret_key());
+    }
+}
+
+#[test]
+fn file_layout_follows_web3_secret_storage() {
+    let keystore = Keystore::encrypt(&secret_key(), PASSWORD, Kdf::scrypt(4)).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&keystore.to_json()).unwrap();
+    assert_eq!(json["version"], 3);
+    assert_eq!(json["id"].as_str().unwrap().len(), 36);
+    assert_eq!(json["crypto"]["cipher"], "aes-128-ctr");
+    assert_eq!(json["crypto"]["kdf"], "scrypt");
+    assert_eq!(json["crypto"]["kdfparams"]["n"], 16);
+    assert_eq!(json["crypto"]["kdfparams"]["dklen"], 32);
+    assert_eq!(json["crypto"]["mac"].as_str().unwrap().len(), 64);
+
+    let keystore = Keystore::encrypt(&secret_key(), PASSWORD, Kdf::pbkdf2(16)).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&keystore.to_json()).unwrap();
+    assert_eq!(json["crypto"]["kdf"], "pbkdf2");
+    assert_eq!(json["crypto"]["kdfparams"]["c"], 16);
+    assert_eq!(json["crypto"]["kdfparams"]["prf"], "hmac-sha256");
+}
+
+#[test]
+fn each_encryption_is_salted_afresh() {
+    let first = Keystore::encrypt(&secret_key(), PASSWORD, Kdf::scrypt(4)).unwrap();
+    let second = Keystore::encrypt(&secret_key(), PASSWORD, Kdf::scrypt(4)).unwrap();
+    assert_ne!(first.id, second.id);
+    assert_ne!(first.crypto.cipherparams, second.crypto.cipherparams);
+    assert_ne!(first.crypto.ciphertext, second.crypto.ciphertext);
+    assert_ne!(first.crypto.kdf, second.crypto.kdf);
+    assert_ne!(first.crypto.kdf, first.crypto.kdf.with_fresh_salt());
+}
+
+#[test]
+fn wrong_password_is_rejected() {
+    for kdf in kdfs() {
+        let keystore = Keystore::encrypt(&secret_key(), PASSWORD, kdf).unwrap();
+        assert_eq!(
+            keystore.decrypt("hunter2"),
+            Err(WRONG_PASSWORD_ERR.to_string())
+        );
+        assert_eq!(keystore.decrypt(""), Err(WRONG_PASSWORD_ERR.to_string()));
+    }
+}
+
+#[test]
+fn tampering_fails_the_mac() {
+    for kdf in kdfs() {
+        let keystore = Keystore::encrypt(&secret_key(), PASSWORD, kdf).unwrap();
+
+        let mut tampered = keystore.clone();
+        flip_first_bit(&mut tampered.crypto.ciphertext);
+        assert_eq!(
+            tampered.decrypt(PASSWORD),
+            Err(WRONG_PASSWORD_ERR.to_string())
+        );
+
+        let mut tampered = keystore.clone();
+        flip_first_bit(&mut tampered.crypto.mac);
+        assert_eq!(
+            tampered.decrypt(PASSWORD),
+            Err(WRONG_PASSWORD_ERR.to_string())
+        );
+
+        let mut truncated = keystore.clone();
+        truncated.crypto.mac.truncate(62);
+        assert_eq!(
+            truncated.decrypt(PASSWORD),
+            Err(WRONG_PASSWORD_ERR.to_string())
+        );
+
+        // Hex case is not significant
+        let mut uppercase = keystore;
+        uppercase.crypto.mac = uppercase.crypto.mac.to_uppercase();
+        assert_eq!(uppercase.decrypt(PASSWORD).unwrap(), secret_key());
+    }
+}
+
+#[test]
+fn mismatched_address_is_rejected() {
+    let mut keystore = Keystore::encrypt(&secret_key(), PASSWORD, Kdf::scrypt(4)).unwrap();
+    keystore.address = Address::ZERO;
+    assert_eq!(
+        keystore.decrypt(PASSWORD),
+        Err("Keystore key does not match its address".to_string())
+    );
+}
+
+#[test]
+fn unsupported_layouts_are_rejected() {
+    let keystore = Keystore::encrypt(&secret_key(), PASSWORD, Kdf::scrypt(4)).unwrap();
+
+    let mut other_version = keystore.clone();
+    other_version.version = 1;
+    assert!(other_version.decrypt(PASSWORD).is_err());
+
+    let mut other_cipher = keystore;
+    other_cipher.crypto.cipher = "aes-128-cbc".to_string();
+    assert!(other_cipher.decrypt(PASSWORD).is_err());
+
+    assert!(Keystore::from_json("{}").is_err());
+}
@@ -0,0 +1,301 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::mempool::{Mempool, MempoolConfig};
+use bharatchain::chain_core::state_db::StateDB;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use secp256k1::SecretKey;
+
+const CHAIN_ID: u64 = 1;
+
+// Secret key `n` (1, 2, ...) as hex
+fn key(n: u8) -> String {
+    format!("{:064x}", n)
+}
+
+fn address(n: u8) -> Address {
+    Account::from_secret_key(&key(n), Default::default())
+        .unwrap()
+        .address
+}
+
+// Senders 1 to 4, each holding 100 coins, and the empty receiver 9
+fn accounts() -> StateDB {
+    let mut accounts: StateDB = (1..=4)
+        .map(|n| Account::from_secret_key(&key(n), "100".parse().unwrap()).unwrap())
+        .collect();
+    accounts.insert(Account::from_secret_key(&key(9), Amount::ZERO).unwrap());
+    accounts
+}
+
+// Signed transfer of `amount` from sender `from` to sender 9, paying `fee` base units
+fn transfer(from: u8, nonce: u64, amount: &str, fee: u64) -> BlockTransaction {
+    let mut tx = BlockTransaction::new(
+        address(from),
+        address(9),
+        amount.parse().unwrap(),
+        nonce,
+        CHAIN_ID,
+    )
+    .with_fee(Amount::from_base_units(fee));
+    tx.sign(&SecretKey::from_slice(&hex::decode(key(from)).unwrap()).unwrap());
+    tx
+}
+
+fn submit(mempool: &mut Mempool, accounts: &StateDB, tx: BlockTransaction) -> String {
+    mempool.submit(tx, accounts, CHAIN_ID).unwrap()
+}
+
+// (sender, nonce) of each transaction in a block template
+fn template(mempool: &Mempool, accounts: &mut StateDB, max: usize) -> Vec<(Address, u64)> {
+    mempool
+        .block_template(accounts, max)
+        .iter()
+        .map(|tx| (tx.sender, tx.nonce))
+        .collect()
+}
+
+#[test]
+fn template_takes_the_highest_fees_first() {
+    let mut accounts = accounts();
+    let mut mempool = Mempool::default();
+    submit(&mut mempool, &accounts, transfer(1, 0, "1", 10));
+    submit(&mut mempool, &accounts, transfer(2, 0, "1", 30));
+    submit(&mut mempool, &accounts, transfer(3, 0, "1", 20));
+
+    assert_eq!(
+        template(&mempool, &mut accounts, 10),
+        vec![(address(2), 0), (address(3), 0), (address(1), 0)]
+    );
+    assert_eq!(
+        template(&mempool, &mut accounts, 2),
+        vec![(address(2), 0), (address(3), 0)]
+    );
+
+    // Building a template leaves the state and the pool untouched
+    assert_eq!(accounts.nonce(&address(2)), 0);
+    assert_eq!(accounts.get(&address(9)).unwrap().balance, Amount::ZERO);
+    assert_eq!(mempool.len(), 3);
+}
+
+#[test]
+fn template_keeps_each_sender_in_nonce_order() {
+    let mut accounts = accounts();
+    let mut mempool = Mempool::default();
+    // A high fee on nonce 1 cannot pull it ahead of nonce 0
+    submit(&mut mempool, &accounts, transfer(1, 0, "1", 1));
+    submit(&mut mempool, &accounts, transfer(1, 1, "1", 50));
+    submit(&mut mempool, &accounts, transfer(2, 0, "1", 5));
+
+    assert_eq!(
+        template(&mempool, &mut accounts, 10),
+        vec![(address(2), 0), (address(1), 0), (address(1), 1)]
+    );
+}
+
+#[test]
+fn template_stops_a_sender_at_a_transaction_that_no_longer_applies() {
+    let mut accounts = accounts();
+    let mut mempool = Mempool::default();
+    // Each fits the balance alone, but not after the one before it
+    submit(&mut mempool, &accounts, transfer(1, 0, "60", 3));
+    submit(&mut mempool, &accounts, transfer(1, 1, "60", 3));
+    submit(&mut mempool, &accounts, transfer(1, 2, "1", 3));
+    submit(&mut mempool, &accounts, transfer(2, 0, "1", 1));
+
+    assert_eq!(
+        template(&mempool, &mut accounts, 10),
+        vec![(address(1), 0), (address(2), 0)]
+    );
+}
+
+#[test]
+fn higher_fee_replaces_a_pending_transaction() {
+    let accounts = accounts();
+    let mut mempool = Mempool::default();
+    let original = submit(&mut mempool, &accounts, transfer(1, 0, "1", 10));
+
+    for fee in [5, 10] {
+        let err = mempool
+            .submit(transfer(1, 0, "2", fee), &accounts, CHAIN_ID)
+            .unwrap_err();
+        assert!(err.contains("must pay more"), "{}", err);
+    }
+    assert!(mempool.contains(&original));
+
+    let replacement = submit(&mut mempool, &accounts, transfer(1, 0, "2", 11));
+    assert_eq!(mempool.len(), 1);
+    assert!(!mempool.contains(&original));
+    assert_eq!(mempool.get(&replacement).unwrap().fee.base_units(), 11);
+}
+
+#[test]
+fn full_pool_evicts_the_lowest_fee_transaction() {
+    let accounts = accounts();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_transactions: 3,
+        max_per_sender: 64,
+    });
+    let first = submit(&mut mempool, &accounts, transfer(1, 0, "1", 4));
+    let second = submit(&mut mempool, &accounts, transfer(1, 1, "1", 6));
+    let cheapest = submit(&mut mempool, &accounts, transfer(2, 0, "1", 2));
+
+    // Not paying more than the cheapest entry
+    let err = mempool
+        .submit(transfer(3, 0, "1", 2), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Mempool is full and the transaction fee is too low");
+    assert_eq!(mempool.len(), 3);
+
+    let newcomer = submit(&mut mempool, &accounts, transfer(3, 0, "1", 3));
+    assert_eq!(mempool.len(), 3);
+    assert!(!mempool.contains(&cheapest));
+    for hash in [&first, &second, &newcomer] {
+        assert!(mempool.contains(hash));
+    }
+}
+
+#[test]
+fn evicting_a_transaction_drops_the_rest_of_its_senders_sequence() {
+    let accounts = accounts();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_transactions: 3,
+        max_per_sender: 64,
+    });
+    // Sender 1's nonce 0 is the cheapest in the pool, though its nonce 1 is not
+    let first = submit(&mut mempool, &accounts, transfer(1, 0, "1", 1));
+    let second = submit(&mut mempool, &accounts, transfer(1, 1, "1", 9));
+    let other = submit(&mut mempool, &accounts, transfer(2, 0, "1", 5));
+
+    let newcomer = submit(&mut mempool, &accounts, transfer(3, 0, "1", 2));
+    assert_eq!(mempool.len(), 2);
+    assert!(!mempool.contains(&first));
+    assert!(!mempool.contains(&second));
+    assert!(mempool.contains(&other));
+    assert!(mempool.contains(&newcomer));
+
+    // Sender 1 starts again from its account nonce
+    let err = mempool
+        .submit(transfer(1, 1, "1", 9), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Nonce 1 leaves a gap (next expected nonce is 0)");
+}
+
+#[test]
+fn full_pool_does_not_evict_the_senders_own_transactions() {
+    let accounts = accounts();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_transactions: 1,
+        max_per_sender: 64,
+    });
+    submit(&mut mempool, &accounts, transfer(1, 0, "1", 1));
+    let err = mempool
+        .submit(transfer(1, 1, "1", 100), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert!(err.contains("Mempool is full"), "{}", err);
+}
+
+#[test]
+fn nonces_must_continue_the_pending_sequence() {
+    let mut accounts = accounts();
+    let mut mempool = Mempool::default();
+
+    let err = mempool
+        .submit(transfer(1, 1, "1", 1), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Nonce 1 leaves a gap (next expected nonce is 0)");
+
+    submit(&mut mempool, &accounts, transfer(1, 0, "1", 1));
+    submit(&mut mempool, &accounts, transfer(1, 1, "1", 1));
+    let err = mempool
+        .submit(transfer(1, 3, "1", 1), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Nonce 3 leaves a gap (next expected nonce is 2)");
+
+    accounts.get_mut(&address(2)).unwrap().nonce = 5;
+    let err = mempool
+        .submit(transfer(2, 4, "1", 1), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Nonce 4 is stale (account nonce is 5)");
+    submit(&mut mempool, &accounts, transfer(2, 5, "1", 1));
+}
+
+#[test]
+fn per_sender_limit_is_enforced() {
+    let accounts = accounts();
+    let mut mempool = Mempool::new(MempoolConfig {
+        max_transactions: 100,
+        max_per_sender: 2,
+    });
+    submit(&mut mempool, &accounts, transfer(1, 0, "1", 1));
+    submit(&mut mempool, &accounts, transfer(1, 1, "1", 1));
+    let err = mempool
+        .submit(transfer(1, 2, "1", 1), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Sender already has 2 pending transactions");
+    submit(&mut mempool, &accounts, transfer(2, 0, "1", 1));
+}
+
+#[test]
+fn invalid_submissions_are_rejected() {
+    let accounts = accounts();
+    let mut mempool = Mempool::default();
+    let tx = transfer(1, 0, "1", 1);
+    let hash = submit(&mut mempool, &accounts, tx.clone());
+
+    let err = mempool.submit(tx, &accounts, CHAIN_ID).unwrap_err();
+    assert_eq!(err, format!("Transaction {} is already pending", hash));
+
+    let err = mempool
+        .submit(transfer(2, 0, "1", 1), &accounts, CHAIN_ID + 1)
+        .unwrap_err();
+    assert!(err.contains("is for chain"), "{}", err);
+
+    let err = mempool
+        .submit(transfer(2, 0, "101", 0), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Insufficient funds");
+
+    // Sender 5 has no account
+    let err = mempool
+        .submit(transfer(5, 0, "1", 0), &accounts, CHAIN_ID)
+        .unwrap_err();
+    assert_eq!(err, "Sender account not found");
+    assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn prune_drops_transactions_whose_nonce_was_used() {
+    let mut accounts = accounts();
+    let mut mempool = Mempool::default();
+    let used = submit(&mut mempool, &accounts, transfer(1, 0, "1", 1));
+    let next = submit(&mut mempool, &accounts, transfer(1, 1, "1", 2));
+    let other = submit(&mut mempool, &accounts, transfer(2, 0, "1", 1));
+
+    // Nonce 0 of sender 1 was used by a transaction from another source
+    accounts.get_mut(&address(1)).unwrap().nonce = 1;
+    mempool.prune(&accounts);
+    assert!(!mempool.contains(&used));
+    assert!(mempool.contains(&next));
+    assert!(mempool.contains(&other));
+
+    // The rest of sender 1's sequence is ready again
+    assert_eq!(
+        template(&mempool, &mut accounts, 10),
+        vec![(address(1), 1), (address(2), 0)]
+    );
+}
+
+#[test]
+fn included_transactions_are_removed() {
+    let mut accounts = accounts();
+    let mut mempool = Mempool::default();
+    let included = submit(&mut mempool, &accounts, transfer(1, 0, "1", 2));
+    let remaining = submit(&mut mempool, &accounts, transfer(2, 0, "1", 1));
+
+    let block = mempool.block_template(&mut accounts, 1);
+    mempool.remove_included(&block);
+    assert!(!mempool.contains(&included));
+    assert!(mempool.contains(&remaining));
+    assert_eq!(mempool.len(), 1);
+}
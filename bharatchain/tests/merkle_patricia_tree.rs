@@ -0,0 +1,143 @@
+use bharatchain::chain_core::merkle_tree::MerklePatriciaTree;
+
+fn sample_pairs() -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = ["do", "dog", "doge", "horse", "a", "ab", "abc", "b"]
+        .iter()
+        .map(|k| (k.to_string(), format!("value-{}", k)))
+        .collect();
+    for i in 0..64u32 {
+        pairs.push((
+            format!("{:x}", i.wrapping_mul(2654435761)),
+            format!("v{}", i),
+        ));
+    }
+    pairs
+}
+
+fn build(pairs: &[(String, String)]) -> MerklePatriciaTree {
+    let mut trie = MerklePatriciaTree::new();
+    for (key, value) in pairs {
+        trie.insert(key, value.clone());
+    }
+    trie
+}
+
+#[test]
+fn empty_trie_has_keccak_empty_root() {
+    let trie = MerklePatriciaTree::new();
+    assert_eq!(
+        trie.root_hash(),
+        "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+    assert_eq!(trie.get("missing"), None);
+}
+
+#[test]
+fn insert_and_get_including_prefix_keys() {
+    let pairs = sample_pairs();
+    let trie = build(&pairs);
+
+    for (key, value) in &pairs {
+        assert_eq!(trie.get(key).as_ref(), Some(value), "key {}", key);
+    }
+    assert_eq!(trie.get("d"), None);
+    assert_eq!(trie.get("dogs"), None);
+}
+
+#[test]
+fn overwrite_replaces_value() {
+    let mut trie = build(&sample_pairs());
+    trie.insert("dog", "puppy".to_string());
+    assert_eq!(trie.get("dog").as_deref(), Some("puppy"));
+    assert_eq!(trie.get("doge").as_deref(), Some("value-doge"));
+}
+
+#[test]
+fn root_is_independent_of_insertion_order() {
+    let pairs = sample_pairs();
+    let mut reversed = pairs.clone();
+    reversed.reverse();
+
+    let mut interleaved = pairs.clone();
+    interleaved.sort_by_key(|(key, _)| key.chars().rev().collect::<String>());
+
+    let root = build(&pairs).root_hash();
+    assert_eq!(root, build(&reversed).root_hash());
+    assert_eq!(root, build(&interleaved).root_hash());
+    assert_ne!(root, MerklePatriciaTree::empty_root());
+}
+
+#[test]
+fn delete_restores_canonical_root() {
+    let pairs = sample_pairs();
+    let mut trie = build(&pairs);
+
+    for (i, (key, _)) in pairs.iter().enumerate() {
+        assert!(trie.delete(key), "key {}", key);
+        assert_eq!(trie.get(key), None);
+        assert_eq!(trie.root_hash(), build(&pairs[i + 1..]).root_hash());
+    }
+
+    assert_eq!(trie.root_hash(), MerklePatriciaTree::empty_root());
+}
+
+#[test]
+fn delete_missing_key_is_a_no_op() {
+    let mut trie = build(&sample_pairs());
+    let root = trie.root_hash();
+
+    assert!(!trie.delete("d"));
+    assert!(!trie.delete("dogs"));
+    assert!(!trie.delete("zzz"));
+    assert_eq!(trie.root_hash(), root);
+}
+
+#[test]
+fn set_root_reverts_to_an_earlier_state() {
+    let mut trie = build(&sample_pairs());
+    let root = trie.root_hash();
+
+    trie.insert("dog", "puppy".to_string());
+    trie.delete("horse");
+    assert_ne!(trie.root_hash(), root);
+
+    trie.set_root(&root).unwrap();
+    assert_eq!(trie.get("dog").as_deref(), Some("value-dog"));
+    assert_eq!(trie.get("horse").as_deref(), Some("value-horse"));
+    assert!(trie.set_root("not-a-root").is_err());
+}
+
+#[test]
+fn prune_drops_replaced_nodes_and_keeps_the_current_state() {
+    let pairs = sample_pairs();
+    let mut trie = build(&pairs);
+    for round in 0..10 {
+        trie.insert("dog", format!("puppy-{}", round));
+        trie.delete("horse");
+        trie.insert("horse", "value-horse".to_string());
+    }
+    let old_root = build(&pairs).root_hash();
+    let root = trie.root_hash();
+
+    trie.prune();
+    assert_eq!(trie.root_hash(), root);
+    assert_eq!(trie.get("dog").as_deref(), Some("puppy-9"));
+    assert_eq!(trie.get("horse").as_deref(), Some("value-horse"));
+
+    // Only the live nodes are left: exactly what a pruned fresh build of the
+    // same contents holds
+    let mut live = pairs.clone();
+    live.iter_mut().find(|(key, _)| key == "dog").unwrap().1 = "puppy-9".to_string();
+    let mut fresh = build(&live);
+    assert!(fresh.node_count() > trie.node_count());
+    fresh.prune();
+    assert_eq!(trie.node_count(), fresh.node_count());
+    assert!(trie.set_root(&old_root).is_err());
+
+    // Pruning an empty trie drops everything
+    for (key, _) in &pairs {
+        trie.delete(key);
+    }
+    trie.prune();
+    assert_eq!(trie.node_count(), 0);
+}
@@ -0,0 +1,211 @@
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::{DataBlock, BLOCK_VERSION, BLOCK_VERSION_LEGACY};
+use bharatchain::chain_core::transaction::{
+    verify_merkle_proof, BlockTransaction, MerkleProof, MerkleTree,
+};
+
+fn root(version: u32, hashes: &[&str]) -> String {
+    MerkleTree::with_version(version)
+        .build_merkle_tree(hashes.iter().map(|hash| hash.to_string()).collect())
+}
+
+// Root of a tree over `count` distinct hashes, and a proof for each of them
+fn proofs_for(version: u32, count: usize) -> (String, Vec<MerkleProof>) {
+    let hashes: Vec<String> = (0..count).map(|i| format!("{:02x}", i)).collect();
+    let mut tree = MerkleTree::with_version(version);
+    let root = tree.build_merkle_tree(hashes.clone());
+    let proofs = hashes
+        .iter()
+        .map(|hash| tree.get_proof(hash).unwrap())
+        .collect();
+    (root, proofs)
+}
+
+#[test]
+fn v2_root_hashes_leaves_and_nodes_with_distinct_prefixes() {
+    // sha256(0x01 || sha256(0x00 || "aa") || sha256(0x00 || "bb")), hex digests as text
+    assert_eq!(
+        root(BLOCK_VERSION, &["aa", "bb"]),
+        "41cccca33c4ab9beac49f38f89ce9927a2839825da89e01c6fdb19c83bfabe3e"
+    );
+    // A single transaction still gets a leaf hash, so it never equals a transaction hash
+    assert_eq!(
+        root(BLOCK_VERSION, &["aa"]),
+        "3f230e74061c800726a611f586dcf7d739cbc83959556a34203d34f7cba562c7"
+    );
+}
+
+#[test]
+fn v2_promotes_the_odd_leaf() {
+    assert_eq!(
+        root(BLOCK_VERSION, &["aa", "bb", "cc"]),
+        "3695047ec61fd7e8205b6c388fb380435e7f293988f797f6f9a2496467c9b817"
+    );
+    // Duplicating the last transaction no longer yields the same root
+    assert_ne!(
+        root(BLOCK_VERSION, &["aa", "bb", "cc"]),
+        root(BLOCK_VERSION, &["aa", "bb", "cc", "cc"])
+    );
+}
+
+#[test]
+fn legacy_root_duplicates_the_odd_leaf() {
+    assert_eq!(
+        root(BLOCK_VERSION_LEGACY, &["aa", "bb", "cc"]),
+        "0cbe39bd48cba9a69ee1e0cf4c002a3a80810e37b63b0b8b30f948cc3d660641"
+    );
+    // The malleability v2 fixes: two transaction lists, one root
+    assert_eq!(
+        root(BLOCK_VERSION_LEGACY, &["aa", "bb", "cc"]),
+        root(BLOCK_VERSION_LEGACY, &["aa", "bb", "cc", "cc"])
+    );
+}
+
+#[test]
+fn empty_roots() {
+    assert_eq!(
+        root(BLOCK_VERSION, &[]),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        MerkleTree::empty_root(BLOCK_VERSION),
+        root(BLOCK_VERSION, &[])
+    );
+    assert_eq!(
+        MerkleTree::empty_root(BLOCK_VERSION_LEGACY),
+        root(BLOCK_VERSION_LEGACY, &[])
+    );
+}
+
+#[test]
+fn legacy_and_v2_roots_differ() {
+    for hashes in [&["aa"][..], &["aa", "bb"], &["aa", "bb", "cc"], &[]] {
+        assert_ne!(
+            root(BLOCK_VERSION_LEGACY, hashes),
+            root(BLOCK_VERSION, hashes),
+            "{:?}",
+            hashes
+        );
+    }
+}
+
+#[test]
+fn proofs_verify_for_every_leaf() {
+    for version in [BLOCK_VERSION, BLOCK_VERSION_LEGACY] {
+        // Odd counts exercise the promoted (v2) or duplicated (legacy) last node
+        for count in 1..=9 {
+            let (root, proofs) = proofs_for(version, count);
+            for proof in &proofs {
+                assert_eq!(proof.version, version);
+                assert!(
+                    verify_merkle_proof(proof, &root),
+                    "v{} leaf {} of {}",
+                    version,
+                    proof.transaction_hash,
+                    count
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn proof_path_length_follows_the_tree_shape() {
+    let (_, single) = proofs_for(BLOCK_VERSION, 1);
+    assert!(single[0].path.is_empty());
+
+    // Five leaves: four pair up over three levels, the fifth is promoted
+    // twice and only joins at the root
+    let (_, five) = proofs_for(BLOCK_VERSION, 5);
+    let lengths: Vec<usize> = five.iter().map(|proof| proof.path.len()).collect();
+    assert_eq!(lengths, vec![3, 3, 3, 3, 1]);
+    assert!(five[4].path[0].is_left);
+
+    // Legacy duplicates the fifth leaf, so it pairs with itself on every level
+    let (_, five) = proofs_for(BLOCK_VERSION_LEGACY, 5);
+    let lengths: Vec<usize> = five.iter().map(|proof| proof.path.len()).collect();
+    assert_eq!(lengths, vec![3, 3, 3, 3, 3]);
+    assert_eq!(five[4].path[0].hash, five[4].transaction_hash);
+}
+
+#[test]
+fn proof_for_a_different_leaf_fails() {
+    for version in [BLOCK_VERSION, BLOCK_VERSION_LEGACY] {
+        let (root, proofs) = proofs_for(version, 5);
+
+        // Another leaf's hash on this leaf's path
+        let mut swapped = proofs[0].clone();
+        swapped.transaction_hash = proofs[1].transaction_hash.clone();
+        assert!(!verify_merkle_proof(&swapped, &root));
+
+        let mut unknown = proofs[4].clone();
+        unknown.transaction_hash = "ff".to_string();
+        assert!(!verify_merkle_proof(&unknown, &root));
+
+        // Tampered sibling, side or path length
+        let mut sibling = proofs[2].clone();
+        sibling.path[1].hash = proofs[2].transaction_hash.clone();
+        assert!(!verify_merkle_proof(&sibling, &root));
+
+        let mut side = proofs[2].clone();
+        side.path[0].is_left = !side.path[0].is_left;
+        assert!(!verify_merkle_proof(&side, &root));
+
+        let mut short = proofs[2].clone();
+        short.path.pop();
+        assert!(!verify_merkle_proof(&short, &root));
+    }
+}
+
+#[test]
+fn proof_against_a_different_root_fails() {
+    let (root, proofs) = proofs_for(BLOCK_VERSION, 4);
+    let (other_root, _) = proofs_for(BLOCK_VERSION, 5);
+    assert!(verify_merkle_proof(&proofs[0], &root));
+    assert!(!verify_merkle_proof(&proofs[0], &other_root));
+    assert!(!verify_merkle_proof(
+        &proofs[0],
+        &MerkleTree::empty_root(BLOCK_VERSION)
+    ));
+
+    // A proof is only valid under the construction of its version
+    let (legacy_root, _) = proofs_for(BLOCK_VERSION_LEGACY, 4);
+    assert!(!verify_merkle_proof(&proofs[0], &legacy_root));
+    let mut relabelled = proofs[0].clone();
+    relabelled.version = BLOCK_VERSION_LEGACY;
+    assert!(!verify_merkle_proof(&relabelled, &root));
+}
+
+#[test]
+fn missing_transaction_has_no_proof() {
+    let mut tree = MerkleTree::new();
+    tree.build_merkle_tree(vec!["aa".to_string(), "bb".to_string()]);
+    assert_eq!(tree.get_proof("cc"), None);
+    // Inner nodes are not leaves
+    let root = root(BLOCK_VERSION, &["aa", "bb"]);
+    assert_eq!(tree.get_proof(&root), None);
+    assert_eq!(MerkleTree::new().get_proof("aa"), None);
+}
+
+#[test]
+fn block_transaction_proofs_verify_against_the_header() {
+    let transactions: Vec<BlockTransaction> = (0..3)
+        .map(|n| {
+            BlockTransaction::new(
+                Address::from_hash([1; 32]),
+                Address::from_hash([2; 32]),
+                Amount::ZERO,
+                n,
+                1,
+            )
+        })
+        .collect();
+    let block = DataBlock::new(1, String::new(), transactions.clone());
+
+    for tx in &transactions {
+        let proof = block.transaction_proof(&tx.compute_hash()).unwrap();
+        assert!(verify_merkle_proof(&proof, &block.header.merkle_root));
+    }
+    assert_eq!(block.transaction_proof("aa"), None);
+}
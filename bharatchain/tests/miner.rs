@@ -0,0 +1,142 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use bharatchain::chain_core::block::{BlockHeader, DataBlock};
+use bharatchain::chain_core::chain::{BharatChain, SharedChain};
+use bharatchain::chain_core::difficulty::CompactTarget;
+use bharatchain::chain_core::genesis::GenesisConfig;
+use bharatchain::chain_core::helper::get_current_timestamp;
+use bharatchain::chain_core::miner::{mine_shared, CancelToken, Miner, MiningOutcome};
+
+// About one hash in eight meets it: 0x1fffff << 232
+const EASY: CompactTarget = CompactTarget::from_bits(0x201f_ffff);
+
+// A target of 1, which no hash is expected to meet
+const IMPOSSIBLE: CompactTarget = CompactTarget::from_bits(0x0300_0001);
+
+// Bitcoin's initial target, far out of reach of a test
+const HARD: CompactTarget = CompactTarget::from_bits(0x1d00_ffff);
+
+// A year the clock will not reach while the tests run
+const FAR_FUTURE: u64 = 4_000_000_000;
+
+fn block(bits: CompactTarget, timestamp: u64) -> DataBlock {
+    let mut block = DataBlock::new(1, String::new(), vec![]);
+    block.header.bits = bits;
+    block.header.timestamp = timestamp;
+    block
+}
+
+fn mined(outcome: MiningOutcome) -> DataBlock {
+    match outcome {
+        MiningOutcome::Mined(block, _) => *block,
+        MiningOutcome::Cancelled(_) => panic!("mining was cancelled"),
+    }
+}
+
+// Whether any nonce up to `max_nonce` solves `header`
+fn round_has_solution(header: &BlockHeader, max_nonce: u32) -> bool {
+    let mut candidate = header.clone();
+    (0..=max_nonce).any(|nonce| {
+        candidate.nounce = nonce;
+        candidate.meets_target(&candidate.hash())
+    })
+}
+
+#[test]
+fn mined_block_meets_its_target() {
+    let block = mined(Miner::new(2).mine(block(EASY, FAR_FUTURE), &CancelToken::new()));
+    assert!(block.meets_target());
+    assert_eq!(block.block_hash, block.calculate_hash());
+}
+
+#[test]
+fn each_round_tries_every_nonce_once_across_the_workers() {
+    // Cancelled up front, so exactly one round runs
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    for threads in [1, 3, 4] {
+        let miner = Miner::new(threads).with_max_nonce(99);
+        match miner.mine(block(IMPOSSIBLE, FAR_FUTURE), &cancel) {
+            MiningOutcome::Cancelled(stats) => assert_eq!(stats.hashes, 100, "{}", threads),
+            MiningOutcome::Mined(..) => panic!("target of 1 was met"),
+        }
+    }
+}
+
+#[test]
+fn exhausted_nonces_roll_the_extra_nonce() {
+    // The timestamp is ahead of the clock, so only the extra nonce can change.
+    // Start from a header whose first round fails.
+    let max_nonce = 3;
+    let mut start = block(EASY, FAR_FUTURE);
+    while round_has_solution(&start.header, max_nonce) {
+        start.header.timestamp += 1;
+    }
+    let mut solved = start.header.clone();
+    while !round_has_solution(&solved, max_nonce) {
+        solved.extra_nonce += 1;
+    }
+
+    let miner = Miner::new(2).with_max_nonce(max_nonce);
+    let block = mined(miner.mine(start, &CancelToken::new()));
+    assert_eq!(block.header.extra_nonce, solved.extra_nonce);
+    assert_eq!(block.header.timestamp, solved.timestamp);
+    assert!(block.header.nounce <= max_nonce);
+    assert!(block.meets_target());
+}
+
+#[test]
+fn exhausted_nonces_roll_the_timestamp_once_the_clock_has_passed_it() {
+    // Impossible in the first round, so the header must change to succeed
+    let max_nonce = 0;
+    let mut start = block(EASY, 1);
+    while round_has_solution(&start.header, max_nonce) {
+        start.header.timestamp += 1;
+    }
+
+    let before = get_current_timestamp();
+    let miner = Miner::new(1).with_max_nonce(max_nonce);
+    let block = mined(miner.mine(start, &CancelToken::new()));
+    assert!(block.header.timestamp >= before);
+    assert_eq!(block.header.nounce, 0);
+    assert!(block.meets_target());
+}
+
+#[test]
+fn cancel_token_stops_mine_shared() {
+    let genesis = GenesisConfig {
+        bits: HARD,
+        ..Default::default()
+    };
+    let chain: SharedChain = Arc::new(Mutex::new(BharatChain::new(genesis).unwrap()));
+
+    let (done, result) = mpsc::channel();
+    let shared = chain.clone();
+    thread::spawn(move || {
+        done.send(mine_shared(&shared, &Miner::new(2), 10)).unwrap();
+    });
+    thread::sleep(Duration::from_millis(100));
+
+    // Every job at this height shares the chain's current token
+    let (_, cancel) = chain.lock().unwrap().prepare_block(vec![]).unwrap();
+    cancel.cancel();
+
+    let outcome = result
+        .recv_timeout(Duration::from_secs(30))
+        .expect("mining stopped");
+    assert!(matches!(outcome, Ok(None)), "{:?}", outcome);
+    assert_eq!(chain.lock().unwrap().height(), 0);
+}
+
+#[test]
+fn cancelled_token_stays_cancelled_across_clones() {
+    let token = CancelToken::new();
+    let clone = token.clone();
+    assert!(!clone.is_cancelled());
+    token.cancel();
+    assert!(clone.is_cancelled());
+    assert!(!CancelToken::new().is_cancelled());
+}
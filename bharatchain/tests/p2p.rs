@@ -0,0 +1,291 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::chain::{BharatChain, SharedChain};
+use bharatchain::chain_core::genesis::{GenesisAllocation, GenesisConfig};
+use bharatchain::chain_core::miner::Miner;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use bharatchain::p2p::message::{
+    read_message, write_message, InventoryItem, Message, MAX_INVENTORY, PROTOCOL_VERSION,
+};
+use bharatchain::p2p::node::{NodeConfig, P2pNode};
+use secp256k1::SecretKey;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+
+const SENDER_KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+const RECEIVER_KEY: &str = "b0b0000000000000000000000000000000000000000000000000000000000002";
+
+fn address(key: &str) -> Address {
+    Account::from_secret_key(key, Default::default())
+        .unwrap()
+        .address
+}
+
+// Low difficulty genesis funding the sender and receiver
+fn genesis() -> GenesisConfig {
+    GenesisConfig {
+        allocations: vec![
+            GenesisAllocation {
+                address: address(SENDER_KEY),
+                balance: "100".parse().unwrap(),
+            },
+            GenesisAllocation {
+                address: address(RECEIVER_KEY),
+                balance: "1".parse().unwrap(),
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+// Start a node listening on a loopback port
+async fn start_node(genesis: GenesisConfig) -> (SocketAddr, SharedChain, P2pNode) {
+    let mut chain = BharatChain::new(genesis).unwrap();
+    chain.miner = Miner::new(1);
+    let chain: SharedChain = Arc::new(Mutex::new(chain));
+
+    let node = P2pNode::new(chain.clone(), NodeConfig::default());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(node.clone().serve(listener));
+    (addr, chain, node)
+}
+
+fn tip(chain: &SharedChain) -> String {
+    chain.lock().unwrap().get_latest_block().block_hash.clone()
+}
+
+// Poll `condition` until it holds, failing the test after a few seconds
+async fn wait_until(what: &str, condition: impl Fn() -> bool) {
+    let started = Instant::now();
+    while !condition() {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "timed out waiting until {}",
+            what
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+fn signed_transfer(chain: &SharedChain) -> BlockTransaction {
+    let chain = chain.lock().unwrap();
+    let mut tx = BlockTransaction::new(
+        address(SENDER_KEY),
+        address(RECEIVER_KEY),
+        "2.5".parse().unwrap(),
+        chain.get_nonce(&address(SENDER_KEY)),
+        chain.chain_id,
+    );
+    tx.sign(&SecretKey::from_slice(&hex::decode(SENDER_KEY).unwrap()).unwrap());
+    tx
+}
+
+#[tokio::test]
+async fn mined_block_propagates_to_every_peer() {
+    // a <- b <- c: c only hears about a's blocks through b
+    let (a_addr, a, _) = start_node(genesis()).await;
+    let (b_addr, b, b_node) = start_node(genesis()).await;
+    let (_, c, c_node) = start_node(genesis()).await;
+
+    let peer = b_node.connect(a_addr).await.unwrap();
+    assert_eq!(peer.version, PROTOCOL_VERSION);
+    assert!(!peer.inbound);
+    c_node.connect(b_addr).await.unwrap();
+
+    a.lock().unwrap().mine_pending_block(100).unwrap();
+    let mined = tip(&a);
+
+    wait_until("every node has the block", || {
+        tip(&b) == mined && tip(&c) == mined
+    })
+    .await;
+    for chain in [&a, &b, &c] {
+        let chain = chain.lock().unwrap();
+        assert_eq!(chain.height(), 1);
+        assert!(chain.is_valid());
+    }
+}
+
+#[tokio::test]
+async fn transactions_are_gossiped_and_mined_blocks_clear_them() {
+    let (a_addr, a, _) = start_node(genesis()).await;
+    let (_, b, b_node) = start_node(genesis()).await;
+    b_node.connect(a_addr).await.unwrap();
+
+    let tx = signed_transfer(&b);
+    let tx_hash = b.lock().unwrap().submit_transaction(tx).unwrap();
+    wait_until("the transaction reaches a", || {
+        a.lock().unwrap().mempool.contains(&tx_hash)
+    })
+    .await;
+
+    a.lock().unwrap().mine_pending_block(100).unwrap();
+    wait_until("b connects the block", || tip(&b) == tip(&a)).await;
+    assert!(b.lock().unwrap().mempool.is_empty());
+    assert_eq!(
+        b.lock().unwrap().get_balance(&address(RECEIVER_KEY)),
+        Some("3.5".parse().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn new_peer_catches_up_with_existing_blocks() {
+    let (a_addr, a, a_node) = start_node(genesis()).await;
+    for _ in 0..3 {
+        a.lock().unwrap().mine_pending_block(100).unwrap();
+    }
+
+    let (_, b, b_node) = start_node(genesis()).await;
+    let peer = b_node.connect(a_addr).await.unwrap();
+    assert_eq!(peer.height, 3);
+
+    wait_until("b syncs a's chain", || tip(&b) == tip(&a)).await;
+    wait_until("a registers the inbound peer", || a_node.peer_count() == 1).await;
+    assert!(a_node.peers()[0].inbound);
+}
+
+#[tokio::test]
+async fn handshake_rejects_a_different_network() {
+    let (a_addr, _, a_node) = start_node(genesis()).await;
+
+    let other_chain = GenesisConfig {
+        chain_id: 7,
+        ..genesis()
+    };
+    let (_, _, b_node) = start_node(other_chain).await;
+    let error = b_node.connect(a_addr).await.unwrap_err();
+    assert!(error.contains("chain"), "{}", error);
+
+    let other_genesis = GenesisConfig {
+        timestamp: 1,
+        ..genesis()
+    };
+    let (_, _, c_node) = start_node(other_genesis).await;
+    let error = c_node.connect(a_addr).await.unwrap_err();
+    assert!(error.contains("genesis"), "{}", error);
+
+    assert_eq!(a_node.peer_count(), 0);
+}
+
+#[tokio::test]
+async fn version_ping_and_getdata_over_the_wire() {
+    let (addr, chain, _) = start_node(genesis()).await;
+    let genesis_hash = tip(&chain);
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+
+    write_message(
+        &mut stream,
+        &Message::Version {
+            version: PROTOCOL_VERSION,
+            chain_id: 1,
+            genesis_hash: genesis_hash.clone(),
+            height: 0,
+            nonce: 99,
+        },
+    )
+    .await
+    .unwrap();
+    match read_message(&mut stream).await.unwrap() {
+        Message::Version {
+            genesis_hash: hash,
+            height,
+            ..
+        } => {
+            assert_eq!(hash, genesis_hash);
+            assert_eq!(height, 0);
+        }
+        other => panic!("expected version, got {:?}", other),
+    }
+    assert!(matches!(
+        read_message(&mut stream).await.unwrap(),
+        Message::Verack
+    ));
+    write_message(&mut stream, &Message::Verack).await.unwrap();
+
+    // We claim no blocks beyond genesis, so the node asks for nothing and just answers our ping
+    write_message(&mut stream, &Message::Ping(42))
+        .await
+        .unwrap();
+    assert!(matches!(
+        read_message(&mut stream).await.unwrap(),
+        Message::Pong(42)
+    ));
+
+    // Blocks it has are sent in full; unknown ones are reported missing
+    let request = Message::GetData(vec![
+        InventoryItem::Block(genesis_hash.clone()),
+        InventoryItem::Block("00".repeat(32)),
+    ]);
+    write_message(&mut stream, &request).await.unwrap();
+    match read_message(&mut stream).await.unwrap() {
+        Message::Block(block) => assert_eq!(block.block_hash, genesis_hash),
+        other => panic!("expected block, got {:?}", other),
+    }
+    match read_message(&mut stream).await.unwrap() {
+        Message::NotFound(items) => assert_eq!(items, vec![InventoryItem::Block("00".repeat(32))]),
+        other => panic!("expected notfound, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn peer_that_stops_reading_is_dropped_when_its_queue_fills() {
+    let mut chain = BharatChain::new(genesis()).unwrap();
+    chain.miner = Miner::new(1);
+    let genesis_hash = chain.get_latest_block().block_hash.clone();
+    let config = NodeConfig {
+        max_queued_messages: 4,
+        ..NodeConfig::default()
+    };
+    let node = P2pNode::new(Arc::new(Mutex::new(chain)), config);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(node.clone().serve(listener));
+
+    // Complete the handshake, then never read again
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    write_message(
+        &mut stream,
+        &Message::Version {
+            version: PROTOCOL_VERSION,
+            chain_id: 1,
+            genesis_hash,
+            height: 0,
+            nonce: 99,
+        },
+    )
+    .await
+    .unwrap();
+    read_message(&mut stream).await.unwrap();
+    read_message(&mut stream).await.unwrap();
+    write_message(&mut stream, &Message::Verack).await.unwrap();
+    wait_until("the node registers the peer", || node.peer_count() == 1).await;
+
+    // Large announcements fill the socket buffers, then the queue
+    let inventory = Message::Inv(
+        (0..MAX_INVENTORY)
+            .map(|i| InventoryItem::Transaction(format!("{:064x}", i)))
+            .collect(),
+    );
+    let started = Instant::now();
+    while node.peer_count() > 0 {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "timed out waiting for the peer to be dropped"
+        );
+        node.broadcast(inventory.clone());
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    // The connection is closed rather than left hanging
+    let mut rest = vec![];
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        tokio::io::AsyncReadExt::read_to_end(&mut stream, &mut rest),
+    )
+    .await
+    .expect("connection closed")
+    .ok();
+}
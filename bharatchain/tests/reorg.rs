@@ -0,0 +1,189 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::block::DataBlock;
+use bharatchain::chain_core::chain::{BharatChain, BlockStatus, ChainEvent, Reorg};
+use bharatchain::chain_core::genesis::{GenesisAllocation, GenesisConfig};
+use bharatchain::chain_core::miner::{Miner, MiningOutcome};
+use bharatchain::chain_core::transaction::BlockTransaction;
+use secp256k1::SecretKey;
+
+const SENDER_KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+const RECEIVER_KEY: &str = "b0b0000000000000000000000000000000000000000000000000000000000002";
+const NEW_KEY: &str = "c4a1000000000000000000000000000000000000000000000000000000000003";
+
+fn address(key: &str) -> Address {
+    Account::from_secret_key(key, Default::default())
+        .unwrap()
+        .address
+}
+
+// Low difficulty chain funding the sender and receiver
+fn new_chain() -> BharatChain {
+    let genesis = GenesisConfig {
+        allocations: vec![
+            GenesisAllocation {
+                address: address(SENDER_KEY),
+                balance: "100".parse().unwrap(),
+            },
+            GenesisAllocation {
+                address: address(RECEIVER_KEY),
+                balance: "1".parse().unwrap(),
+            },
+        ],
+        ..Default::default()
+    };
+    let mut chain = BharatChain::new(genesis).unwrap();
+    chain.miner = Miner::new(1);
+    chain
+}
+
+fn signed_transfer(chain: &BharatChain, amount: &str) -> BlockTransaction {
+    let mut tx = BlockTransaction::new(
+        address(SENDER_KEY),
+        address(RECEIVER_KEY),
+        amount.parse().unwrap(),
+        chain.get_nonce(&address(SENDER_KEY)),
+        chain.chain_id,
+    );
+    tx.sign(&SecretKey::from_slice(&hex::decode(SENDER_KEY).unwrap()).unwrap());
+    tx
+}
+
+// Mine `txns` onto the chain's tip and return the block
+fn mine(chain: &mut BharatChain, txns: Vec<BlockTransaction>) -> DataBlock {
+    chain.add_block(txns).unwrap();
+    chain.get_latest_block().clone()
+}
+
+// A block on the chain's tip with valid proof-of-work but a wrong state root
+fn mine_invalid(chain: &mut BharatChain) -> DataBlock {
+    let (mut block, cancel) = chain.prepare_block(vec![]).unwrap();
+    block.set_state_root("00".repeat(32));
+    match chain.miner.mine(block, &cancel) {
+        MiningOutcome::Mined(block, _) => *block,
+        MiningOutcome::Cancelled(_) => panic!("mining was cancelled"),
+    }
+}
+
+fn balance(chain: &BharatChain, key: &str) -> String {
+    chain.get_balance(&address(key)).unwrap().to_string()
+}
+
+#[test]
+fn block_without_more_work_stays_on_a_side_branch() {
+    let mut a = new_chain();
+    let mut b = new_chain();
+    let a1 = mine(&mut a, vec![]);
+    let tx = signed_transfer(&b, "2.5");
+    let b1 = mine(&mut b, vec![tx]);
+
+    assert_eq!(a.submit_block(b1.clone()), Ok(BlockStatus::SideBranch));
+    assert_eq!(a.get_latest_block().block_hash, a1.block_hash);
+    assert_eq!(balance(&a, SENDER_KEY), "100");
+    assert!(a.submit_block(b1).is_err(), "side blocks are known");
+}
+
+#[test]
+fn heavier_branch_reorganizes_and_rolls_back_state() {
+    let mut a = new_chain();
+    let mut b = new_chain();
+    let tx = signed_transfer(&a, "2.5");
+    let tx_hash = tx.compute_hash();
+    let a1 = mine(&mut a, vec![tx]);
+    assert_eq!(balance(&a, SENDER_KEY), "97.5");
+
+    let b1 = mine(&mut b, vec![]);
+    let b2 = mine(&mut b, vec![]);
+    let events = a.subscribe();
+
+    assert_eq!(a.submit_block(b1.clone()), Ok(BlockStatus::SideBranch));
+    let expected = Reorg {
+        fork_point: 0,
+        disconnected: vec![a1.block_hash.clone()],
+        connected: vec![b1.block_hash.clone(), b2.block_hash.clone()],
+        returned_transactions: vec![tx_hash.clone()],
+    };
+    assert_eq!(
+        a.submit_block(b2.clone()),
+        Ok(BlockStatus::Reorganized(expected.clone()))
+    );
+
+    // Main chain and state now follow the branch; the orphaned transfer is pending again
+    assert_eq!(a.height(), 2);
+    assert_eq!(a.get_latest_block().block_hash, b2.block_hash);
+    assert_eq!(a.get_latest_block().header.state_root, b2.header.state_root);
+    assert_eq!(balance(&a, SENDER_KEY), "100");
+    assert_eq!(a.get_nonce(&address(SENDER_KEY)), 0);
+    assert!(a.mempool.contains(&tx_hash));
+    assert!(a.is_valid());
+
+    let events: Vec<ChainEvent> = events.try_iter().collect();
+    assert_eq!(
+        events,
+        vec![
+            ChainEvent::TransactionAccepted { tx_hash },
+            ChainEvent::Reorganized(expected),
+        ]
+    );
+}
+
+#[test]
+fn failed_reorganization_restores_the_chain_and_mempool() {
+    let mut a = new_chain();
+    let mut b = new_chain();
+    let a1 = mine(&mut a, vec![]);
+
+    let tx = signed_transfer(&a, "2.5");
+    let tx_hash = a.submit_transaction(tx.clone()).unwrap();
+    let b1 = mine(&mut b, vec![tx]);
+    let b2 = mine_invalid(&mut b);
+
+    assert_eq!(a.submit_block(b1), Ok(BlockStatus::SideBranch));
+    let err = a.submit_block(b2.clone()).unwrap_err();
+    assert!(err.contains("state root"), "{}", err);
+
+    // The old tip is back, with its state, and the branch's transfer is pending again
+    assert_eq!(a.get_latest_block().block_hash, a1.block_hash);
+    assert_eq!(balance(&a, SENDER_KEY), "100");
+    assert!(a.mempool.contains(&tx_hash));
+    assert!(a.is_valid());
+
+    // The invalid block is remembered
+    let err = a.submit_block(b2).unwrap_err();
+    assert!(err.contains("invalid branch"), "{}", err);
+
+    // The restored mempool still mines
+    a.mine_pending_block(10).unwrap();
+    assert_eq!(balance(&a, SENDER_KEY), "97.5");
+}
+
+#[test]
+fn reorganization_removes_accounts_the_old_branch_created() {
+    let mut a = new_chain();
+    let mut b = new_chain();
+    let mut tx = BlockTransaction::new(
+        address(SENDER_KEY),
+        address(NEW_KEY),
+        "2.5".parse().unwrap(),
+        0,
+        a.chain_id,
+    );
+    tx.sign(&SecretKey::from_slice(&hex::decode(SENDER_KEY).unwrap()).unwrap());
+    mine(&mut a, vec![tx]);
+    assert!(a.state.get(&address(NEW_KEY).to_string()).is_some());
+
+    mine(&mut b, vec![]);
+    let b2 = mine(&mut b, vec![]);
+    let b1 = b.chain[1].clone();
+    a.submit_block(b1).unwrap();
+    assert!(matches!(
+        a.submit_block(b2.clone()),
+        Ok(BlockStatus::Reorganized(_))
+    ));
+
+    // The account is gone from both the accounts and the state trie
+    assert_eq!(a.get_balance(&address(NEW_KEY)), None);
+    assert_eq!(a.state.get(&address(NEW_KEY).to_string()), None);
+    assert_eq!(a.state.root_hash(), b2.header.state_root);
+    assert!(a.is_valid());
+}
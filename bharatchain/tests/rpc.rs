@@ -0,0 +1,238 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::chain::{BharatChain, SharedChain};
+use bharatchain::chain_core::encoding;
+use bharatchain::chain_core::genesis::{GenesisAllocation, GenesisConfig};
+use bharatchain::chain_core::transaction::BlockTransaction;
+use bharatchain::rpc::http::{MAX_HEADERS_SIZE, MAX_LINE_SIZE};
+use bharatchain::rpc::server::{RpcServer, INVALID_PARAMS, METHOD_NOT_FOUND, TRANSACTION_REJECTED};
+use secp256k1::SecretKey;
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const SENDER_KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+const RECEIVER_KEY: &str = "b0b0000000000000000000000000000000000000000000000000000000000002";
+
+// Start a server on a loopback port backed by a low difficulty chain
+async fn start_server(allocations: Vec<GenesisAllocation>) -> (SocketAddr, SharedChain) {
+    let genesis = GenesisConfig {
+        allocations,
+        ..Default::default()
+    };
+    let chain: SharedChain = Arc::new(Mutex::new(BharatChain::new(genesis).unwrap()));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(RpcServer::new(chain.clone()).serve(listener));
+    (addr, chain)
+}
+
+async fn post(addr: SocketAddr, body: &str) -> (String, String) {
+    let request = format!(
+        "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        addr,
+        body.len(),
+        body
+    );
+    send_raw(addr, request.as_bytes()).await
+}
+
+// Send `request` as is and return the response status line and body
+async fn send_raw(addr: SocketAddr, request: &[u8]) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+async fn call(addr: SocketAddr, method: &str, params: Value) -> Value {
+    let request = json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 7 });
+    let (status, body) = post(addr, &request.to_string()).await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+
+    let response: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["jsonrpc"], "2.0");
+    assert_eq!(response["id"], 7);
+    response
+}
+
+fn signed_transfer(chain: &SharedChain, amount: &str) -> BlockTransaction {
+    let sender = Account::from_secret_key(SENDER_KEY, Default::default()).unwrap();
+    let receiver = Account::from_secret_key(RECEIVER_KEY, Default::default()).unwrap();
+    let chain = chain.lock().unwrap();
+
+    let mut tx = BlockTransaction::new(
+        sender.address,
+        receiver.address,
+        amount.parse().unwrap(),
+        chain.get_nonce(&sender.address),
+        chain.chain_id,
+    );
+    tx.sign(&SecretKey::from_slice(&hex::decode(SENDER_KEY).unwrap()).unwrap());
+    tx
+}
+
+#[tokio::test]
+async fn chain_height_and_block_queries() {
+    let (addr, chain) = start_server(vec![]).await;
+    let genesis_hash = chain.lock().unwrap().get_latest_block().block_hash.clone();
+
+    assert_eq!(call(addr, "chainHeight", json!([])).await["result"], 0);
+    assert_eq!(call(addr, "chainId", json!([])).await["result"], 1);
+
+    let by_number = call(addr, "getBlockByNumber", json!([0])).await;
+    assert_eq!(by_number["result"]["block_hash"], genesis_hash);
+
+    let by_hash = call(addr, "getBlockByHash", json!([genesis_hash])).await;
+    assert_eq!(by_hash["result"]["block_number"], 0);
+
+    assert_eq!(
+        call(addr, "getBlockByNumber", json!([5])).await["result"],
+        Value::Null
+    );
+    assert_eq!(
+        call(addr, "getBalance", json!(["unknown"])).await["result"],
+        Value::Null
+    );
+}
+
+#[tokio::test]
+async fn send_raw_transaction_is_pooled_mined_and_queryable() {
+    let sender = Account::from_secret_key(SENDER_KEY, Default::default()).unwrap();
+    let receiver = Account::from_secret_key(RECEIVER_KEY, Default::default()).unwrap();
+    let (addr, chain) = start_server(vec![
+        GenesisAllocation {
+            address: sender.address,
+            balance: "100".parse().unwrap(),
+        },
+        GenesisAllocation {
+            address: receiver.address,
+            balance: Default::default(),
+        },
+    ])
+    .await;
+
+    let tx = signed_transfer(&chain, "40.5");
+    let raw = hex::encode(encoding::encode(&tx));
+
+    let sent = call(addr, "sendRawTransaction", json!([raw])).await;
+    let tx_hash = sent["result"].as_str().unwrap().to_string();
+    assert_eq!(tx_hash, tx.compute_hash());
+
+    // The transaction waits in the mempool until a block is mined
+    assert_eq!(call(addr, "chainHeight", json!([])).await["result"], 0);
+    assert_eq!(
+        call(addr, "getNonce", json!([sender.address])).await["result"],
+        1
+    );
+    let duplicate = call(addr, "sendRawTransaction", json!([raw])).await;
+    assert_eq!(duplicate["error"]["code"], TRANSACTION_REJECTED);
+
+    chain.lock().unwrap().mine_pending_block(100).unwrap();
+    assert!(chain.lock().unwrap().mempool.is_empty());
+
+    assert_eq!(call(addr, "chainHeight", json!([])).await["result"], 1);
+    assert_eq!(
+        call(addr, "getBalance", json!([sender.address])).await["result"],
+        "59.5"
+    );
+    assert_eq!(
+        call(addr, "getBalance", json!([receiver.address])).await["result"],
+        "40.5"
+    );
+
+    let found = call(addr, "getTransaction", json!([tx_hash])).await;
+    assert_eq!(found["result"]["blockNumber"], 1);
+    assert_eq!(found["result"]["transaction"]["amount"], "40.5");
+
+    // Replaying the same transaction must be rejected
+    let replay = call(addr, "sendRawTransaction", json!([raw])).await;
+    assert_eq!(replay["error"]["code"], TRANSACTION_REJECTED);
+}
+
+#[tokio::test]
+async fn errors_follow_json_rpc() {
+    let (addr, chain) = start_server(vec![]).await;
+
+    let unknown = call(addr, "noSuchMethod", json!([])).await;
+    assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+    let bad_params = call(addr, "getBlockByNumber", json!(["zero"])).await;
+    assert_eq!(bad_params["error"]["code"], INVALID_PARAMS);
+
+    // The sender has no account, so the transaction cannot be funded
+    let tx = signed_transfer(&chain, "1");
+    let raw = hex::encode(encoding::encode(&tx));
+    let rejected = call(addr, "sendRawTransaction", json!([raw])).await;
+    assert_eq!(rejected["error"]["code"], TRANSACTION_REJECTED);
+
+    let (status, body) = post(addr, "{not json").await;
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    let parse_error: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(parse_error["error"]["code"], -32700);
+}
+
+#[tokio::test]
+async fn oversized_request_line_is_rejected() {
+    let (addr, _) = start_server(vec![]).await;
+    // The whole limit without a line ending
+    let mut request = b"POST /".to_vec();
+    request.resize(MAX_LINE_SIZE, b'a');
+
+    let (status, body) = send_raw(addr, &request).await;
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    assert!(body.contains("line longer than"), "{}", body);
+}
+
+#[tokio::test]
+async fn oversized_header_line_is_rejected() {
+    let (addr, _) = start_server(vec![]).await;
+    let mut request = b"POST / HTTP/1.1\r\nX-Padding: ".to_vec();
+    request.resize(request.len() + MAX_LINE_SIZE - 11, b'a');
+
+    let (status, body) = send_raw(addr, &request).await;
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    assert!(body.contains("line longer than"), "{}", body);
+}
+
+#[tokio::test]
+async fn oversized_headers_are_rejected() {
+    let (addr, _) = start_server(vec![]).await;
+    // Lines of 1 KiB, each under the line limit, one more than the headers allow
+    let line = format!("X-Padding: {}\r\n", "a".repeat(1024 - 13));
+    assert_eq!(line.len(), 1024);
+    let mut request = "POST / HTTP/1.1\r\n".to_string();
+    request.push_str(&line.repeat(MAX_HEADERS_SIZE / 1024 + 1));
+
+    let (status, body) = send_raw(addr, request.as_bytes()).await;
+    assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    assert_eq!(body, "Request headers too large");
+}
+
+#[tokio::test]
+async fn slow_request_times_out() {
+    let chain: SharedChain = Arc::new(Mutex::new(
+        BharatChain::new(GenesisConfig::default()).unwrap(),
+    ));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = RpcServer::new(chain).with_request_timeout(Duration::from_millis(200));
+    tokio::spawn(server.serve(listener));
+
+    // Headers started but never finished
+    let request = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n";
+    let (status, _) = tokio::time::timeout(Duration::from_secs(10), send_raw(addr, request))
+        .await
+        .expect("server answered");
+    assert_eq!(status, "HTTP/1.1 408 Request Timeout");
+
+    // The body never arrives
+    let request = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}";
+    let (status, _) = send_raw(addr, request).await;
+    assert_eq!(status, "HTTP/1.1 408 Request Timeout");
+}
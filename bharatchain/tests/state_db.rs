@@ -0,0 +1,176 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::state_db::StateDB;
+
+fn address(byte: u8) -> Address {
+    Address::from_hash([byte; 32])
+}
+
+fn account(byte: u8, units: u64) -> Account {
+    Account {
+        balance: Amount::from_base_units(units),
+        ..Account::new(address(byte))
+    }
+}
+
+fn balance(state: &StateDB, byte: u8) -> Option<u64> {
+    state
+        .get(&address(byte))
+        .map(|account| account.balance.base_units())
+}
+
+fn credit(state: &mut StateDB, byte: u8, units: u64) {
+    state
+        .get_mut(&address(byte))
+        .unwrap()
+        .credit(Amount::from_base_units(units))
+        .unwrap();
+}
+
+// Accounts 1 and 2 holding 100 and 200 units
+fn sample_state() -> StateDB {
+    vec![account(1, 100), account(2, 200)].into_iter().collect()
+}
+
+#[test]
+fn get_create_and_iterate() {
+    let mut state = sample_state();
+    assert_eq!(state.len(), 2);
+    assert!(state.contains(&address(1)));
+    assert_eq!(balance(&state, 2), Some(200));
+    assert_eq!(balance(&state, 3), None);
+    assert_eq!(state.nonce(&address(3)), 0);
+
+    assert!(state.create(address(1)).is_err());
+    state.create(address(3)).unwrap().nonce = 5;
+    assert_eq!(state.nonce(&address(3)), 5);
+    state.get_or_create(address(3)).nonce += 1;
+    assert_eq!(state.nonce(&address(3)), 6);
+
+    let mut addresses: Vec<Address> = state.iter().map(|account| account.address).collect();
+    addresses.sort();
+    assert_eq!(addresses, vec![address(1), address(2), address(3)]);
+}
+
+#[test]
+fn revert_undoes_every_kind_of_change() {
+    let mut state = sample_state();
+    let snapshot = state.snapshot();
+
+    credit(&mut state, 1, 5);
+    state.get_or_create(address(3));
+    state.get_or_create(address(2)).nonce = 9;
+    state.insert(account(4, 40));
+    state.insert(account(1, 1));
+    state.remove(&address(2));
+    assert_eq!(state.len(), 3);
+
+    state.revert(snapshot);
+    assert_eq!(state.len(), 2);
+    assert_eq!(balance(&state, 1), Some(100));
+    assert_eq!(balance(&state, 2), Some(200));
+    assert_eq!(state.nonce(&address(2)), 0);
+    assert!(!state.contains(&address(3)));
+    assert!(!state.contains(&address(4)));
+}
+
+#[test]
+fn inner_commit_is_still_undone_by_an_outer_revert() {
+    let mut state = sample_state();
+    let outer = state.snapshot();
+    credit(&mut state, 1, 1);
+
+    let inner = state.snapshot();
+    credit(&mut state, 2, 2);
+    state.get_or_create(address(3));
+    state.commit(inner);
+    assert_eq!(balance(&state, 2), Some(202));
+
+    // A reverted inner snapshot only undoes its own changes
+    let inner = state.snapshot();
+    credit(&mut state, 1, 10);
+    state.revert(inner);
+    assert_eq!(balance(&state, 1), Some(101));
+
+    state.revert(outer);
+    assert_eq!(balance(&state, 1), Some(100));
+    assert_eq!(balance(&state, 2), Some(200));
+    assert!(!state.contains(&address(3)));
+}
+
+#[test]
+fn changes_without_an_open_snapshot_are_not_journaled() {
+    let mut state = sample_state();
+    let snapshot = state.snapshot();
+    state.commit(snapshot);
+
+    credit(&mut state, 1, 1);
+    let snapshot = state.snapshot();
+    assert!(state.changes_since(snapshot).is_empty());
+    state.revert(snapshot);
+    assert_eq!(balance(&state, 1), Some(101));
+}
+
+#[test]
+fn changes_since_reports_the_first_prior_value_once() {
+    let mut state = sample_state();
+    let snapshot = state.snapshot();
+    credit(&mut state, 1, 5);
+    credit(&mut state, 1, 7);
+    state.get_or_create(address(3));
+    state.get_or_create(address(3)).nonce = 1;
+
+    let inner = state.snapshot();
+    credit(&mut state, 1, 11);
+    assert_eq!(
+        state.changes_since(inner),
+        vec![(address(1), Some(account(1, 112)))]
+    );
+    state.commit(inner);
+
+    let mut changes = state.changes_since(snapshot);
+    changes.sort_by_key(|(address, _)| *address);
+    assert_eq!(
+        changes,
+        vec![(address(1), Some(account(1, 100))), (address(3), None)]
+    );
+    state.commit(snapshot);
+}
+
+#[test]
+fn restore_undoes_committed_changes() {
+    let mut state = sample_state();
+    let snapshot = state.snapshot();
+    credit(&mut state, 1, 5);
+    state.get_or_create(address(3)).nonce = 1;
+    state.remove(&address(2));
+    let changes = state.changes_since(snapshot);
+    state.commit(snapshot);
+    assert_eq!(state.len(), 2);
+
+    state.restore(changes);
+    assert_eq!(state.len(), 2);
+    assert_eq!(balance(&state, 1), Some(100));
+    assert_eq!(balance(&state, 2), Some(200));
+    assert!(!state.contains(&address(3)));
+}
+
+#[test]
+#[should_panic(expected = "snapshot was already closed")]
+fn reverting_a_closed_snapshot_panics() {
+    let mut state = sample_state();
+    let snapshot = state.snapshot();
+    state.commit(snapshot);
+    state.revert(snapshot);
+}
+
+#[test]
+#[should_panic(expected = "snapshot was already closed")]
+fn committing_a_snapshot_closed_by_an_outer_revert_panics() {
+    let mut state = sample_state();
+    let outer = state.snapshot();
+    let inner = state.snapshot();
+    state.revert(outer);
+    state.commit(inner);
+}
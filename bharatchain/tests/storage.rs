@@ -0,0 +1,187 @@
+use bharatchain::chain_core::block::DataBlock;
+use bharatchain::chain_core::storage::BlockStore;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// Fresh store directory for one test
+fn store_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("bharatchain-store-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+// Genesis followed by `count - 1` linked blocks
+fn blocks(count: u64) -> Vec<DataBlock> {
+    let mut blocks = vec![DataBlock::new(0, "0".repeat(64), vec![])];
+    for number in 1..count {
+        let previous_hash = blocks.last().unwrap().block_hash.clone();
+        blocks.push(DataBlock::new(number, previous_hash, vec![]));
+    }
+    blocks
+}
+
+fn write_store(dir: &Path, blocks: &[DataBlock]) {
+    let mut store = BlockStore::open(dir).unwrap();
+    for block in blocks {
+        store.append(block).unwrap();
+    }
+}
+
+fn append_bytes(dir: &Path, bytes: &[u8]) {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(dir.join("blocks.log"))
+        .unwrap();
+    file.write_all(bytes).unwrap();
+}
+
+fn log_len(dir: &Path) -> u64 {
+    fs::metadata(dir.join("blocks.log")).unwrap().len()
+}
+
+fn hashes(blocks: &[DataBlock]) -> Vec<String> {
+    blocks
+        .iter()
+        .map(|block| block.block_hash.clone())
+        .collect()
+}
+
+#[test]
+fn reopening_replays_the_log() {
+    let dir = store_dir("replay");
+    let blocks = blocks(3);
+    write_store(&dir, &blocks);
+
+    let mut store = BlockStore::open(&dir).unwrap();
+    assert_eq!(store.len(), 3);
+    assert_eq!(hashes(&store.load_all().unwrap()), hashes(&blocks));
+    let second = store.get_by_hash(&blocks[1].block_hash).unwrap().unwrap();
+    assert_eq!(second.header.block_number, 1);
+    let third = store.get(2).unwrap().unwrap();
+    assert_eq!(third.block_hash, blocks[2].block_hash);
+    assert!(store.get(3).unwrap().is_none());
+
+    // Appends continue after the replayed records
+    let next = DataBlock::new(3, blocks[2].block_hash.clone(), vec![]);
+    store.append(&next).unwrap();
+    drop(store);
+    assert_eq!(BlockStore::open(&dir).unwrap().len(), 4);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn log_keeps_side_branches_in_connection_order() {
+    let dir = store_dir("branches");
+    let blocks = blocks(3);
+    write_store(&dir, &blocks);
+
+    // A second block 1, as connected by a reorganization, follows its parent
+    let mut fork = DataBlock::new(1, blocks[0].block_hash.clone(), vec![]);
+    fork.header.timestamp += 1;
+    fork.block_hash = fork.calculate_hash();
+    let mut store = BlockStore::open(&dir).unwrap();
+    store.append(&fork).unwrap();
+    assert_eq!(store.get(3).unwrap().unwrap().block_hash, fork.block_hash);
+
+    // A block whose parent was never stored, or one stored twice, is rejected
+    let orphan = DataBlock::new(5, "ab".repeat(32), vec![]);
+    assert_eq!(
+        store.append(&orphan).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+    assert_eq!(
+        store.append(&blocks[1]).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn torn_tail_is_truncated() {
+    let dir = store_dir("torn");
+    let blocks = blocks(3);
+    write_store(&dir, &blocks);
+    let intact_len = log_len(&dir);
+
+    // A record header promising more bytes than were written
+    append_bytes(&dir, &[0, 0, 1, 0, 0xde, 0xad, 0xbe, 0xef, 1, 2, 3]);
+    let store = BlockStore::open(&dir).unwrap();
+    assert_eq!(hashes(&store.load_all().unwrap()), hashes(&blocks));
+    drop(store);
+    assert_eq!(log_len(&dir), intact_len);
+
+    // A header cut short
+    append_bytes(&dir, &[0, 0]);
+    assert_eq!(BlockStore::open(&dir).unwrap().len(), 3);
+    assert_eq!(log_len(&dir), intact_len);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checksum_failure_truncates_from_that_record() {
+    let dir = store_dir("checksum");
+    let blocks = blocks(3);
+    write_store(&dir, &blocks[..2]);
+    let two_records = log_len(&dir);
+    write_store(&dir, &blocks[2..]);
+
+    // Flip the last payload byte of the final record
+    let path = dir.join("blocks.log");
+    let mut bytes = fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 0xff;
+    fs::write(&path, bytes).unwrap();
+
+    let store = BlockStore::open(&dir).unwrap();
+    assert_eq!(hashes(&store.load_all().unwrap()), hashes(&blocks[..2]));
+    drop(store);
+    assert_eq!(log_len(&dir), two_records);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn damage_before_the_last_record_is_an_error_and_keeps_the_log() {
+    let dir = store_dir("damaged");
+    let blocks = blocks(3);
+    write_store(&dir, &blocks[..1]);
+    let first_record = log_len(&dir);
+    write_store(&dir, &blocks[1..]);
+    let full_len = log_len(&dir);
+
+    // Flip a payload byte of the middle record; the last one is still intact
+    let path = dir.join("blocks.log");
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[first_record as usize + 20] ^= 0xff;
+    fs::write(&path, bytes).unwrap();
+
+    let err = BlockStore::open(&dir).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(log_len(&dir), full_len);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn undecodable_record_is_an_error_and_keeps_the_log() {
+    let dir = store_dir("undecodable");
+    write_store(&dir, &blocks(2));
+
+    // Intact record, checksum included, in an encoding this version cannot read
+    let payload = [0xffu8; 16];
+    let mut record = (payload.len() as u32).to_be_bytes().to_vec();
+    record.extend_from_slice(&crc32fast::hash(&payload).to_be_bytes());
+    record.extend_from_slice(&payload);
+    append_bytes(&dir, &record);
+    let full_len = log_len(&dir);
+
+    let err = BlockStore::open(&dir).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(log_len(&dir), full_len);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,328 @@
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::block::{BlockHeader, DataBlock};
+use bharatchain::chain_core::chain::{BharatChain, SharedChain, MAX_FUTURE_BLOCK_TIME};
+use bharatchain::chain_core::genesis::GenesisConfig;
+use bharatchain::chain_core::helper::get_current_timestamp;
+use bharatchain::chain_core::miner::Miner;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use bharatchain::p2p::message::{
+    read_message, write_message, InventoryItem, Message, MAX_HEADERS, PROTOCOL_VERSION,
+};
+use bharatchain::p2p::node::{NodeConfig, P2pNode};
+use bharatchain::p2p::sync::{BlockSync, SyncActions, SyncConfig};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+
+// Start a node listening on a loopback port
+async fn start_node(config: NodeConfig) -> (SocketAddr, SharedChain, P2pNode) {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    chain.miner = Miner::new(1);
+    let chain: SharedChain = Arc::new(Mutex::new(chain));
+
+    let node = P2pNode::new(chain.clone(), config);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(node.clone().serve(listener));
+    (addr, chain, node)
+}
+
+// Node that gives up on a peer's blocks quickly
+fn impatient() -> NodeConfig {
+    NodeConfig {
+        sync: SyncConfig {
+            block_timeout: Duration::from_millis(300),
+            ..SyncConfig::default()
+        },
+        ..NodeConfig::default()
+    }
+}
+
+// Start a node that has mined `blocks` blocks past genesis
+async fn start_miner(blocks: u64) -> (SocketAddr, SharedChain) {
+    let (addr, chain, _) = start_node(NodeConfig::default()).await;
+    for _ in 0..blocks {
+        chain.lock().unwrap().mine_pending_block(100).unwrap();
+    }
+    (addr, chain)
+}
+
+fn tip(chain: &SharedChain) -> String {
+    chain.lock().unwrap().get_latest_block().block_hash.clone()
+}
+
+fn headers(chain: &SharedChain) -> Vec<BlockHeader> {
+    let chain = chain.lock().unwrap();
+    (1..=chain.height())
+        .map(|n| chain.get_block_by_number(n).unwrap().header.clone())
+        .collect()
+}
+
+// Poll `condition` until it holds, failing the test after a few seconds
+async fn wait_until(what: &str, condition: impl Fn() -> bool) {
+    let started = Instant::now();
+    while !condition() {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "timed out waiting until {}",
+            what
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+// Connect to `addr` as a hand-driven peer claiming `height` blocks
+async fn fake_peer(addr: SocketAddr, genesis_hash: &str, height: u64) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let version = Message::Version {
+        version: PROTOCOL_VERSION,
+        chain_id: 1,
+        genesis_hash: genesis_hash.to_string(),
+        height,
+        nonce: 99,
+    };
+    write_message(&mut stream, &version).await.unwrap();
+    assert!(matches!(
+        read_message(&mut stream).await.unwrap(),
+        Message::Version { .. }
+    ));
+    assert!(matches!(
+        read_message(&mut stream).await.unwrap(),
+        Message::Verack
+    ));
+    write_message(&mut stream, &Message::Verack).await.unwrap();
+    stream
+}
+
+// Read messages until the node asks for something matching `wanted`
+async fn expect(stream: &mut TcpStream, wanted: fn(&Message) -> bool) -> Message {
+    loop {
+        let message = read_message(stream).await.unwrap();
+        if wanted(&message) {
+            return message;
+        }
+    }
+}
+
+// Read until the node closes the connection
+async fn expect_disconnect(stream: &mut TcpStream) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while read_message(stream).await.is_ok() {}
+    })
+    .await
+    .expect("the node should drop the peer");
+}
+
+#[tokio::test]
+async fn new_node_downloads_the_chain_from_several_peers() {
+    // Long enough to cross a retarget
+    let (a_addr, a) = start_miner(12).await;
+    let (b_addr, b, b_node) = start_node(NodeConfig::default()).await;
+    b_node.connect(a_addr).await.unwrap();
+    wait_until("b syncs from a", || tip(&b) == tip(&a)).await;
+
+    let (_, c, c_node) = start_node(NodeConfig::default()).await;
+    c_node.connect(a_addr).await.unwrap();
+    c_node.connect(b_addr).await.unwrap();
+    wait_until("c syncs", || tip(&c) == tip(&a) && !c_node.is_syncing()).await;
+
+    let c = c.lock().unwrap();
+    assert_eq!(c.height(), 12);
+    assert_eq!(c.total_work(), a.lock().unwrap().total_work());
+    assert!(c.is_valid());
+}
+
+#[tokio::test]
+async fn stalling_peer_is_dropped_and_its_blocks_fetched_elsewhere() {
+    let (a_addr, a) = start_miner(5).await;
+    let (addr, chain, node) = start_node(impatient()).await;
+    let genesis_hash = tip(&chain);
+
+    // The fake peer serves headers, so every block is asked of it first, but never sends them
+    let mut staller = fake_peer(addr, &genesis_hash, 5).await;
+    expect(&mut staller, |m| matches!(m, Message::GetHeaders { .. })).await;
+    write_message(&mut staller, &Message::Headers(headers(&a)))
+        .await
+        .unwrap();
+    match expect(&mut staller, |m| matches!(m, Message::GetData(_))).await {
+        Message::GetData(items) => assert_eq!(items.len(), 5),
+        _ => unreachable!(),
+    }
+    node.connect(a_addr).await.unwrap();
+
+    expect_disconnect(&mut staller).await;
+    wait_until("the node syncs from a", || tip(&chain) == tip(&a)).await;
+    assert!(!node.is_syncing());
+}
+
+#[tokio::test]
+async fn invalid_headers_disconnect_the_peer() {
+    let (_, a) = start_miner(3).await;
+    let (addr, chain, node) = start_node(NodeConfig::default()).await;
+    let genesis_hash = tip(&chain);
+
+    // Changing a header breaks the link from the next one
+    let mut headers = headers(&a);
+    headers[1].timestamp += 1;
+
+    let mut peer = fake_peer(addr, &genesis_hash, 3).await;
+    expect(&mut peer, |m| matches!(m, Message::GetHeaders { .. })).await;
+    write_message(&mut peer, &Message::Headers(headers))
+        .await
+        .unwrap();
+    expect_disconnect(&mut peer).await;
+
+    wait_until("the peer is forgotten", || node.peer_count() == 0).await;
+    assert!(!node.is_syncing());
+    assert_eq!(chain.lock().unwrap().height(), 0);
+}
+
+#[tokio::test]
+async fn block_not_matching_its_header_disconnects_the_peer() {
+    let (a_addr, a) = start_miner(3).await;
+    let (addr, chain, node) = start_node(NodeConfig::default()).await;
+    let genesis_hash = tip(&chain);
+
+    let mut peer = fake_peer(addr, &genesis_hash, 3).await;
+    expect(&mut peer, |m| matches!(m, Message::GetHeaders { .. })).await;
+    write_message(&mut peer, &Message::Headers(headers(&a)))
+        .await
+        .unwrap();
+    let Message::GetData(items) = expect(&mut peer, |m| matches!(m, Message::GetData(_))).await
+    else {
+        unreachable!()
+    };
+
+    // Answer with the requested block plus a transaction its header does not commit to
+    let InventoryItem::Block(hash) = &items[0] else {
+        panic!("expected a block request")
+    };
+    let mut block = a.lock().unwrap().get_block_by_hash(hash).unwrap().clone();
+    let minted =
+        BlockTransaction::coinbase(Address::from_hash([7; 32]), "50".parse().unwrap(), 1, 1);
+    block.body.transactions.insert(0, minted);
+    write_message(&mut peer, &Message::Block(Box::new(block)))
+        .await
+        .unwrap();
+    expect_disconnect(&mut peer).await;
+
+    // The headers were fine, so an honest peer can finish the download
+    node.connect(a_addr).await.unwrap();
+    wait_until("the node syncs from a", || tip(&chain) == tip(&a)).await;
+    assert!(chain.lock().unwrap().is_valid());
+}
+
+// `count` empty blocks past genesis, spaced by the target block time so the
+// target never changes
+fn empty_blocks(count: u64) -> Vec<DataBlock> {
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    (0..count)
+        .map(|_| {
+            let parent = chain.get_latest_block().clone();
+            let mut block = DataBlock::new(
+                parent.header.block_number + 1,
+                parent.block_hash.clone(),
+                vec![],
+            );
+            block.header.bits = chain.next_target();
+            block.header.timestamp =
+                parent.header.timestamp + chain.genesis.retarget.target_block_time;
+            block.set_state_root(parent.header.state_root.clone());
+            while !block.meets_target() {
+                block.header.nounce += 1;
+                block.block_hash = block.calculate_hash();
+            }
+            chain.submit_block(block.clone()).unwrap();
+            block
+        })
+        .collect()
+}
+
+fn asks_for_headers(actions: &SyncActions) -> bool {
+    actions
+        .send
+        .iter()
+        .any(|(_, message)| matches!(message, Message::GetHeaders { .. }))
+}
+
+#[test]
+fn header_queue_waits_for_blocks_before_fetching_more() {
+    let blocks = empty_blocks(MAX_HEADERS as u64 + 600);
+    let headers: Vec<BlockHeader> = blocks.iter().map(|block| block.header.clone()).collect();
+    let mut chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    let mut sync = BlockSync::new(SyncConfig {
+        max_blocks_in_flight: 1024,
+        max_queued_headers: MAX_HEADERS + 500,
+        ..SyncConfig::default()
+    });
+
+    let actions = sync.peer_connected(1, blocks.len() as u64, &chain);
+    assert!(asks_for_headers(&actions));
+
+    // A full batch, but another one would not fit yet
+    let actions = sync
+        .headers_received(1, headers[..MAX_HEADERS].to_vec(), &chain)
+        .unwrap();
+    assert!(!asks_for_headers(&actions));
+    assert_eq!(sync.pending_blocks(), MAX_HEADERS);
+
+    // Headers are asked for again once 500 are left
+    let resumed = blocks[..MAX_HEADERS]
+        .iter()
+        .position(|block| asks_for_headers(&sync.block_received(1, block.clone(), &mut chain)))
+        .expect("headers asked for again");
+    assert_eq!(resumed + 1, MAX_HEADERS - 500);
+    assert_eq!(sync.pending_blocks(), 500);
+
+    let actions = sync
+        .headers_received(1, headers[MAX_HEADERS..].to_vec(), &chain)
+        .unwrap();
+    assert!(!asks_for_headers(&actions));
+    assert_eq!(sync.pending_blocks(), 1100);
+}
+
+// Re-mine a header after changing it
+fn remine(header: &mut BlockHeader) {
+    while !header.meets_target(&header.hash()) {
+        header.nounce += 1;
+    }
+}
+
+// Offer `headers` to a new sync that asked peer 1 for them
+fn offer_headers(headers: Vec<BlockHeader>) -> Result<SyncActions, String> {
+    let chain = BharatChain::new(GenesisConfig::default()).unwrap();
+    let mut sync = BlockSync::new(SyncConfig::default());
+    sync.peer_connected(1, headers.len() as u64, &chain);
+    sync.headers_received(1, headers, &chain)
+}
+
+#[test]
+fn headers_must_follow_the_median_time_past() {
+    let blocks = empty_blocks(12);
+    let mut headers: Vec<BlockHeader> = blocks.iter().map(|block| block.header.clone()).collect();
+    assert!(offer_headers(headers.clone()).is_ok());
+
+    // The median of the 11 headers before the last is the sixth of them
+    let median_time = headers[5].timestamp;
+    headers[11].timestamp = median_time;
+    remine(&mut headers[11]);
+    let err = offer_headers(headers.clone()).unwrap_err();
+    assert!(err.contains("median time"), "{}", err);
+
+    headers[11].timestamp = median_time + 1;
+    remine(&mut headers[11]);
+    assert!(offer_headers(headers).is_ok());
+}
+
+#[test]
+fn headers_too_far_in_the_future_are_rejected() {
+    let mut headers: Vec<BlockHeader> = empty_blocks(2)
+        .iter()
+        .map(|block| block.header.clone())
+        .collect();
+    headers[1].timestamp = get_current_timestamp() + MAX_FUTURE_BLOCK_TIME + 60;
+    remine(&mut headers[1]);
+    let err = offer_headers(headers).unwrap_err();
+    assert!(err.contains("future"), "{}", err);
+}
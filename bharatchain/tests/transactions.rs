@@ -0,0 +1,280 @@
+use bharatchain::chain_core::account::Account;
+use bharatchain::chain_core::address::Address;
+use bharatchain::chain_core::amount::Amount;
+use bharatchain::chain_core::block::DataBlock;
+use bharatchain::chain_core::chain::{BharatChain, DEFAULT_CHAIN_ID};
+use bharatchain::chain_core::genesis::{GenesisAllocation, GenesisConfig};
+use bharatchain::chain_core::reward::RewardSchedule;
+use bharatchain::chain_core::state_db::StateDB;
+use bharatchain::chain_core::transaction::BlockTransaction;
+use secp256k1::SecretKey;
+
+const SENDER_KEY: &str = "a1ce000000000000000000000000000000000000000000000000000000000001";
+const FRESH_KEY: &str = "f2e5000000000000000000000000000000000000000000000000000000000003";
+
+fn address(key: &str) -> Address {
+    Account::from_secret_key(key, Amount::ZERO).unwrap().address
+}
+
+fn coins(amount: &str) -> Amount {
+    amount.parse().unwrap()
+}
+
+fn secret_key(key: &str) -> SecretKey {
+    SecretKey::from_slice(&hex::decode(key).unwrap()).unwrap()
+}
+
+// Only the sender is funded
+fn accounts() -> StateDB {
+    [Account::from_secret_key(SENDER_KEY, coins("100")).unwrap()]
+        .into_iter()
+        .collect()
+}
+
+// Accounts of the sender, funded, and of the receiver
+fn funded_pair() -> StateDB {
+    [
+        Account::from_secret_key(SENDER_KEY, coins("100")).unwrap(),
+        Account::from_secret_key(FRESH_KEY, Amount::ZERO).unwrap(),
+    ]
+    .into_iter()
+    .collect()
+}
+
+// Low difficulty chain funding only the sender
+fn new_chain() -> BharatChain {
+    let genesis = GenesisConfig {
+        allocations: vec![GenesisAllocation {
+            address: address(SENDER_KEY),
+            balance: coins("100"),
+        }],
+        ..Default::default()
+    };
+    BharatChain::new(genesis).unwrap()
+}
+
+// Transfer from the sender with an explicit nonce and chain id
+fn transfer_with(nonce: u64, chain_id: u64, amount: &str) -> BlockTransaction {
+    let mut tx = BlockTransaction::new(
+        address(SENDER_KEY),
+        address(FRESH_KEY),
+        coins(amount),
+        nonce,
+        chain_id,
+    );
+    tx.sign(&secret_key(SENDER_KEY));
+    tx
+}
+
+// First transfer from the sender on the default chain
+fn transfer(amount: &str) -> BlockTransaction {
+    transfer_with(0, DEFAULT_CHAIN_ID, amount)
+}
+
+fn assert_replay_rejected(txns: Vec<BlockTransaction>, reason: &str) {
+    let mut chain = new_chain();
+    let err = chain.add_block(txns).unwrap_err();
+    assert!(err.contains(reason), "{}", err);
+    assert_eq!(chain.chain.len(), 1);
+    assert_eq!(chain.get_nonce(&address(SENDER_KEY)), 0);
+}
+
+#[test]
+fn signed_transfer_verifies() {
+    let tx = transfer("1");
+    assert_eq!(tx.verify_signature(), Ok(()));
+    assert!(tx.is_valid(&accounts()));
+}
+
+#[test]
+fn tampered_fields_fail_verification() {
+    let signed = transfer("1");
+    let tampers: [fn(&mut BlockTransaction); 7] = [
+        |tx| tx.id.push('x'),
+        |tx| tx.chain_id += 1,
+        |tx| tx.nonce += 1,
+        |tx| tx.receiver = address(SENDER_KEY),
+        |tx| tx.amount = coins("2"),
+        |tx| tx.timestamp += 1,
+        |tx| {
+            let mut signature = hex::decode(&tx.signature).unwrap();
+            signature[63] ^= 1;
+            tx.signature = hex::encode(signature);
+        },
+    ];
+    for (i, tamper) in tampers.iter().enumerate() {
+        let mut tx = signed.clone();
+        tamper(&mut tx);
+        assert_eq!(
+            tx.verify_signature(),
+            Err("Signature verification failed".to_string()),
+            "tamper {}",
+            i
+        );
+        assert!(!tx.is_valid(&accounts()));
+    }
+}
+
+#[test]
+fn signature_from_a_mismatched_key_is_rejected() {
+    // Signed by another key, which is attached as the public key
+    let mut tx = transfer("1");
+    tx.sign(&secret_key(FRESH_KEY));
+    assert_eq!(
+        tx.verify_signature(),
+        Err("Public key does not match sender address".to_string())
+    );
+    assert!(!tx.is_valid(&accounts()));
+
+    // Signed by another key, presented under the sender's public key
+    tx.public_key = transfer("1").public_key;
+    assert_eq!(
+        tx.verify_signature(),
+        Err("Signature verification failed".to_string())
+    );
+    assert!(!tx.is_valid(&accounts()));
+
+    // A valid signature claiming another sender
+    let mut tx = transfer("1");
+    tx.sender = address(FRESH_KEY);
+    assert_eq!(
+        tx.verify_signature(),
+        Err("Public key does not match sender address".to_string())
+    );
+}
+
+#[test]
+fn unsigned_or_malformed_signatures_are_rejected() {
+    let unsigned = BlockTransaction::new(
+        address(SENDER_KEY),
+        address(FRESH_KEY),
+        coins("1"),
+        0,
+        DEFAULT_CHAIN_ID,
+    );
+    assert!(unsigned.verify_signature().is_err());
+    assert!(!unsigned.is_valid(&accounts()));
+
+    let signed = transfer("1");
+    let mut tx = signed.clone();
+    tx.signature = "zz".to_string();
+    assert!(tx.verify_signature().is_err());
+
+    let mut tx = signed;
+    tx.signature.truncate(64);
+    assert!(tx.verify_signature().is_err());
+}
+
+#[test]
+fn transfer_beyond_the_balance_is_invalid() {
+    assert!(!transfer("100.5").is_valid(&accounts()));
+    assert!(transfer("100").is_valid(&accounts()));
+}
+
+#[test]
+fn block_with_the_right_nonces_is_accepted() {
+    let mut chain = new_chain();
+    let txns = vec![
+        transfer_with(0, chain.chain_id, "1"),
+        transfer_with(1, chain.chain_id, "2"),
+    ];
+    chain.add_block(txns).unwrap();
+    assert_eq!(chain.get_nonce(&address(SENDER_KEY)), 2);
+    assert_eq!(chain.get_balance(&address(FRESH_KEY)), Some(coins("3")));
+    assert!(chain.is_valid());
+}
+
+#[test]
+fn wrong_chain_id_is_rejected() {
+    assert_replay_rejected(
+        vec![transfer_with(0, DEFAULT_CHAIN_ID + 1, "1")],
+        "is for chain",
+    );
+}
+
+#[test]
+fn stale_nonce_is_rejected() {
+    let mut chain = new_chain();
+    chain.add_block(vec![transfer("1")]).unwrap();
+
+    // Same nonce, different transaction
+    let err = chain.add_block(vec![transfer("2")]).unwrap_err();
+    assert!(err.contains("reuses nonce 0"), "{}", err);
+    assert_eq!(chain.chain.len(), 2);
+}
+
+#[test]
+fn duplicate_nonce_in_one_block_is_rejected() {
+    assert_replay_rejected(vec![transfer("1"), transfer("2")], "reuses nonce 0");
+}
+
+#[test]
+fn out_of_order_nonce_is_rejected() {
+    let gap = vec![transfer_with(1, DEFAULT_CHAIN_ID, "1")];
+    assert_replay_rejected(gap, "out-of-order nonce 1");
+
+    let swapped = vec![
+        transfer_with(1, DEFAULT_CHAIN_ID, "1"),
+        transfer_with(0, DEFAULT_CHAIN_ID, "2"),
+    ];
+    assert_replay_rejected(swapped, "out-of-order nonce 1");
+}
+
+// Blocks applied without going through the chain, e.g. received from a peer,
+// get the same protection
+#[test]
+fn applying_a_block_checks_nonces_and_chain_id() {
+    let apply = |txns: Vec<BlockTransaction>| {
+        let mut accounts = funded_pair();
+        let applied = DataBlock::new(1, String::new(), txns)
+            .apply_transactions(&mut accounts, &RewardSchedule::default(), DEFAULT_CHAIN_ID)
+            .is_ok();
+        (applied, accounts.nonce(&address(SENDER_KEY)))
+    };
+
+    assert_eq!(apply(vec![transfer("1")]), (true, 1));
+    assert_eq!(apply(vec![transfer("1"), transfer("2")]), (false, 0));
+    assert_eq!(
+        apply(vec![transfer_with(1, DEFAULT_CHAIN_ID, "1")]),
+        (false, 0)
+    );
+    assert_eq!(
+        apply(vec![transfer_with(0, DEFAULT_CHAIN_ID + 1, "1")]),
+        (false, 0)
+    );
+
+    let mut accounts = funded_pair();
+    accounts.get_mut(&address(SENDER_KEY)).unwrap().nonce = 3;
+    assert_eq!(
+        transfer("1").execute(&mut accounts),
+        Err(format!(
+            "Transaction {} has nonce 0, expected 3",
+            transfer("1").id
+        ))
+    );
+    assert_eq!(
+        accounts.get(&address(SENDER_KEY)).unwrap().balance,
+        coins("100")
+    );
+}
+
+#[test]
+fn sending_to_an_unseen_address_creates_its_account() {
+    let mut chain = new_chain();
+    let fresh = address(FRESH_KEY);
+    assert_eq!(chain.get_balance(&fresh), None);
+
+    chain.submit_transaction(transfer("10")).unwrap();
+    chain.mine_pending_block(10).unwrap();
+    assert_eq!(chain.get_balance(&fresh), Some(coins("10")));
+    assert_eq!(chain.get_balance(&address(SENDER_KEY)), Some(coins("90")));
+
+    // The new account can spend what it received
+    let mut tx = BlockTransaction::new(fresh, address(SENDER_KEY), coins("4"), 0, chain.chain_id);
+    tx.sign(&secret_key(FRESH_KEY));
+    chain.submit_transaction(tx).unwrap();
+    chain.mine_pending_block(10).unwrap();
+    assert_eq!(chain.get_balance(&fresh), Some(coins("6")));
+    assert_eq!(chain.get_nonce(&fresh), 1);
+    assert!(chain.is_valid());
+}